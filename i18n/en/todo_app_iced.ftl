@@ -12,6 +12,7 @@ describe-task-placeholder = Describe your task...
 filter-all = All
 filter-active = Active
 filter-completed = Done
+filter-trash = Trash
 
 # Task counter
 tasks-left = { $count ->
@@ -24,4 +25,173 @@ tasks-left = { $count ->
 empty-no-tasks = You have not created a task yet...
 empty-all-done = All your tasks are done! :D
 empty-no-completed = You have not completed a task yet...
+empty-trash = The trash is empty
 
+
+
+# Legacy import
+legacy-imported = { $count ->
+    [1] Imported 1 task from todos.json
+   *[other] Imported { $count } tasks from todos.json
+}
+
+# Data location
+data-location = Saved at { $path }
+
+# Recovery
+recovery-title = Your tasks could not be loaded
+recovery-file = The data file could not be opened.
+recovery-format = The data file is damaged or in a format this version does not understand.
+recovery-unsupported-version = The data was written by a newer version of the app (schema { $found }, this version supports up to { $supported }).
+recovery-retry = Try again
+recovery-read-only = Open read-only
+recovery-start-fresh = Start fresh
+recovery-backup-hint = Starting fresh keeps the current data as a timestamped backup. Nothing is saved until you pick an option.
+recovery-reset-failed = The backup could not be made, so nothing was changed.
+read-only-notice = Read-only: changes will not be saved
+backed-up = Previous data backed up to { $path }
+
+# Save status
+status-saving = Saving...
+status-pending = Unsaved changes
+status-saved = Saved at { $time }
+status-failed = { $attempts ->
+    [1] Save failed, retrying...
+   *[other] Save failed { $attempts } times, retrying...
+}
+save-failure-banner = Your changes are not being saved.
+save-retry-now = Retry now
+save-failure-closing = Your changes could not be saved, so the window was kept open.
+save-close-anyway = Close without saving
+
+# Instance lock
+read-only-already-running = Read-only: another window of the app is open, changes will not be saved
+recovery-already-running = Another window of the app is using this data. Close it to recover the data from here.
+
+# Encryption
+unlock-title = Your tasks are encrypted
+unlock-placeholder = Passphrase
+unlock-button = Unlock
+unlock-working = Unlocking...
+unlock-wrong = That passphrase is not right. Try again.
+recovery-locked = The data is encrypted and could not be unlocked.
+encryption-button = Encrypt
+encryption-button-encrypted = Encrypted
+encryption-title-encrypt = Encrypt your tasks with a passphrase
+encryption-title-change = Change the passphrase
+passphrase-new = New passphrase
+passphrase-confirm = Repeat the passphrase
+encryption-encrypt = Encrypt
+encryption-change = Change passphrase
+encryption-remove = Remove encryption
+encryption-cancel = Cancel
+encryption-hint = The passphrase cannot be recovered. If you forget it, your tasks are lost.
+passphrase-empty = Enter a passphrase.
+passphrase-mismatch = The passphrases do not match.
+passphrase-failed = The data could not be re-encrypted, so nothing was changed.
+
+# Trash
+task-restore = Restore
+trash-retention = Delete trashed tasks after
+trash-retention-days = { $days ->
+    [1] 1 day
+   *[other] { $days } days
+}
+trash-keep-forever = Never
+trash-empty = Empty trash
+
+# History
+task-history = History
+history-title = History
+history-close = Close
+history-loading = Loading the history...
+history-empty = No changes recorded yet.
+history-failed = The history could not be loaded.
+history-created = Created as "{ $description }"
+history-renamed = Renamed from "{ $from }" to "{ $to }"
+history-completed = Marked as done
+history-uncompleted = Marked as not done
+history-deleted = Moved to the trash
+history-restored = Restored from the trash
+history-revert = Revert to this
+
+# Backups
+backup-button = Backups
+backup-title = Backups
+backup-interval = { $hours ->
+    [1] Back up every hour
+   *[other] Back up every { $hours } hours
+}
+backup-generations = Keep { $count } backups
+backup-now = Back up now
+backup-close = Close
+backup-loading = Looking for backups...
+backup-none = No backups yet.
+backup-task-count = { $count ->
+    [1] 1 task
+   *[other] { $count } tasks
+}
+backup-unreadable = Unreadable
+backup-restore = Restore
+backup-hint = Restoring replaces your tasks with the backup. They are backed up first, so a restore can be undone.
+backup-failed = The backup could not be created.
+backup-list-failed = The backups could not be listed.
+backup-restore-failed = That backup could not be restored, so nothing was changed.
+
+# Maintenance
+maintenance-button = Check and repair
+maintenance-running = Checking the database...
+maintenance-healthy = No problems found. The database was compacted and its indexes rebuilt.
+maintenance-repaired = The database was damaged and has been rebuilt. { $tasks ->
+    [1] 1 task was recovered
+   *[other] { $tasks } tasks were recovered
+}, { $unreadable ->
+    [0] and nothing was lost.
+    [1] 1 row could not be read.
+   *[other] { $unreadable } rows could not be read.
+}
+maintenance-report = Report saved to { $path }
+maintenance-failed = The check could not be finished.
+
+# Markdown
+copy-markdown = Copy as Markdown
+
+# Import and export
+interchange-button = Import / Export
+interchange-title = Import and export
+interchange-path-placeholder = File to import from or export to
+interchange-export = Export
+interchange-preview = Read file
+interchange-close = Close
+interchange-hint = JSON and CSV keep everything, the trash included. Files ending in .txt, .md, .ics or .org, and Taskwarrior exports, can be imported too.
+interchange-exported = Exported to { $path }
+interchange-mode-merge = Merge
+interchange-mode-replace = Replace
+interchange-merge-hint = Tasks already here take what the file says. Tasks missing from the file stay.
+interchange-replace-hint = The file becomes your list. Tasks missing from it are deleted for good.
+interchange-summary = { $added } new, { $updated } changed, { $unchanged } unchanged{ $removed ->
+    [0] {""}
+   *[other] , { $removed } deleted
+}
+interchange-import = Import
+interchange-cancel = Cancel
+interchange-no-file = Enter a file first.
+interchange-unknown-format = Use a file ending in .json, .csv, .txt, .md, .ics or .org.
+interchange-read-failed = The file could not be read.
+interchange-export-failed = The file could not be written.
+
+# Sync
+sync-button = Sync
+sync-syncing = Syncing...
+sync-synced = Synced at { $time }
+sync-failed = Sync failed
+sync-title = Sync with CalDAV
+sync-url-placeholder = Task list address, e.g. https://example.com/dav/calendars/me/tasks/
+sync-username-placeholder = User name
+sync-password-placeholder = Password
+sync-connect = Connect and sync
+sync-now = Sync now
+sync-disconnect = Disconnect
+sync-close = Close
+sync-no-url = Enter the task list's address first.
+sync-hint = Tasks are synced every five minutes while the app is open. Tasks moved to the trash are deleted from the server.
\ No newline at end of file
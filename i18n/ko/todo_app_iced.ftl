@@ -12,6 +12,7 @@ describe-task-placeholder = 할 일을 설명해주세요...
 filter-all = 전체
 filter-active = 진행 중
 filter-completed = 완료
+filter-trash = 휴지통
 
 # 작업 카운터
 tasks-left = { $count ->
@@ -24,4 +25,151 @@ tasks-left = { $count ->
 empty-no-tasks = 아직 작업을 생성하지 않았습니다...
 empty-all-done = 모든 작업을 완료했습니다! :D
 empty-no-completed = 아직 완료한 작업이 없습니다...
+empty-trash = 휴지통이 비어 있습니다
 
+
+
+# 이전 데이터 가져오기
+legacy-imported = todos.json에서 { $count }개 작업을 가져왔습니다
+
+# 데이터 위치
+data-location = 저장 위치: { $path }
+
+# 복구
+recovery-title = 작업을 불러오지 못했습니다
+recovery-file = 데이터 파일을 열 수 없습니다.
+recovery-format = 데이터 파일이 손상되었거나 이 버전이 이해할 수 없는 형식입니다.
+recovery-unsupported-version = 더 새로운 버전의 앱에서 저장한 데이터입니다 (스키마 { $found }, 이 버전은 { $supported }까지 지원).
+recovery-retry = 다시 시도
+recovery-read-only = 읽기 전용으로 열기
+recovery-start-fresh = 새로 시작
+recovery-backup-hint = 새로 시작하면 현재 데이터는 시간이 기록된 백업으로 보관됩니다. 옵션을 선택하기 전에는 아무것도 저장되지 않습니다.
+recovery-reset-failed = 백업을 만들 수 없어 아무것도 변경하지 않았습니다.
+read-only-notice = 읽기 전용: 변경 사항이 저장되지 않습니다
+backed-up = 이전 데이터를 { $path }에 백업했습니다
+
+# 저장 상태
+status-saving = 저장 중...
+status-pending = 저장되지 않은 변경 사항
+status-saved = { $time }에 저장됨
+status-failed = 저장 실패 ({ $attempts }회), 다시 시도하는 중...
+save-failure-banner = 변경 사항이 저장되지 않고 있습니다.
+save-retry-now = 지금 다시 시도
+save-failure-closing = 변경 사항을 저장하지 못해 창을 닫지 않았습니다.
+save-close-anyway = 저장하지 않고 닫기
+
+# 인스턴스 잠금
+read-only-already-running = 읽기 전용: 앱의 다른 창이 열려 있어 변경 사항이 저장되지 않습니다
+recovery-already-running = 앱의 다른 창이 이 데이터를 사용 중입니다. 여기서 복구하려면 그 창을 닫으세요.
+
+# 암호화
+unlock-title = 할 일이 암호화되어 있습니다
+unlock-placeholder = 암호
+unlock-button = 잠금 해제
+unlock-working = 잠금 해제 중...
+unlock-wrong = 암호가 올바르지 않습니다. 다시 시도하세요.
+recovery-locked = 데이터가 암호화되어 있어 잠금을 해제할 수 없습니다.
+encryption-button = 암호화
+encryption-button-encrypted = 암호화됨
+encryption-title-encrypt = 암호로 할 일 암호화
+encryption-title-change = 암호 변경
+passphrase-new = 새 암호
+passphrase-confirm = 암호 다시 입력
+encryption-encrypt = 암호화
+encryption-change = 암호 변경
+encryption-remove = 암호화 해제
+encryption-cancel = 취소
+encryption-hint = 암호는 복구할 수 없습니다. 잊어버리면 할 일을 잃게 됩니다.
+passphrase-empty = 암호를 입력하세요.
+passphrase-mismatch = 암호가 일치하지 않습니다.
+passphrase-failed = 데이터를 다시 암호화하지 못해 아무것도 변경되지 않았습니다.
+
+# 휴지통
+task-restore = 복원
+trash-retention = 휴지통의 할 일 영구 삭제
+trash-retention-days = { $days }일 후
+trash-keep-forever = 안 함
+trash-empty = 휴지통 비우기
+
+# 변경 기록
+task-history = 기록
+history-title = 변경 기록
+history-close = 닫기
+history-loading = 기록을 불러오는 중...
+history-empty = 아직 기록된 변경이 없습니다.
+history-failed = 기록을 불러오지 못했습니다.
+history-created = "{ $description }"(으)로 생성됨
+history-renamed = "{ $from }"에서 "{ $to }"(으)로 이름 변경
+history-completed = 완료로 표시함
+history-uncompleted = 미완료로 표시함
+history-deleted = 휴지통으로 이동함
+history-restored = 휴지통에서 복원함
+history-revert = 이 버전으로 되돌리기
+
+# 백업
+backup-button = 백업
+backup-title = 백업
+backup-interval = { $hours }시간마다 백업
+backup-generations = 백업 { $count }개 보관
+backup-now = 지금 백업
+backup-close = 닫기
+backup-loading = 백업을 찾는 중...
+backup-none = 아직 백업이 없습니다.
+backup-task-count = 작업 { $count }개
+backup-unreadable = 읽을 수 없음
+backup-restore = 복원
+backup-hint = 복원하면 현재 작업이 백업 내용으로 바뀝니다. 현재 작업은 먼저 백업되므로 되돌릴 수 있습니다.
+backup-failed = 백업을 만들지 못했습니다.
+backup-list-failed = 백업 목록을 불러오지 못했습니다.
+backup-restore-failed = 이 백업을 복원하지 못해 아무것도 변경되지 않았습니다.
+
+# 유지 관리
+maintenance-button = 검사 및 복구
+maintenance-running = 데이터베이스를 검사하는 중...
+maintenance-healthy = 문제가 없습니다. 데이터베이스를 압축하고 인덱스를 다시 만들었습니다.
+maintenance-repaired = 데이터베이스가 손상되어 다시 만들었습니다. 작업 { $tasks }개를 복구했고, 읽을 수 없는 행은 { $unreadable }개입니다.
+maintenance-report = 보고서 저장 위치: { $path }
+maintenance-failed = 검사를 끝내지 못했습니다.
+
+# 마크다운
+copy-markdown = 마크다운으로 복사
+
+# 가져오기 및 내보내기
+interchange-button = 가져오기 / 내보내기
+interchange-title = 가져오기 및 내보내기
+interchange-path-placeholder = 가져오거나 내보낼 파일
+interchange-export = 내보내기
+interchange-preview = 파일 읽기
+interchange-close = 닫기
+interchange-hint = JSON과 CSV는 휴지통을 포함해 모든 내용을 보존합니다. .txt, .md, .ics, .org 파일과 Taskwarrior 내보내기 파일도 가져올 수 있습니다.
+interchange-exported = 내보낸 위치: { $path }
+interchange-mode-merge = 병합
+interchange-mode-replace = 바꾸기
+interchange-merge-hint = 이미 있는 작업은 파일 내용으로 바뀌고, 파일에 없는 작업은 그대로 남습니다.
+interchange-replace-hint = 파일 내용이 목록 전체가 됩니다. 파일에 없는 작업은 완전히 삭제됩니다.
+interchange-summary = 새 작업 { $added }개, 변경 { $updated }개, 그대로 { $unchanged }개{ $removed ->
+    [0] {""}
+   *[other] , 삭제 { $removed }개
+}
+interchange-import = 가져오기
+interchange-cancel = 취소
+interchange-no-file = 먼저 파일을 입력하세요.
+interchange-unknown-format = .json, .csv, .txt, .md, .ics, .org로 끝나는 파일을 사용하세요.
+interchange-read-failed = 파일을 읽지 못했습니다.
+interchange-export-failed = 파일을 쓰지 못했습니다.
+
+# 동기화
+sync-button = 동기화
+sync-syncing = 동기화 중...
+sync-synced = { $time }에 동기화됨
+sync-failed = 동기화 실패
+sync-title = CalDAV 동기화
+sync-url-placeholder = 작업 목록 주소 (예: https://example.com/dav/calendars/me/tasks/)
+sync-username-placeholder = 사용자 이름
+sync-password-placeholder = 비밀번호
+sync-connect = 연결하고 동기화
+sync-now = 지금 동기화
+sync-disconnect = 연결 해제
+sync-close = 닫기
+sync-no-url = 먼저 작업 목록 주소를 입력하세요.
+sync-hint = 앱이 열려 있는 동안 5분마다 작업을 동기화합니다. 휴지통으로 옮긴 작업은 서버에서 삭제됩니다.
\ No newline at end of file
@@ -1,50 +1,294 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use iced::keyboard::{self, key};
 use iced::widget::{center_x, column, keyed_column, mouse_area, scrollable, text_input};
-use iced::{window, Element, Fill, Function, Subscription, Task as Command, Theme};
+use iced::time::{self, Duration, Instant};
+use iced::{clipboard, mouse, window, Element, Fill, Function, Subscription, Task as Command, Theme};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::sleep;
+use uuid::Uuid;
+#[cfg(target_arch = "wasm32")]
+use wasmtimer::tokio::sleep;
 
-use crate::i18n::{translate, Language};
-use crate::state::{Filter, State};
+use crate::i18n::{translate, translate_backed_up, translate_legacy_imported, Language};
+use crate::state::persistence::{
+    LoadError, MaintenanceReport, SaveError, SavedChanges, SavedState, Snapshot, Storage,
+};
+use crate::state::{
+    BackupDialog, Drag, Filter, ImportPreview, InterchangeDialog, Maintenance, Notice,
+    PassphraseDialog, ReadOnly, SaveFailure, State, SyncDialog, SyncStatus,
+};
+use crate::state::caldav::{self, Collection, SyncError, Synced};
+use crate::state::history::{Change, HistoryEntry, HistoryPanel};
+use crate::state::interchange::{self, markdown, Format, Mode};
 use crate::task::{Task, TaskMessage};
-use crate::ui::{controls::view_controls, styles::subtle};
+use crate::ui::backups::view_backup_dialog;
+use crate::ui::encryption::{view_passphrase_dialog, view_unlock};
+use crate::ui::history::view_history_panel;
+use crate::ui::interchange::view_interchange_dialog;
+use crate::ui::status::{view_save_failure_banner, view_status_bar};
+use crate::ui::sync::view_sync_dialog;
+use crate::ui::trash::view_trash_bar;
+use crate::ui::{controls::view_controls, recovery::view_recovery, styles::subtle};
+
+/// How long edits have to settle before they are saved.
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How often the data is checked for writes from other processes.
+const EXTERNAL_CHECK: Duration = Duration::from_secs(2);
+
+/// How often the tasks are synced with the CalDAV collection, if there is one.
+const SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
 #[derive(Debug)]
 pub enum Todos {
-    Loading,
-    Loaded(State),
+    Loading(Arc<dyn Storage>),
+    /// The data is encrypted. Loading continues once it is unlocked.
+    Locked {
+        storage: Arc<dyn Storage>,
+        passphrase: String,
+        wrong_passphrase: bool,
+        unlocking: bool,
+        already_running: bool,
+    },
+    /// Loading failed. Nothing is saved until the user picks a way out.
+    Recovery {
+        storage: Arc<dyn Storage>,
+        error: LoadError,
+        reset_failed: bool,
+        /// Another instance owns the data, so it must not be reset from here.
+        already_running: bool,
+        /// The last attempt to repair the data from here.
+        maintenance: Option<Maintenance>,
+    },
+    Loaded(Box<State>),
 }
 
+// `TaskMessage` carries a task's own message, named after its type
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone)]
 pub enum Message {
-    Loaded(Result<crate::state::persistence::SavedState, crate::state::persistence::LoadError>),
-    Saved(Result<(), crate::state::persistence::SaveError>),
+    Loaded(Result<SavedState, LoadError>),
+    LoadedReadOnly(ReadOnly, Result<SavedState, LoadError>),
+    Saved(Result<(), SaveError>),
+    SaveDebounced(Instant),
+    RetrySave,
+    RetryLoad,
+    OpenReadOnly,
+    StartFresh,
+    Reset(Result<Option<String>, SaveError>),
+    PassphraseChanged(String),
+    Unlock,
+    Unlocked(Result<(), LoadError>),
+    PassphraseDialogToggled,
+    NewPassphraseChanged(String),
+    PassphraseConfirmationChanged(String),
+    SetPassphrase,
+    RemovePassphrase,
+    PassphraseSet(Result<bool, SaveError>),
+    BackupDue,
+    BackedUp(Result<Option<Snapshot>, SaveError>),
+    BackupDialogToggled,
+    SnapshotsLoaded(Result<Vec<Snapshot>, LoadError>),
+    BackupIntervalChanged(u32),
+    BackupGenerationsChanged(u32),
+    BackUpNow,
+    RestoreSnapshot(Snapshot),
+    Restored(Result<(), SaveError>),
+    Maintain,
+    Maintained(Result<MaintenanceReport, SaveError>),
+    InterchangeDialogToggled,
+    InterchangePathChanged(String),
+    ExportFormatChanged(Format),
+    Export(Format),
+    Exported(Result<PathBuf, SaveError>),
+    PreviewImport,
+    ImportRead(Result<Vec<Task>, LoadError>),
+    ImportModeChanged(Mode),
+    ConfirmImport,
+    ImportCancelled,
+    SyncDialogToggled,
+    SyncUrlChanged(String),
+    SyncUsernameChanged(String),
+    SyncPasswordChanged(String),
+    ConnectSync,
+    DisconnectSync,
+    Sync,
+    Synced(Result<Synced, SyncError>),
+    CheckExternalChanges,
+    ExternalChecked(Result<bool, LoadError>),
+    ExternalLoaded(Result<SavedState, LoadError>),
     InputChanged(String),
+    InputPasted(String),
+    PasteRead(String, Option<String>),
+    CopyAsMarkdown,
     InputHovered,
     InputUnhovered,
     CreateTask,
     FilterChanged(Filter),
     TaskMessage(usize, TaskMessage),
+    TrashRetentionChanged(Option<u32>),
+    EmptyTrash,
+    HistoryLoaded(Uuid, Result<Vec<HistoryEntry>, LoadError>),
+    HistoryClosed,
+    DescriptionReverted(Uuid, String),
+    TaskSelected(Uuid),
+    MoveSelectedUp,
+    MoveSelectedDown,
+    DragStarted(usize),
+    DragHovered(usize),
+    DragEnded,
     TabPressed { shift: bool },
     ToggleFullscreen(window::Mode),
+    WindowResized(iced::Size),
+    WindowMoved(iced::Point),
     LanguageChanged(Language),
+    NoticeDismissed,
+    CloseRequested,
+    CloseWithoutSaving,
+}
+
+impl Message {
+    /// Whether the message changes state that is persisted, and so needs saving.
+    fn is_mutation(&self) -> bool {
+        match self {
+            Message::InputChanged(_)
+            | Message::PasteRead(..)
+            | Message::CreateTask
+            | Message::FilterChanged(_)
+            | Message::TrashRetentionChanged(_)
+            | Message::EmptyTrash
+            | Message::DescriptionReverted(..)
+            | Message::BackupIntervalChanged(_)
+            | Message::BackupGenerationsChanged(_)
+            | Message::MoveSelectedUp
+            | Message::MoveSelectedDown
+            | Message::ConfirmImport
+            | Message::ConnectSync
+            | Message::DisconnectSync
+            | Message::Synced(_)
+            | Message::DragEnded
+            | Message::ToggleFullscreen(_)
+            | Message::WindowResized(_)
+            | Message::WindowMoved(_)
+            | Message::LanguageChanged(_) => true,
+            Message::TaskMessage(_, task_message) => task_message.is_mutation(),
+            Message::Loaded(_)
+            | Message::LoadedReadOnly(..)
+            | Message::Saved(_)
+            | Message::SaveDebounced(_)
+            | Message::RetrySave
+            | Message::RetryLoad
+            | Message::OpenReadOnly
+            | Message::StartFresh
+            | Message::Reset(_)
+            | Message::PassphraseChanged(_)
+            | Message::Unlock
+            | Message::Unlocked(_)
+            | Message::PassphraseDialogToggled
+            | Message::NewPassphraseChanged(_)
+            | Message::PassphraseConfirmationChanged(_)
+            | Message::SetPassphrase
+            | Message::RemovePassphrase
+            | Message::PassphraseSet(_)
+            | Message::BackupDue
+            | Message::BackedUp(_)
+            | Message::BackupDialogToggled
+            | Message::SnapshotsLoaded(_)
+            | Message::BackUpNow
+            | Message::RestoreSnapshot(_)
+            | Message::Restored(_)
+            | Message::Maintain
+            | Message::Maintained(_)
+            | Message::InterchangeDialogToggled
+            | Message::InterchangePathChanged(_)
+            | Message::ExportFormatChanged(_)
+            | Message::Export(_)
+            | Message::Exported(_)
+            | Message::PreviewImport
+            | Message::ImportRead(_)
+            | Message::ImportModeChanged(_)
+            | Message::ImportCancelled
+            | Message::SyncDialogToggled
+            | Message::SyncUrlChanged(_)
+            | Message::SyncUsernameChanged(_)
+            | Message::SyncPasswordChanged(_)
+            | Message::Sync
+            | Message::CheckExternalChanges
+            | Message::ExternalChecked(_)
+            | Message::ExternalLoaded(_)
+            | Message::InputPasted(_)
+            | Message::CopyAsMarkdown
+            | Message::InputHovered
+            | Message::InputUnhovered
+            | Message::HistoryLoaded(..)
+            | Message::HistoryClosed
+            | Message::TaskSelected(_)
+            | Message::DragStarted(_)
+            | Message::DragHovered(_)
+            | Message::TabPressed { .. }
+            | Message::NoticeDismissed
+            | Message::CloseRequested
+            | Message::CloseWithoutSaving => false,
+        }
+    }
 }
 
 impl Todos {
     pub const ICON_FONT: &'static [u8] = include_bytes!("../fonts/icons.ttf");
 
-    pub fn new() -> (Self, Command<Message>) {
-        use crate::state::persistence::SavedState;
-        
-        println!("Data saved at: {:?}", SavedState::path());
+    /// Starts loading. With `already_running`, another instance holds the
+    /// data directory's lock and the data is only opened read-only.
+    pub fn new(storage: Arc<dyn Storage>, already_running: bool) -> (Self, Command<Message>) {
+        println!("Data saved at: {}", storage.location());
 
-        (
-            Self::Loading,
-            Command::perform(SavedState::load(), Message::Loaded),
-        )
+        let load = Self::load(&storage, already_running);
+
+        (Self::Loading(storage), load)
+    }
+
+    fn load(storage: &Arc<dyn Storage>, already_running: bool) -> Command<Message> {
+        if already_running {
+            Command::perform(
+                storage.load_read_only(),
+                Message::LoadedReadOnly.with(ReadOnly::AlreadyRunning),
+            )
+        } else {
+            Command::perform(storage.load(), Message::Loaded)
+        }
+    }
+
+    fn loaded_state(storage: Arc<dyn Storage>, saved_state: SavedState) -> State {
+        if let Some(language) = saved_state.preferences.language {
+            crate::i18n::update_language(language);
+        }
+
+        let mut state = State {
+            input_value: saved_state.input_value,
+            filter: saved_state.filter,
+            tasks: saved_state.tasks,
+            notice: saved_state.legacy_imported.map(Notice::LegacyImported),
+            encrypted: storage.is_encrypted(),
+            caldav: saved_state.caldav,
+            ..State::new(storage)
+        }
+        .with_preferences(saved_state.preferences);
+
+        let purged = state.purge_trash(chrono::Utc::now());
+        if state.normalize_positions() || purged {
+            state.dirty = true;
+            state.last_mutation = Some(Instant::now());
+        }
+
+        state
     }
 
     pub fn title(&self) -> String {
         let (dirty, language) = match self {
-            Todos::Loading => (false, Language::default()),
+            Todos::Loading(_) | Todos::Locked { .. } | Todos::Recovery { .. } => {
+                (false, Language::default())
+            }
             Todos::Loaded(state) => (state.dirty, state.language),
         };
 
@@ -56,27 +300,162 @@ impl Todos {
     }
 
     pub fn update(&mut self, message: Message) -> Command<Message> {
+        if let Message::CloseRequested = message
+            && !matches!(self, Todos::Loaded(_))
+        {
+            return Self::close(self.storage());
+        }
+
         match self {
-            Todos::Loading => {
+            Todos::Loading(storage) => {
+                let storage = storage.clone();
+
                 match message {
                     Message::Loaded(Ok(saved_state)) => {
-                        *self = Todos::Loaded(State {
-                            input_value: saved_state.input_value,
-                            filter: saved_state.filter,
-                            tasks: saved_state.tasks,
-                            ..State::default()
-                        });
+                        let mut state = Self::loaded_state(storage, saved_state);
+                        let back_up = Self::back_up(&state);
+                        let sync = Self::sync(&mut state);
+                        *self = Todos::Loaded(Box::new(state));
+                        return Command::batch([text_input::focus("new-task"), back_up, sync]);
+                    }
+                    Message::LoadedReadOnly(reason, Ok(saved_state)) => {
+                        *self = Todos::Loaded(Box::new(State {
+                            read_only: Some(reason),
+                            dirty: false,
+                            ..Self::loaded_state(storage, saved_state)
+                        }));
+                    }
+                    Message::Loaded(Err(LoadError::Locked)) => {
+                        *self = Self::locked(storage, false);
+                        return text_input::focus("passphrase");
+                    }
+                    Message::LoadedReadOnly(reason, Err(LoadError::Locked)) => {
+                        *self = Self::locked(storage, reason == ReadOnly::AlreadyRunning);
+                        return text_input::focus("passphrase");
+                    }
+                    Message::Loaded(Err(error)) => {
+                        *self = Todos::Recovery {
+                            storage,
+                            error,
+                            reset_failed: false,
+                            already_running: false,
+                            maintenance: None,
+                        };
+                        return Command::none();
                     }
-                    Message::Loaded(Err(_)) => {
-                        *self = Todos::Loaded(State::default());
+                    Message::LoadedReadOnly(reason, Err(error)) => {
+                        *self = Todos::Recovery {
+                            storage,
+                            error,
+                            reset_failed: false,
+                            already_running: reason == ReadOnly::AlreadyRunning,
+                            maintenance: None,
+                        };
+                        return Command::none();
                     }
                     _ => {}
                 }
 
                 text_input::focus("new-task")
             }
+            Todos::Locked {
+                storage,
+                passphrase,
+                wrong_passphrase,
+                unlocking,
+                already_running,
+            } => match message {
+                Message::PassphraseChanged(value) => {
+                    *passphrase = value;
+                    Command::none()
+                }
+                Message::Unlock if !*unlocking && !passphrase.is_empty() => {
+                    *unlocking = true;
+                    Command::perform(
+                        storage.unlock(std::mem::take(passphrase)),
+                        Message::Unlocked,
+                    )
+                }
+                Message::Unlocked(Ok(())) => {
+                    let load = Self::load(storage, *already_running);
+                    *self = Todos::Loading(storage.clone());
+                    load
+                }
+                Message::Unlocked(Err(LoadError::WrongPassphrase)) => {
+                    *unlocking = false;
+                    *wrong_passphrase = true;
+                    text_input::focus("passphrase")
+                }
+                Message::Unlocked(Err(error)) => {
+                    *self = Todos::Recovery {
+                        storage: storage.clone(),
+                        error,
+                        reset_failed: false,
+                        already_running: *already_running,
+                        maintenance: None,
+                    };
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            Todos::Recovery {
+                storage,
+                reset_failed,
+                already_running,
+                maintenance,
+                ..
+            } => match message {
+                Message::Maintained(result) => {
+                    *maintenance = Some(match result {
+                        Ok(report) => Maintenance::Done(report),
+                        Err(error) => {
+                            eprintln!("{}", error);
+                            Maintenance::Failed
+                        }
+                    });
+                    Command::none()
+                }
+                // Everything else has to wait for the repair to finish
+                _ if matches!(maintenance, Some(Maintenance::Running)) => Command::none(),
+                Message::Maintain if !*already_running && storage.supports_maintenance() => {
+                    *maintenance = Some(Maintenance::Running);
+                    Command::perform(storage.maintain(), Message::Maintained)
+                }
+                Message::RetryLoad => {
+                    let load = Self::load(storage, *already_running);
+                    *self = Todos::Loading(storage.clone());
+                    load
+                }
+                Message::OpenReadOnly => {
+                    let load = storage.load_read_only();
+                    *self = Todos::Loading(storage.clone());
+                    Command::perform(load, Message::LoadedReadOnly.with(ReadOnly::LoadFailed))
+                }
+                Message::StartFresh if !*already_running => {
+                    Command::perform(storage.reset(), Message::Reset)
+                }
+                Message::Reset(Ok(backup)) => {
+                    *self = Todos::Loaded(Box::new(State {
+                        notice: backup.map(Notice::BackedUp),
+                        ..State::new(storage.clone())
+                    }));
+                    text_input::focus("new-task")
+                }
+                Message::Reset(Err(_)) => {
+                    *reset_failed = true;
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            // Load the restored data as if starting up
+            Todos::Loaded(state) if matches!(message, Message::Restored(Ok(()))) => {
+                let storage = state.storage.clone();
+                let load = Self::load(&storage, false);
+                *self = Todos::Loading(storage);
+                load
+            }
             Todos::Loaded(state) => {
-                let mut saved = false;
+                let mutation = message.is_mutation();
 
                 let command = match message {
                     Message::InputChanged(value) => {
@@ -93,24 +472,124 @@ impl Todos {
                     }
                     Message::CreateTask => {
                         if !state.input_value.is_empty() {
-                            state.tasks.push(Task::new(state.input_value.clone()));
-                            state.input_value.clear();
+                            let description = std::mem::take(&mut state.input_value);
+                            state.add_task(Task::new(description));
+                        }
+                        Command::none()
+                    }
+                    // The input drops line breaks, so look at what was pasted
+                    Message::InputPasted(value) => {
+                        clipboard::read().map(Message::PasteRead.with(value))
+                    }
+                    Message::PasteRead(value, pasted) => {
+                        match pasted.as_deref().and_then(markdown::parse_paste) {
+                            // Several lines make several tasks, the input stays as it was
+                            Some(tasks) => {
+                                for task in tasks {
+                                    state.add_task(task);
+                                }
+                            }
+                            None => state.input_value = value,
                         }
                         Command::none()
                     }
+                    Message::CopyAsMarkdown => {
+                        let filter = state.filter;
+                        clipboard::write(markdown::export(
+                            state.tasks.iter().filter(|task| filter.matches(task)),
+                        ))
+                    }
                     Message::FilterChanged(filter) => {
                         state.filter = filter;
                         Command::none()
                     }
-                    Message::TaskMessage(i, TaskMessage::Delete) => {
-                        state.tasks.remove(i);
+                    Message::TaskMessage(i, TaskMessage::DeleteForever) => {
+                        if i < state.tasks.len() {
+                            let task = state.tasks.remove(i);
+                            state.changes.delete(task.id());
+                        }
+                        Command::none()
+                    }
+                    Message::TrashRetentionChanged(days) => {
+                        state.trash_retention_days = days;
+                        Command::none()
+                    }
+                    Message::EmptyTrash => {
+                        state.empty_trash();
+                        Command::none()
+                    }
+                    Message::TaskMessage(i, TaskMessage::ShowHistory) => {
+                        let open = state.history_panel.as_ref().map(|panel| panel.task_id);
+
+                        match state.tasks.get(i).map(Task::id) {
+                            Some(id) if open != Some(id) => {
+                                state.history_panel = Some(HistoryPanel::new(id));
+                                Command::perform(
+                                    state.storage.load_history(id),
+                                    Message::HistoryLoaded.with(id),
+                                )
+                            }
+                            // Pressed again for the same task
+                            _ => {
+                                state.history_panel = None;
+                                Command::none()
+                            }
+                        }
+                    }
+                    Message::HistoryLoaded(id, result) => {
+                        let changes = &state.changes;
+
+                        if let Some(panel) = state.history_panel.as_mut().filter(|panel| panel.task_id == id) {
+                            match result {
+                                Ok(mut entries) => {
+                                    // Recorded here but not saved yet
+                                    let pending: Vec<HistoryEntry> = changes
+                                        .history_of(id)
+                                        .filter(|entry| !entries.contains(entry))
+                                        .cloned()
+                                        .collect();
+                                    entries.extend(pending);
+                                    panel.entries = Some(entries);
+                                }
+                                Err(error) => {
+                                    eprintln!("{}", error);
+                                    panel.failed = true;
+                                }
+                            }
+                        }
+                        Command::none()
+                    }
+                    Message::HistoryClosed => {
+                        state.history_panel = None;
+                        Command::none()
+                    }
+                    Message::DescriptionReverted(id, description) => {
+                        if let Some(task) = state.tasks.iter_mut().find(|task| task.id() == id)
+                            && let Some(change) = task.revert_description(description)
+                        {
+                            let entry = HistoryEntry::new(task, change);
+                            state.changes.upsert(id);
+                            state.record(entry);
+                        }
                         Command::none()
                     }
                     Message::TaskMessage(i, task_message) => {
                         if let Some(task) = state.tasks.get_mut(i) {
                             let should_focus = matches!(task_message, TaskMessage::Edit);
 
-                            task.update(task_message);
+                            if task_message.is_mutation() {
+                                state.changes.upsert(task.id());
+                            }
+
+                            let entries: Vec<HistoryEntry> = task
+                                .update(task_message)
+                                .into_iter()
+                                .map(|change| HistoryEntry::new(task, change))
+                                .collect();
+                            let done = entries.iter().any(|entry| entry.change == Change::Completed(true));
+                            for entry in entries {
+                                state.record(entry);
+                            }
 
                             if should_focus {
                                 let id = Task::text_input_id(i);
@@ -118,6 +597,10 @@ impl Todos {
                                     text_input::focus(id.clone()),
                                     text_input::select_all(id),
                                 ])
+                            } else if done {
+                                // A side effect of the app, not of the task, so
+                                // sync and merges stay quiet
+                                Command::future(async { crate::audio::play_done_sound() }).discard()
                             } else {
                                 Command::none()
                             }
@@ -125,11 +608,580 @@ impl Todos {
                             Command::none()
                         }
                     }
-                    Message::Saved(_result) => {
+                    Message::TaskSelected(id) => {
+                        state.selected = Some(id);
+                        Command::none()
+                    }
+                    Message::MoveSelectedUp => {
+                        state.move_selected(true);
+                        Command::none()
+                    }
+                    Message::MoveSelectedDown => {
+                        state.move_selected(false);
+                        Command::none()
+                    }
+                    Message::DragStarted(i) => {
+                        if let Some(task) = state.tasks.get(i) {
+                            state.selected = Some(task.id());
+                            state.drag = Some(Drag { from: i, to: i });
+                        }
+                        Command::none()
+                    }
+                    Message::DragHovered(i) => {
+                        if let Some(drag) = &mut state.drag {
+                            drag.to = i;
+                        }
+                        Command::none()
+                    }
+                    Message::DragEnded => {
+                        if let Some(Drag { from, to }) = state.drag.take() {
+                            state.move_task(from, to);
+                        }
+                        Command::none()
+                    }
+                    Message::Saved(result) => {
                         state.saving = false;
-                        saved = true;
+
+                        match result {
+                            Ok(()) => {
+                                state.changes.confirm();
+                                state.last_saved = Some(chrono::Local::now());
+                                state.save_failure = None;
+
+                                if state.closing {
+                                    Self::flush_or_close(state)
+                                } else {
+                                    Command::none()
+                                }
+                            }
+                            Err(error) => {
+                                eprintln!("{}", error);
+                                state.changes.restore();
+                                state.dirty = true;
+
+                                // Stay open so nothing is lost; the banner offers
+                                // to close without saving
+                                let previous = state.save_failure.as_ref();
+                                let failure = SaveFailure {
+                                    error,
+                                    attempts: previous.map_or(0, |f| f.attempts) + 1,
+                                    retry_pending: true,
+                                    close_blocked: state.closing || previous.is_some_and(|f| f.close_blocked),
+                                };
+                                state.closing = false;
+                                let delay = failure.retry_delay();
+                                state.save_failure = Some(failure);
+
+                                Command::perform(sleep(delay), |_| Message::RetrySave)
+                            }
+                        }
+                    }
+                    Message::RetrySave => {
+                        if let Some(failure) = &mut state.save_failure {
+                            failure.retry_pending = false;
+                        }
+                        Self::save(state)
+                    }
+                    Message::PassphraseDialogToggled => {
+                        state.passphrase_dialog = match state.passphrase_dialog.take() {
+                            // Re-encrypting cannot be cancelled halfway
+                            Some(dialog) if dialog.working => Some(dialog),
+                            Some(_) => None,
+                            None => Some(PassphraseDialog::default()),
+                        };
+                        Command::none()
+                    }
+                    Message::BackupDue => Self::back_up(state),
+                    Message::BackedUp(result) => {
+                        if let Err(error) = &result {
+                            eprintln!("{}", error);
+                        }
+
+                        match &mut state.backup_dialog {
+                            Some(dialog) => {
+                                dialog.working = false;
+                                dialog.error = result.is_err().then_some("backup-failed");
+                                Command::perform(state.storage.snapshots(), Message::SnapshotsLoaded)
+                            }
+                            None => Command::none(),
+                        }
+                    }
+                    Message::BackupDialogToggled => match state.backup_dialog.take() {
+                        // Restoring cannot be cancelled halfway
+                        Some(dialog) if dialog.working => {
+                            state.backup_dialog = Some(dialog);
+                            Command::none()
+                        }
+                        Some(_) => Command::none(),
+                        None => {
+                            state.backup_dialog = Some(BackupDialog::default());
+                            Command::perform(state.storage.snapshots(), Message::SnapshotsLoaded)
+                        }
+                    },
+                    Message::SnapshotsLoaded(result) => {
+                        if let Some(dialog) = &mut state.backup_dialog {
+                            match result {
+                                Ok(snapshots) => dialog.snapshots = Some(snapshots),
+                                Err(error) => {
+                                    eprintln!("{}", error);
+                                    dialog.snapshots = Some(Vec::new());
+                                    dialog.error = Some("backup-list-failed");
+                                }
+                            }
+                        }
                         Command::none()
                     }
+                    Message::BackupIntervalChanged(hours) => {
+                        state.backup_interval_hours = hours;
+                        Command::none()
+                    }
+                    Message::BackupGenerationsChanged(generations) => {
+                        state.backup_generations = generations;
+                        Command::none()
+                    }
+                    Message::BackUpNow => match &mut state.backup_dialog {
+                        Some(dialog) if !dialog.working => {
+                            dialog.working = true;
+                            dialog.error = None;
+                            dialog.maintenance = None;
+                            Self::back_up(state)
+                        }
+                        _ => Command::none(),
+                    },
+                    Message::RestoreSnapshot(snapshot) => match &mut state.backup_dialog {
+                        Some(dialog) if !dialog.working => {
+                            dialog.working = true;
+                            dialog.error = None;
+                            dialog.maintenance = None;
+                            Command::perform(
+                                state.storage.restore(snapshot, state.backup_generations),
+                                Message::Restored,
+                            )
+                        }
+                        _ => Command::none(),
+                    },
+                    Message::Restored(result) => {
+                        if let Err(error) = result {
+                            eprintln!("{}", error);
+                        }
+                        if let Some(dialog) = &mut state.backup_dialog {
+                            dialog.working = false;
+                            dialog.error = Some("backup-restore-failed");
+                        }
+                        Command::none()
+                    }
+                    Message::Maintain => match &mut state.backup_dialog {
+                        Some(dialog)
+                            if !dialog.working
+                                && state.read_only.is_none()
+                                && state.storage.supports_maintenance() =>
+                        {
+                            dialog.working = true;
+                            dialog.error = None;
+                            dialog.maintenance = Some(Maintenance::Running);
+                            Command::perform(state.storage.maintain(), Message::Maintained)
+                        }
+                        _ => Command::none(),
+                    },
+                    Message::Maintained(result) => {
+                        let maintenance = match result {
+                            Ok(report) => {
+                                // What is on screen may hold rows the repair
+                                // could not read, so write all of it back
+                                if report.salvage.is_some() {
+                                    state.save_all();
+                                }
+                                Maintenance::Done(report)
+                            }
+                            Err(error) => {
+                                eprintln!("{}", error);
+                                Maintenance::Failed
+                            }
+                        };
+
+                        if let Some(dialog) = &mut state.backup_dialog {
+                            dialog.working = false;
+                            dialog.maintenance = Some(maintenance);
+                        }
+                        Self::save(state)
+                    }
+                    Message::InterchangeDialogToggled => match state.interchange_dialog.take() {
+                        // A file being written is not worth leaving half done
+                        Some(dialog) if dialog.working => {
+                            state.interchange_dialog = Some(dialog);
+                            Command::none()
+                        }
+                        Some(_) => Command::none(),
+                        None => {
+                            state.interchange_dialog = Some(InterchangeDialog {
+                                path: interchange::default_path().display().to_string(),
+                                ..InterchangeDialog::default()
+                            });
+                            Command::none()
+                        }
+                    },
+                    Message::InterchangePathChanged(path) => {
+                        if let Some(dialog) = &mut state.interchange_dialog {
+                            dialog.path = path;
+                            dialog.preview = None;
+                            dialog.error = None;
+                        }
+                        Command::none()
+                    }
+                    Message::ExportFormatChanged(format) => {
+                        if let Some(dialog) = &mut state.interchange_dialog {
+                            dialog.format = format;
+                        }
+                        Command::none()
+                    }
+                    Message::Export(format) => {
+                        // What is on screen, saved or not. The history is read
+                        // from the storage, which keeps it
+                        let mut saved_state = SavedState {
+                            input_value: state.input_value.clone(),
+                            filter: state.filter,
+                            tasks: state.tasks.clone(),
+                            preferences: state.preferences(),
+                            caldav: state.caldav.clone(),
+                            ..SavedState::default()
+                        };
+                        let history = state.storage.load_all_history();
+
+                        match &mut state.interchange_dialog {
+                            Some(dialog) if !dialog.working => {
+                                dialog.preview = None;
+                                dialog.exported = None;
+
+                                if dialog.path.trim().is_empty() {
+                                    dialog.error = Some("interchange-no-file");
+                                    Command::none()
+                                } else {
+                                    let path = PathBuf::from(dialog.path.trim()).with_extension(format.extension());
+                                    dialog.path = path.display().to_string();
+                                    dialog.error = None;
+                                    dialog.working = true;
+
+                                    Command::perform(
+                                        async move {
+                                            // Only JSON has a place for it
+                                            if format == Format::Json {
+                                                saved_state.history = history.await.map_err(SaveError::write)?;
+                                            }
+                                            interchange::write_file(path, format.export(&saved_state)).await
+                                        },
+                                        Message::Exported,
+                                    )
+                                }
+                            }
+                            _ => Command::none(),
+                        }
+                    }
+                    Message::Exported(result) => {
+                        if let Some(dialog) = &mut state.interchange_dialog {
+                            dialog.working = false;
+                            match result {
+                                Ok(path) => dialog.exported = Some(path),
+                                Err(error) => {
+                                    eprintln!("{}", error);
+                                    dialog.error = Some("interchange-export-failed");
+                                }
+                            }
+                        }
+                        Command::none()
+                    }
+                    Message::PreviewImport => match &mut state.interchange_dialog {
+                        Some(dialog) if !dialog.working => {
+                            let path = PathBuf::from(dialog.path.trim());
+                            dialog.preview = None;
+                            dialog.exported = None;
+
+                            if dialog.path.trim().is_empty() {
+                                dialog.error = Some("interchange-no-file");
+                                Command::none()
+                            } else if Format::from_path(&path).is_none() {
+                                dialog.error = Some("interchange-unknown-format");
+                                Command::none()
+                            } else {
+                                dialog.error = None;
+                                dialog.working = true;
+                                Command::perform(
+                                    async move { interchange::read_file(&path).await },
+                                    Message::ImportRead,
+                                )
+                            }
+                        }
+                        _ => Command::none(),
+                    },
+                    Message::ImportRead(result) => {
+                        if let Some(dialog) = &mut state.interchange_dialog {
+                            dialog.working = false;
+                            match result {
+                                Ok(tasks) => {
+                                    dialog.preview = Some(ImportPreview {
+                                        merge: interchange::merge(&state.tasks, tasks.clone(), dialog.mode),
+                                        tasks,
+                                    });
+                                }
+                                Err(error) => {
+                                    eprintln!("{}", error);
+                                    dialog.error = Some("interchange-read-failed");
+                                }
+                            }
+                        }
+                        Command::none()
+                    }
+                    Message::ImportModeChanged(mode) => {
+                        if let Some(dialog) = &mut state.interchange_dialog {
+                            dialog.mode = mode;
+                            if let Some(preview) = &mut dialog.preview {
+                                preview.merge = interchange::merge(&state.tasks, preview.tasks.clone(), mode);
+                            }
+                        }
+                        Command::none()
+                    }
+                    Message::ConfirmImport => match state.interchange_dialog.take() {
+                        Some(InterchangeDialog {
+                            preview: Some(preview),
+                            mode,
+                            working: false,
+                            ..
+                        }) if state.read_only.is_none() => {
+                            // The tasks may have changed since the preview
+                            let merge = interchange::merge(&state.tasks, preview.tasks, mode);
+                            // Replacing deletes tasks for good, so keep a way back
+                            let back_up = if mode == Mode::Replace {
+                                Self::back_up(state)
+                            } else {
+                                Command::none()
+                            };
+
+                            state.apply_import(merge);
+                            back_up
+                        }
+                        dialog => {
+                            state.interchange_dialog = dialog;
+                            Command::none()
+                        }
+                    },
+                    Message::ImportCancelled => {
+                        if let Some(dialog) = &mut state.interchange_dialog {
+                            dialog.preview = None;
+                        }
+                        Command::none()
+                    }
+                    Message::SyncDialogToggled => {
+                        state.sync_dialog = match state.sync_dialog.take() {
+                            Some(_) => None,
+                            None => Some(match &state.caldav {
+                                Some(collection) => SyncDialog {
+                                    url: collection.url.clone(),
+                                    username: collection.username.clone(),
+                                    password: collection.password.clone(),
+                                    error: None,
+                                },
+                                None => SyncDialog::default(),
+                            }),
+                        };
+                        Command::none()
+                    }
+                    Message::SyncUrlChanged(value) => {
+                        if let Some(dialog) = &mut state.sync_dialog {
+                            dialog.url = value;
+                            dialog.error = None;
+                        }
+                        Command::none()
+                    }
+                    Message::SyncUsernameChanged(value) => {
+                        if let Some(dialog) = &mut state.sync_dialog {
+                            dialog.username = value;
+                        }
+                        Command::none()
+                    }
+                    Message::SyncPasswordChanged(value) => {
+                        if let Some(dialog) = &mut state.sync_dialog {
+                            dialog.password = value;
+                        }
+                        Command::none()
+                    }
+                    Message::ConnectSync => match &mut state.sync_dialog {
+                        Some(dialog) if dialog.url.trim().is_empty() => {
+                            dialog.error = Some("sync-no-url");
+                            Command::none()
+                        }
+                        Some(dialog) => {
+                            let connected = Collection::new(
+                                dialog.url.trim().to_string(),
+                                dialog.username.clone(),
+                                dialog.password.clone(),
+                            );
+                            dialog.error = None;
+
+                            // A new password for the same account keeps what
+                            // was synced so far
+                            state.caldav = Some(match state.caldav.take() {
+                                Some(collection) if collection.same_account(&connected) => Collection {
+                                    password: connected.password,
+                                    ..collection
+                                },
+                                _ => connected,
+                            });
+                            state.sync_status = SyncStatus::Idle;
+                            Self::sync(state)
+                        }
+                        None => Command::none(),
+                    },
+                    Message::DisconnectSync => {
+                        state.caldav = None;
+                        state.sync_status = SyncStatus::Idle;
+                        state.sync_dialog = None;
+                        Command::none()
+                    }
+                    Message::Sync => Self::sync(state),
+                    Message::Synced(result) => match result {
+                        // Disconnected or pointed elsewhere meanwhile
+                        Ok(synced)
+                            if !state
+                                .caldav
+                                .as_ref()
+                                .is_some_and(|collection| collection.same_account(&synced.collection)) =>
+                        {
+                            state.sync_status = SyncStatus::Idle;
+                            Self::sync(state)
+                        }
+                        Ok(mut synced) => {
+                            // The password may have been changed meanwhile
+                            if let Some(collection) = &state.caldav {
+                                synced.collection.password.clone_from(&collection.password);
+                            }
+                            state.apply_sync(synced);
+                            state.sync_status = SyncStatus::Synced(chrono::Local::now());
+                            Command::none()
+                        }
+                        Err(error) => {
+                            eprintln!("{}", error);
+                            if state.caldav.is_some() {
+                                state.sync_status = SyncStatus::Failed(error);
+                            }
+                            Command::none()
+                        }
+                    },
+                    Message::NewPassphraseChanged(value) => {
+                        if let Some(dialog) = &mut state.passphrase_dialog {
+                            dialog.passphrase = value;
+                        }
+                        Command::none()
+                    }
+                    Message::PassphraseConfirmationChanged(value) => {
+                        if let Some(dialog) = &mut state.passphrase_dialog {
+                            dialog.confirmation = value;
+                        }
+                        Command::none()
+                    }
+                    Message::SetPassphrase => match &mut state.passphrase_dialog {
+                        Some(dialog) if !dialog.working => {
+                            if dialog.passphrase.is_empty() {
+                                dialog.error = Some("passphrase-empty");
+                                Command::none()
+                            } else if dialog.passphrase != dialog.confirmation {
+                                dialog.error = Some("passphrase-mismatch");
+                                Command::none()
+                            } else {
+                                dialog.working = true;
+                                Command::perform(
+                                    state.storage.set_passphrase(Some(dialog.passphrase.clone())),
+                                    |result| Message::PassphraseSet(result.map(|()| true)),
+                                )
+                            }
+                        }
+                        _ => Command::none(),
+                    },
+                    Message::RemovePassphrase => match &mut state.passphrase_dialog {
+                        Some(dialog) if !dialog.working => {
+                            dialog.working = true;
+                            Command::perform(state.storage.set_passphrase(None), |result| {
+                                Message::PassphraseSet(result.map(|()| false))
+                            })
+                        }
+                        _ => Command::none(),
+                    },
+                    Message::PassphraseSet(Ok(encrypted)) => {
+                        state.encrypted = encrypted;
+                        state.passphrase_dialog = None;
+                        Command::none()
+                    }
+                    Message::PassphraseSet(Err(error)) => {
+                        eprintln!("{}", error);
+                        if let Some(dialog) = &mut state.passphrase_dialog {
+                            dialog.working = false;
+                            dialog.error = Some("passphrase-failed");
+                        }
+                        Command::none()
+                    }
+                    Message::CheckExternalChanges => {
+                        if state.reloading || state.replacing_data() {
+                            Command::none()
+                        } else {
+                            state.reloading = true;
+                            Command::perform(state.storage.changed(), Message::ExternalChecked)
+                        }
+                    }
+                    Message::ExternalChecked(Ok(true)) => {
+                        let load = if state.read_only.is_some() {
+                            state.storage.load_read_only()
+                        } else {
+                            state.storage.load()
+                        };
+                        Command::perform(load, Message::ExternalLoaded)
+                    }
+                    // Errors are not worth reporting every few seconds; loads and
+                    // saves surface the real problems
+                    Message::ExternalChecked(Ok(false) | Err(_)) => {
+                        state.reloading = false;
+                        Command::none()
+                    }
+                    Message::ExternalLoaded(result) => {
+                        state.reloading = false;
+
+                        match result {
+                            Ok(saved_state) => {
+                                if state.merge_external(saved_state.tasks) && state.read_only.is_none() {
+                                    state.dirty = true;
+                                    state.last_mutation = Some(Instant::now());
+                                }
+                            }
+                            Err(error) => eprintln!("{}", error),
+                        }
+                        Command::none()
+                    }
+                    Message::SaveDebounced(now) => {
+                        let due = state
+                            .last_mutation
+                            .is_some_and(|at| now.duration_since(at) >= SAVE_DEBOUNCE);
+
+                        if due {
+                            Self::save(state)
+                        } else {
+                            Command::none()
+                        }
+                    }
+                    Message::CloseRequested => {
+                        if state.read_only.is_some() {
+                            Self::close(&state.storage)
+                        } else if state.saving {
+                            // `Saved` picks up the rest once the running save is done
+                            state.closing = true;
+                            Command::none()
+                        } else {
+                            // A failed save may have been temporary, so closing
+                            // tries once more without waiting for the retry
+                            if let Some(failure) = &mut state.save_failure {
+                                failure.retry_pending = false;
+                            }
+                            state.closing = true;
+                            Self::flush_or_close(state)
+                        }
+                    }
+                    Message::CloseWithoutSaving => Self::close(&state.storage),
                     Message::TabPressed { shift } => {
                         if shift {
                             iced::widget::focus_previous()
@@ -138,52 +1190,200 @@ impl Todos {
                         }
                     }
                     Message::ToggleFullscreen(mode) => {
+                        state.fullscreen = mode == window::Mode::Fullscreen;
                         window::latest().and_then(move |window| window::set_mode(window, mode))
                     }
+                    // Fullscreen reports the monitor's geometry, which is not
+                    // what the window should come back to
+                    Message::WindowResized(size) => {
+                        if !state.fullscreen {
+                            state.window_size = Some((size.width, size.height));
+                        }
+                        Command::none()
+                    }
+                    Message::WindowMoved(position) => {
+                        if !state.fullscreen {
+                            state.window_position = Some((position.x, position.y));
+                        }
+                        Command::none()
+                    }
                     Message::LanguageChanged(language) => {
                         state.language = language;
                         crate::i18n::update_language(language);
                         Command::none()
                     }
-                    Message::Loaded(_) => Command::none(),
+                    Message::NoticeDismissed => {
+                        state.notice = None;
+                        Command::none()
+                    }
+                    Message::Loaded(_)
+                    | Message::LoadedReadOnly(..)
+                    | Message::RetryLoad
+                    | Message::OpenReadOnly
+                    | Message::StartFresh
+                    | Message::Reset(_)
+                    | Message::PassphraseChanged(_)
+                    | Message::Unlock
+                    | Message::Unlocked(_) => Command::none(),
                 };
 
-                if !saved {
+                if mutation && state.read_only.is_none() {
                     state.dirty = true;
+                    state.last_mutation = Some(Instant::now());
                 }
 
-                let save = if state.dirty && !state.saving {
-                    state.dirty = false;
-                    state.saving = true;
+                command
+            }
+        }
+    }
 
-                    use crate::state::persistence::SavedState;
-                    Command::perform(
-                        SavedState {
-                            input_value: state.input_value.clone(),
-                            filter: state.filter,
-                            tasks: state.tasks.clone(),
-                        }
-                        .save(),
-                        Message::Saved,
-                    )
-                } else {
-                    Command::none()
-                };
+    /// Starts saving the pending changes, unless a save is already running or
+    /// a retry is scheduled.
+    fn save(state: &mut State) -> Command<Message> {
+        let retry_pending = state
+            .save_failure
+            .as_ref()
+            .is_some_and(|failure| failure.retry_pending);
+
+        // Saving into data that is being replaced would mix the two
+        if !state.dirty
+            || state.saving
+            || state.read_only.is_some()
+            || retry_pending
+            || state.replacing_data()
+        {
+            return Command::none();
+        }
+
+        state.dirty = false;
+        state.saving = true;
+
+        let (upserted, deleted, history) = state.changes.take(&state.tasks);
+        Command::perform(
+            state.storage.save(SavedChanges {
+                input_value: state.input_value.clone(),
+                filter: state.filter,
+                upserted,
+                deleted,
+                history,
+                preferences: state.preferences(),
+                caldav: state.caldav.clone(),
+            }),
+            Message::Saved,
+        )
+    }
+
+    /// Starts syncing with the CalDAV collection, unless there is none, a sync
+    /// is already running or the data is read-only.
+    fn sync(state: &mut State) -> Command<Message> {
+        let Some(collection) = state.caldav.clone() else {
+            return Command::none();
+        };
+
+        if matches!(state.sync_status, SyncStatus::Syncing)
+            || state.read_only.is_some()
+            || state.replacing_data()
+        {
+            return Command::none();
+        }
+
+        state.sync_status = SyncStatus::Syncing;
+
+        // The sync works out from the history when each side changed a task,
+        // including what is recorded here but not saved yet
+        let pending = state
+            .tasks
+            .iter()
+            .flat_map(|task| state.changes.history_of(task.id()))
+            .cloned()
+            .collect();
+
+        Command::perform(
+            caldav::sync(collection, state.tasks.clone(), state.storage.clone(), pending),
+            Message::Synced,
+        )
+    }
+
+    /// Snapshots the data, unless the storage cannot or the data is read-only.
+    fn back_up(state: &State) -> Command<Message> {
+        if !state.storage.supports_backups() || state.read_only.is_some() {
+            return Command::none();
+        }
+
+        Command::perform(state.storage.back_up(state.backup_generations), Message::BackedUp)
+    }
+
+    /// Saves whatever is still pending, or exits once everything is saved.
+    fn flush_or_close(state: &mut State) -> Command<Message> {
+        if state.dirty {
+            Self::save(state)
+        } else {
+            Self::close(&state.storage)
+        }
+    }
+
+    fn close(storage: &Arc<dyn Storage>) -> Command<Message> {
+        Command::future(storage.close()).then(|_| iced::exit())
+    }
+
+    fn locked(storage: Arc<dyn Storage>, already_running: bool) -> Self {
+        Todos::Locked {
+            storage,
+            passphrase: String::new(),
+            wrong_passphrase: false,
+            unlocking: false,
+            already_running,
+        }
+    }
 
-                Command::batch(vec![command, save])
+    fn storage(&self) -> &Arc<dyn Storage> {
+        match self {
+            Todos::Loading(storage) | Todos::Locked { storage, .. } | Todos::Recovery { storage, .. } => {
+                storage
             }
+            Todos::Loaded(state) => &state.storage,
         }
     }
 
     pub fn view(&self) -> Element<'_, Message> {
         match self {
-            Todos::Loading => self.loading_view(),
+            Todos::Loading(_) => self.loading_view(),
+            Todos::Locked {
+                storage,
+                passphrase,
+                wrong_passphrase,
+                unlocking,
+                ..
+            } => view_unlock(
+                passphrase,
+                *wrong_passphrase,
+                *unlocking,
+                storage.location(),
+                Language::default(),
+            ),
+            Todos::Recovery {
+                storage,
+                error,
+                reset_failed,
+                already_running,
+                maintenance,
+            } => view_recovery(
+                error,
+                storage.location(),
+                *reset_failed,
+                *already_running,
+                storage.supports_maintenance(),
+                maintenance.as_ref(),
+                Language::default(),
+            ),
             Todos::Loaded(state) => self.loaded_view(state),
         }
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
-        keyboard::on_key_press(|key, modifiers| {
+        let close_requests = window::close_requests().map(|_| Message::CloseRequested);
+
+        let keys = keyboard::on_key_press(|key, modifiers| {
             let keyboard::Key::Named(key) = key else {
                 return None;
             };
@@ -192,6 +1392,8 @@ impl Todos {
                 (key::Named::Tab, _) => Some(Message::TabPressed {
                     shift: modifiers.shift(),
                 }),
+                (key::Named::ArrowUp, keyboard::Modifiers::ALT) => Some(Message::MoveSelectedUp),
+                (key::Named::ArrowDown, keyboard::Modifiers::ALT) => Some(Message::MoveSelectedDown),
                 (key::Named::ArrowUp, keyboard::Modifiers::SHIFT) => {
                     Some(Message::ToggleFullscreen(window::Mode::Fullscreen))
                 }
@@ -200,7 +1402,71 @@ impl Todos {
                 }
                 _ => None,
             }
-        })
+        });
+
+        let external_changes = match self {
+            Todos::Loaded(_) => time::every(EXTERNAL_CHECK).map(|_| Message::CheckExternalChanges),
+            _ => Subscription::none(),
+        };
+
+        let debounce = match self {
+            Todos::Loaded(state) if state.dirty => {
+                time::every(SAVE_DEBOUNCE).map(Message::SaveDebounced)
+            }
+            _ => Subscription::none(),
+        };
+
+        // Dropping outside the list still ends the drag
+        let drag_release = match self {
+            Todos::Loaded(state) if state.drag.is_some() => {
+                iced::event::listen_with(|event, _status, _window| match event {
+                    iced::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                        Some(Message::DragEnded)
+                    }
+                    _ => None,
+                })
+            }
+            _ => Subscription::none(),
+        };
+
+        let window_geometry = match self {
+            Todos::Loaded(_) => iced::event::listen_with(|event, _status, _window| match event {
+                iced::Event::Window(window::Event::Resized(size)) => {
+                    Some(Message::WindowResized(size))
+                }
+                iced::Event::Window(window::Event::Moved(position)) => {
+                    Some(Message::WindowMoved(position))
+                }
+                _ => None,
+            }),
+            _ => Subscription::none(),
+        };
+
+        let backups = match self {
+            Todos::Loaded(state) if state.storage.supports_backups() && state.read_only.is_none() => {
+                let hours = u64::from(state.backup_interval_hours.max(1));
+                time::every(Duration::from_secs(hours * 60 * 60)).map(|_| Message::BackupDue)
+            }
+            _ => Subscription::none(),
+        };
+
+        let sync = match self {
+            Todos::Loaded(state) if state.caldav.is_some() && state.read_only.is_none() => {
+                time::every(SYNC_INTERVAL).map(|_| Message::Sync)
+            }
+            _ => Subscription::none(),
+        };
+
+        Subscription::batch([
+            keys,
+            close_requests,
+            external_changes,
+            debounce,
+            drag_release,
+            window_geometry,
+            backups,
+            sync,
+        ])
     }
 
     fn loading_view(&self) -> Element<'_, Message> {
@@ -216,16 +1482,68 @@ impl Todos {
     fn loaded_view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
         let input = self.create_input(&state.input_value, state.language);
         let input_container = self.create_input_container(input, state.input_hovered);
-        let controls = view_controls(&state.tasks, state.filter, state.language);
-        let tasks_view = self.create_tasks_view(&state.tasks, state.filter, state.language);
+        // There is no syncing from the browser
+        let sync = (cfg!(not(target_arch = "wasm32")) && state.read_only.is_none()).then_some(&state.sync_status);
+        let controls = view_controls(&state.tasks, state.filter, sync, state.language);
+        let tasks_view = self.create_tasks_view(state);
 
         let footer_input = mouse_area(input_container)
             .on_enter(Message::InputHovered)
             .on_exit(Message::InputUnhovered);
 
-        let content = column![controls, tasks_view, footer_input]
-            .spacing(20)
-            .height(Fill);
+        let mut content = column![].spacing(20).height(Fill);
+
+        if let Some(reason) = state.read_only {
+            let key = match reason {
+                ReadOnly::LoadFailed => "read-only-notice",
+                ReadOnly::AlreadyRunning => "read-only-already-running",
+            };
+            content = content.push(
+                iced::widget::text(translate(key, state.language))
+                    .size(14)
+                    .style(subtle),
+            );
+        }
+
+        if let Some(failure) = state.save_failure.as_ref().filter(|f| f.is_persistent()) {
+            content = content.push(view_save_failure_banner(failure, state.language));
+        }
+
+        if let Some(dialog) = &state.passphrase_dialog {
+            content = content.push(view_passphrase_dialog(dialog, state.encrypted, state.language));
+        }
+
+        if let Some(dialog) = &state.backup_dialog {
+            content = content.push(view_backup_dialog(dialog, state));
+        }
+
+        if let Some(dialog) = &state.interchange_dialog {
+            content = content.push(view_interchange_dialog(dialog, state));
+        }
+
+        if let Some(dialog) = &state.sync_dialog {
+            content = content.push(view_sync_dialog(dialog, state));
+        }
+
+        if let Some(notice) = &state.notice {
+            content = content.push(self.notice_view(notice, state.language));
+        }
+
+        if let Some(panel) = &state.history_panel
+            && let Some(task) = state.tasks.iter().find(|task| task.id() == panel.task_id)
+        {
+            content = content.push(view_history_panel(panel, task, state.language));
+        }
+
+        let mut content = content.push(controls);
+
+        if state.filter == Filter::Trash {
+            content = content.push(view_trash_bar(state));
+        }
+
+        let content = content
+            .push(tasks_view)
+            .push(column![footer_input, view_status_bar(state)].spacing(8));
 
         center_x(content)
             .padding(iced::Padding {
@@ -237,10 +1555,26 @@ impl Todos {
             .into()
     }
 
+    fn notice_view<'a>(&'a self, notice: &Notice, language: Language) -> Element<'a, Message> {
+        use iced::widget::{button, text};
+
+        let message = match notice {
+            Notice::LegacyImported(count) => translate_legacy_imported(*count, language),
+            Notice::BackedUp(path) => translate_backed_up(path, language),
+        };
+
+        button(text(message).size(14).style(subtle))
+            .on_press(Message::NoticeDismissed)
+            .style(button::text)
+            .width(Fill)
+            .into()
+    }
+
     fn create_input(&self, input_value: &str, language: Language) -> Element<'_, Message> {
         text_input(&translate("add-task-placeholder", language), input_value)
             .id("new-task")
             .on_input(Message::InputChanged)
+            .on_paste(Message::InputPasted)
             .on_submit(Message::CreateTask)
             .padding(iced::Padding {
                 top: 8.0,
@@ -303,17 +1637,53 @@ impl Todos {
             .into()
     }
 
-    fn create_tasks_view<'a>(&'a self, tasks: &'a [Task], filter: Filter, language: Language) -> Element<'a, Message> {
-        let filtered_tasks = tasks.iter().filter(|task| filter.matches(task));
+    fn create_tasks_view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        use iced::widget::{container, row};
+        use crate::ui::icons::drag_handle_icon;
+
+        let filter = state.filter;
+        let filtered_tasks = state.tasks.iter().filter(|task| filter.matches(task));
 
         if filtered_tasks.count() > 0 {
+            let drop_target = state.drag.filter(|drag| drag.from != drag.to).map(|drag| drag.to);
+
             let tasks_column = keyed_column(
-                tasks
+                state
+                    .tasks
                     .iter()
                     .enumerate()
                     .filter(|(_, task)| filter.matches(task))
                     .map(|(i, task)| {
-                        (task.id(), task.view(i).map(Message::TaskMessage.with(i)))
+                        let handle = mouse_area(drag_handle_icon())
+                            .on_press(Message::DragStarted(i))
+                            .interaction(mouse::Interaction::Grab);
+
+                        let item = row![handle, task.view(i).map(Message::TaskMessage.with(i))]
+                            .spacing(4)
+                            .align_y(iced::Center);
+
+                        let highlighted = state.selected == Some(task.id()) || drop_target == Some(i);
+
+                        let item = container(item).style(move |theme: &Theme| {
+                            if !highlighted {
+                                return container::Style::default();
+                            }
+
+                            container::Style {
+                                border: iced::Border {
+                                    color: theme.extended_palette().primary.weak.color,
+                                    width: 1.0,
+                                    radius: 8.0.into(),
+                                },
+                                ..Default::default()
+                            }
+                        });
+
+                        let item = mouse_area(item)
+                            .on_press(Message::TaskSelected(task.id()))
+                            .on_enter(Message::DragHovered(i));
+
+                        (task.id(), item.into())
                     }),
             )
             .spacing(10)
@@ -325,8 +1695,9 @@ impl Todos {
                 Filter::All => "empty-no-tasks",
                 Filter::Active => "empty-all-done",
                 Filter::Completed => "empty-no-completed",
+                Filter::Trash => "empty-trash",
             };
-            self.empty_message(key, language)
+            self.empty_message(key, state.language)
         }
     }
 
@@ -341,4 +1712,155 @@ impl Todos {
         .height(Fill)
         .into()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::persistence::memory_persistence::MemoryStorage;
+
+    /// Loads the app from `storage`, as the command from `Todos::new` would.
+    async fn load(storage: &MemoryStorage) -> Todos {
+        let (mut todos, _) = Todos::new(Arc::new(storage.clone()), false);
+        let _ = todos.update(Message::Loaded(storage.load().await));
+        todos
+    }
+
+    /// Saves the pending changes, as the debounce does once edits settle.
+    /// Memory storage applies them before the returned command would run.
+    fn save(todos: &mut Todos) {
+        let _ = todos.update(Message::SaveDebounced(Instant::now() + SAVE_DEBOUNCE));
+        let _ = todos.update(Message::Saved(Ok(())));
+    }
+
+    fn state(todos: &Todos) -> &State {
+        match todos {
+            Todos::Loaded(state) => state,
+            _ => panic!("not loaded"),
+        }
+    }
+
+    fn create(todos: &mut Todos, description: &str) {
+        let _ = todos.update(Message::InputChanged(description.to_string()));
+        let _ = todos.update(Message::CreateTask);
+    }
+
+    async fn saved(storage: &MemoryStorage) -> Vec<(String, bool, bool)> {
+        storage
+            .load()
+            .await
+            .unwrap()
+            .tasks
+            .iter()
+            .map(|task| (task.description().to_string(), task.completed(), task.is_deleted()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn created_tasks_are_saved_in_order() {
+        let storage = MemoryStorage::default();
+        let mut todos = load(&storage).await;
+
+        create(&mut todos, "Buy milk");
+        create(&mut todos, "Call mom");
+        assert!(state(&todos).dirty);
+        assert!(state(&todos).input_value.is_empty());
+        assert!(saved(&storage).await.is_empty());
+
+        save(&mut todos);
+        assert!(!state(&todos).dirty);
+        assert_eq!(
+            saved(&storage).await,
+            [
+                ("Buy milk".to_string(), false, false),
+                ("Call mom".to_string(), false, false),
+            ]
+        );
+
+        let history = storage.load_history(state(&todos).tasks[0].id()).await.unwrap();
+        assert_eq!(history.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn toggling_a_task_saves_it_after_a_restart() {
+        let storage = MemoryStorage::default();
+        let mut todos = load(&storage).await;
+        create(&mut todos, "Buy milk");
+        create(&mut todos, "Call mom");
+        save(&mut todos);
+
+        let mut todos = load(&storage).await;
+        let _ = todos.update(Message::TaskMessage(1, TaskMessage::Completed(true)));
+        save(&mut todos);
+
+        assert_eq!(
+            saved(&storage).await,
+            [
+                ("Buy milk".to_string(), false, false),
+                ("Call mom".to_string(), true, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn deleting_moves_to_the_trash_and_deleting_again_removes() {
+        let storage = MemoryStorage::default();
+        let mut todos = load(&storage).await;
+        create(&mut todos, "Buy milk");
+        create(&mut todos, "Call mom");
+        save(&mut todos);
+
+        let _ = todos.update(Message::TaskMessage(0, TaskMessage::Delete));
+        save(&mut todos);
+        assert_eq!(
+            saved(&storage).await,
+            [
+                ("Buy milk".to_string(), false, true),
+                ("Call mom".to_string(), false, false),
+            ]
+        );
+
+        let _ = todos.update(Message::TaskMessage(0, TaskMessage::DeleteForever));
+        save(&mut todos);
+        assert_eq!(saved(&storage).await, [("Call mom".to_string(), false, false)]);
+    }
+
+    #[tokio::test]
+    async fn closing_after_a_failed_save_tries_once_more() {
+        let storage = MemoryStorage::default();
+        let mut todos = load(&storage).await;
+        create(&mut todos, "Buy milk");
+
+        let busy = || SaveError::write("database is locked");
+        let _ = todos.update(Message::SaveDebounced(Instant::now() + SAVE_DEBOUNCE));
+        let _ = todos.update(Message::Saved(Err(busy())));
+        assert!(state(&todos).save_failure.as_ref().unwrap().retry_pending);
+
+        // Without waiting for the scheduled retry
+        let _ = todos.update(Message::CloseRequested);
+        assert!(state(&todos).saving && state(&todos).closing);
+
+        // Failing again keeps the window open with the changes
+        let _ = todos.update(Message::Saved(Err(busy())));
+        let failure = state(&todos).save_failure.as_ref().unwrap();
+        assert!(failure.close_blocked && failure.is_persistent());
+        assert!(!state(&todos).closing && state(&todos).dirty);
+
+        // A retry that works clears it
+        let _ = todos.update(Message::RetrySave);
+        let _ = todos.update(Message::Saved(Ok(())));
+        assert!(state(&todos).save_failure.is_none());
+        assert_eq!(saved(&storage).await, [("Buy milk".to_string(), false, false)]);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_saved_before_the_debounce() {
+        let storage = MemoryStorage::default();
+        let mut todos = load(&storage).await;
+        create(&mut todos, "Buy milk");
+
+        let _ = todos.update(Message::SaveDebounced(Instant::now()));
+        assert!(state(&todos).dirty);
+        assert!(saved(&storage).await.is_empty());
+    }
+}
@@ -1,8 +1,12 @@
+#[cfg(not(target_arch = "wasm32"))]
 use rodio::{Decoder, OutputStreamBuilder, Sink};
+#[cfg(not(target_arch = "wasm32"))]
 use std::io::Cursor;
 
+#[cfg(not(target_arch = "wasm32"))]
 const DONE_SOUND: &[u8] = include_bytes!("../assets/done.wav");
 
+#[cfg(not(target_arch = "wasm32"))]
 pub fn init_audio() {
     match OutputStreamBuilder::open_default_stream() {
         Ok(_stream_handle) => {
@@ -14,13 +18,14 @@ pub fn init_audio() {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub fn play_done_sound() {
     std::thread::spawn(|| {
         let wav_data = DONE_SOUND;
         match OutputStreamBuilder::open_default_stream() {
             Ok(stream_handler) => {
-                let sink = Sink::connect_new(&stream_handler.mixer());
-                let cursor = Cursor::new(wav_data.as_ref());
+                let sink = Sink::connect_new(stream_handler.mixer());
+                let cursor = Cursor::new(wav_data);
                 match Decoder::new(cursor) {
                     Ok(source) => {
                         sink.append(source);
@@ -32,4 +37,11 @@ pub fn play_done_sound() {
             Err(e) => eprintln!("Failed to open audio stream: {}", e),
         }
     });
-}
\ No newline at end of file
+}
+
+// No sound in the browser, which has neither rodio's output nor threads
+#[cfg(target_arch = "wasm32")]
+pub fn init_audio() {}
+
+#[cfg(target_arch = "wasm32")]
+pub fn play_done_sound() {}
\ No newline at end of file
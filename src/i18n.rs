@@ -6,6 +6,7 @@ use i18n_embed::{
 use i18n_embed_fl::fl;
 use std::sync::LazyLock;
 use i18n_embed::unic_langid::LanguageIdentifier;
+use serde::{Deserialize, Serialize};
 
 #[derive(RustEmbed)]
 #[folder = "i18n"]
@@ -18,12 +19,32 @@ pub static LANGUAGE_LOADER: LazyLock<FluentLanguageLoader> = LazyLock::new(|| {
     loader
 });
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Language {
+    #[serde(rename = "en")]
     English,
+    #[serde(rename = "ko")]
     Korean,
 }
 
+impl Language {
+    /// Short code used when storing the language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Korean => "ko",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "en" => Some(Language::English),
+            "ko" => Some(Language::Korean),
+            _ => None,
+        }
+    }
+}
+
 impl Default for Language {
     fn default() -> Self {
         let requested_languages = DesktopLanguageRequester::requested_languages();
@@ -54,9 +75,108 @@ pub fn translate(key: &str, language: Language) -> String {
         "filter-all" => fl!(LANGUAGE_LOADER, "filter-all"),
         "filter-active" => fl!(LANGUAGE_LOADER, "filter-active"),
         "filter-completed" => fl!(LANGUAGE_LOADER, "filter-completed"),
+        "filter-trash" => fl!(LANGUAGE_LOADER, "filter-trash"),
         "empty-no-tasks" => fl!(LANGUAGE_LOADER, "empty-no-tasks"),
         "empty-all-done" => fl!(LANGUAGE_LOADER, "empty-all-done"),
         "empty-no-completed" => fl!(LANGUAGE_LOADER, "empty-no-completed"),
+        "empty-trash" => fl!(LANGUAGE_LOADER, "empty-trash"),
+        "read-only-notice" => fl!(LANGUAGE_LOADER, "read-only-notice"),
+        "recovery-title" => fl!(LANGUAGE_LOADER, "recovery-title"),
+        "recovery-file" => fl!(LANGUAGE_LOADER, "recovery-file"),
+        "recovery-format" => fl!(LANGUAGE_LOADER, "recovery-format"),
+        "recovery-retry" => fl!(LANGUAGE_LOADER, "recovery-retry"),
+        "recovery-read-only" => fl!(LANGUAGE_LOADER, "recovery-read-only"),
+        "recovery-start-fresh" => fl!(LANGUAGE_LOADER, "recovery-start-fresh"),
+        "recovery-backup-hint" => fl!(LANGUAGE_LOADER, "recovery-backup-hint"),
+        "recovery-reset-failed" => fl!(LANGUAGE_LOADER, "recovery-reset-failed"),
+        "status-saving" => fl!(LANGUAGE_LOADER, "status-saving"),
+        "status-pending" => fl!(LANGUAGE_LOADER, "status-pending"),
+        "save-failure-banner" => fl!(LANGUAGE_LOADER, "save-failure-banner"),
+        "save-retry-now" => fl!(LANGUAGE_LOADER, "save-retry-now"),
+        "save-failure-closing" => fl!(LANGUAGE_LOADER, "save-failure-closing"),
+        "save-close-anyway" => fl!(LANGUAGE_LOADER, "save-close-anyway"),
+        "read-only-already-running" => fl!(LANGUAGE_LOADER, "read-only-already-running"),
+        "recovery-already-running" => fl!(LANGUAGE_LOADER, "recovery-already-running"),
+        "unlock-title" => fl!(LANGUAGE_LOADER, "unlock-title"),
+        "unlock-placeholder" => fl!(LANGUAGE_LOADER, "unlock-placeholder"),
+        "unlock-button" => fl!(LANGUAGE_LOADER, "unlock-button"),
+        "unlock-working" => fl!(LANGUAGE_LOADER, "unlock-working"),
+        "unlock-wrong" => fl!(LANGUAGE_LOADER, "unlock-wrong"),
+        "recovery-locked" => fl!(LANGUAGE_LOADER, "recovery-locked"),
+        "encryption-button" => fl!(LANGUAGE_LOADER, "encryption-button"),
+        "encryption-button-encrypted" => fl!(LANGUAGE_LOADER, "encryption-button-encrypted"),
+        "encryption-title-encrypt" => fl!(LANGUAGE_LOADER, "encryption-title-encrypt"),
+        "encryption-title-change" => fl!(LANGUAGE_LOADER, "encryption-title-change"),
+        "passphrase-new" => fl!(LANGUAGE_LOADER, "passphrase-new"),
+        "passphrase-confirm" => fl!(LANGUAGE_LOADER, "passphrase-confirm"),
+        "encryption-encrypt" => fl!(LANGUAGE_LOADER, "encryption-encrypt"),
+        "encryption-change" => fl!(LANGUAGE_LOADER, "encryption-change"),
+        "encryption-remove" => fl!(LANGUAGE_LOADER, "encryption-remove"),
+        "encryption-cancel" => fl!(LANGUAGE_LOADER, "encryption-cancel"),
+        "encryption-hint" => fl!(LANGUAGE_LOADER, "encryption-hint"),
+        "passphrase-empty" => fl!(LANGUAGE_LOADER, "passphrase-empty"),
+        "passphrase-mismatch" => fl!(LANGUAGE_LOADER, "passphrase-mismatch"),
+        "passphrase-failed" => fl!(LANGUAGE_LOADER, "passphrase-failed"),
+        "trash-retention" => fl!(LANGUAGE_LOADER, "trash-retention"),
+        "trash-keep-forever" => fl!(LANGUAGE_LOADER, "trash-keep-forever"),
+        "trash-empty" => fl!(LANGUAGE_LOADER, "trash-empty"),
+        "history-title" => fl!(LANGUAGE_LOADER, "history-title"),
+        "history-close" => fl!(LANGUAGE_LOADER, "history-close"),
+        "history-loading" => fl!(LANGUAGE_LOADER, "history-loading"),
+        "history-empty" => fl!(LANGUAGE_LOADER, "history-empty"),
+        "history-failed" => fl!(LANGUAGE_LOADER, "history-failed"),
+        "history-completed" => fl!(LANGUAGE_LOADER, "history-completed"),
+        "history-uncompleted" => fl!(LANGUAGE_LOADER, "history-uncompleted"),
+        "history-deleted" => fl!(LANGUAGE_LOADER, "history-deleted"),
+        "history-restored" => fl!(LANGUAGE_LOADER, "history-restored"),
+        "history-revert" => fl!(LANGUAGE_LOADER, "history-revert"),
+        "backup-button" => fl!(LANGUAGE_LOADER, "backup-button"),
+        "backup-title" => fl!(LANGUAGE_LOADER, "backup-title"),
+        "backup-now" => fl!(LANGUAGE_LOADER, "backup-now"),
+        "backup-close" => fl!(LANGUAGE_LOADER, "backup-close"),
+        "backup-loading" => fl!(LANGUAGE_LOADER, "backup-loading"),
+        "backup-none" => fl!(LANGUAGE_LOADER, "backup-none"),
+        "backup-unreadable" => fl!(LANGUAGE_LOADER, "backup-unreadable"),
+        "backup-restore" => fl!(LANGUAGE_LOADER, "backup-restore"),
+        "backup-hint" => fl!(LANGUAGE_LOADER, "backup-hint"),
+        "backup-failed" => fl!(LANGUAGE_LOADER, "backup-failed"),
+        "backup-list-failed" => fl!(LANGUAGE_LOADER, "backup-list-failed"),
+        "backup-restore-failed" => fl!(LANGUAGE_LOADER, "backup-restore-failed"),
+        "maintenance-button" => fl!(LANGUAGE_LOADER, "maintenance-button"),
+        "maintenance-running" => fl!(LANGUAGE_LOADER, "maintenance-running"),
+        "maintenance-healthy" => fl!(LANGUAGE_LOADER, "maintenance-healthy"),
+        "maintenance-failed" => fl!(LANGUAGE_LOADER, "maintenance-failed"),
+        "copy-markdown" => fl!(LANGUAGE_LOADER, "copy-markdown"),
+        "interchange-button" => fl!(LANGUAGE_LOADER, "interchange-button"),
+        "interchange-title" => fl!(LANGUAGE_LOADER, "interchange-title"),
+        "interchange-path-placeholder" => fl!(LANGUAGE_LOADER, "interchange-path-placeholder"),
+        "interchange-export" => fl!(LANGUAGE_LOADER, "interchange-export"),
+        "interchange-preview" => fl!(LANGUAGE_LOADER, "interchange-preview"),
+        "interchange-close" => fl!(LANGUAGE_LOADER, "interchange-close"),
+        "interchange-hint" => fl!(LANGUAGE_LOADER, "interchange-hint"),
+        "interchange-mode-merge" => fl!(LANGUAGE_LOADER, "interchange-mode-merge"),
+        "interchange-mode-replace" => fl!(LANGUAGE_LOADER, "interchange-mode-replace"),
+        "interchange-merge-hint" => fl!(LANGUAGE_LOADER, "interchange-merge-hint"),
+        "interchange-replace-hint" => fl!(LANGUAGE_LOADER, "interchange-replace-hint"),
+        "interchange-import" => fl!(LANGUAGE_LOADER, "interchange-import"),
+        "interchange-cancel" => fl!(LANGUAGE_LOADER, "interchange-cancel"),
+        "interchange-no-file" => fl!(LANGUAGE_LOADER, "interchange-no-file"),
+        "interchange-unknown-format" => fl!(LANGUAGE_LOADER, "interchange-unknown-format"),
+        "interchange-read-failed" => fl!(LANGUAGE_LOADER, "interchange-read-failed"),
+        "interchange-export-failed" => fl!(LANGUAGE_LOADER, "interchange-export-failed"),
+        "sync-button" => fl!(LANGUAGE_LOADER, "sync-button"),
+        "sync-syncing" => fl!(LANGUAGE_LOADER, "sync-syncing"),
+        "sync-failed" => fl!(LANGUAGE_LOADER, "sync-failed"),
+        "sync-title" => fl!(LANGUAGE_LOADER, "sync-title"),
+        "sync-url-placeholder" => fl!(LANGUAGE_LOADER, "sync-url-placeholder"),
+        "sync-username-placeholder" => fl!(LANGUAGE_LOADER, "sync-username-placeholder"),
+        "sync-password-placeholder" => fl!(LANGUAGE_LOADER, "sync-password-placeholder"),
+        "sync-connect" => fl!(LANGUAGE_LOADER, "sync-connect"),
+        "sync-now" => fl!(LANGUAGE_LOADER, "sync-now"),
+        "sync-disconnect" => fl!(LANGUAGE_LOADER, "sync-disconnect"),
+        "sync-close" => fl!(LANGUAGE_LOADER, "sync-close"),
+        "sync-no-url" => fl!(LANGUAGE_LOADER, "sync-no-url"),
+        "sync-hint" => fl!(LANGUAGE_LOADER, "sync-hint"),
         _ => key.to_string(),
     }
 }
@@ -65,6 +185,87 @@ pub fn translate_tasks_left(count: usize, _language: Language) -> String {
     fl!(LANGUAGE_LOADER, "tasks-left", count = count)
 }
 
+pub fn translate_legacy_imported(count: usize, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "legacy-imported", count = count)
+}
+
+pub fn translate_data_location(path: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "data-location", path = path)
+}
+
+pub fn translate_backed_up(path: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "backed-up", path = path)
+}
+
+pub fn translate_unsupported_version(found: i64, supported: i64, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "recovery-unsupported-version", found = found, supported = supported)
+}
+
+pub fn translate_retention_days(days: u32, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "trash-retention-days", days = days)
+}
+
+pub fn translate_saved_at(time: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "status-saved", time = time)
+}
+
+pub fn translate_save_failed(attempts: u32, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "status-failed", attempts = attempts)
+}
+
+pub fn translate_history_created(description: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "history-created", description = description)
+}
+
+pub fn translate_history_renamed(from: &str, to: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "history-renamed", from = from, to = to)
+}
+
+pub fn translate_backup_interval(hours: u32, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "backup-interval", hours = hours)
+}
+
+pub fn translate_backup_generations(count: u32, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "backup-generations", count = count)
+}
+
+pub fn translate_backup_task_count(count: usize, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "backup-task-count", count = count)
+}
+
+pub fn translate_maintenance_repaired(tasks: usize, unreadable: usize, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "maintenance-repaired", tasks = tasks, unreadable = unreadable)
+}
+
+pub fn translate_maintenance_report(path: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "maintenance-report", path = path)
+}
+
+pub fn translate_import_summary(
+    added: usize,
+    updated: usize,
+    unchanged: usize,
+    removed: usize,
+    _language: Language,
+) -> String {
+    fl!(
+        LANGUAGE_LOADER,
+        "interchange-summary",
+        added = added,
+        updated = updated,
+        unchanged = unchanged,
+        removed = removed
+    )
+}
+
+pub fn translate_exported(path: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "interchange-exported", path = path)
+}
+
+pub fn translate_synced_at(time: &str, _language: Language) -> String {
+    fl!(LANGUAGE_LOADER, "sync-synced", time = time)
+}
+
 pub fn update_language(language: Language) {
     let lang_ids = match language {
         Language::Korean => vec!["ko-KR".parse::<LanguageIdentifier>().unwrap()],
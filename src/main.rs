@@ -7,8 +7,365 @@ mod state;
 mod task;
 mod ui;
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
 use iced::window;
 
+#[cfg(not(target_arch = "wasm32"))]
+use state::interchange::{self, Format};
+#[cfg(not(target_arch = "wasm32"))]
+use state::persistence::instance_lock::InstanceLock;
+#[cfg(not(target_arch = "wasm32"))]
+use state::persistence::{SavedChanges, SavedState};
+use state::persistence::{Backend, Preferences, Storage};
+
+#[cfg(not(target_arch = "wasm32"))]
+const USAGE: &str = "\
+Usage: todos [OPTIONS]
+
+Options:
+      --data-dir <DIR>     Directory to keep the task data in
+      --db <FILE>          Database file to use, inside or outside the data directory
+                           (the repository's directory with --storage git)
+      --portable           Keep the data next to the executable
+      --storage <BACKEND>  Storage backend: sqlite, json, git or memory
+      --maintain           Check, compact and if need be repair the data, then exit
+      --import <FILE>      Add the tasks in FILE, updating those already stored, then exit
+      --export <FILE>      Write the tasks to FILE, then exit
+      --format <FORMAT>    Format of FILE, when its extension is not enough
+  -h, --help               Print this help
+
+The format of --import and --export follows the file extension:
+  .txt                     todo.txt
+  .md, .markdown           Markdown checklist
+  .ics, .ical              iCalendar VTODOs
+  .json                    JSON, keeping everything including the trash, or
+                           Taskwarrior's `task export` when importing
+  .csv                     CSV with a header row, including the trash
+  .org                     Org TODO and DONE headlines
+Use --format taskwarrior to export for `task import`.
+
+With --storage git, each task is a file in a git repository in the data
+directory, committed on every save. Add an `origin` remote to the repository
+to pull from and push to it while the app runs.
+
+Environment:
+  TODOS_DATA_DIR           Data directory, when no option above picks one
+  TODOS_STORAGE            Storage backend, when --storage is not given";
+
+/// Where and how the task data is stored, resolved once at startup.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+struct Options {
+    data_dir: PathBuf,
+    db: Option<PathBuf>,
+    backend: Backend,
+    /// Done instead of opening the window.
+    action: Option<Action>,
+    /// Overrides the extension of the file imported or exported.
+    format: Option<Format>,
+}
+
+/// Work done from the command line, without opening the window.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+enum Action {
+    Maintain,
+    Import(PathBuf),
+    Export(PathBuf),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Options {
+    /// Parses the command line. The data directory is taken from, in order:
+    /// `--data-dir`, `--portable`, the directory of `--db`, `TODOS_DATA_DIR`
+    /// and finally the per-user data directory.
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut data_dir = None;
+        let mut db: Option<PathBuf> = None;
+        let mut portable = false;
+        let mut backend = Backend::from_env();
+        let mut action = None;
+        let mut format = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--data-dir" => {
+                    data_dir = Some(args.next().ok_or("--data-dir needs a directory")?.into());
+                }
+                "--db" => {
+                    db = Some(args.next().ok_or("--db needs a file")?.into());
+                }
+                "--portable" => {
+                    portable = true;
+                }
+                "--storage" => {
+                    backend = args.next().ok_or("--storage needs a backend")?.parse()?;
+                }
+                "--maintain" => {
+                    action = Some(Action::Maintain);
+                }
+                "--import" => {
+                    action = Some(Action::Import(args.next().ok_or("--import needs a file")?.into()));
+                }
+                "--export" => {
+                    action = Some(Action::Export(args.next().ok_or("--export needs a file")?.into()));
+                }
+                "--format" => {
+                    format = Some(args.next().ok_or("--format needs a format")?.parse()?);
+                }
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+
+        let data_dir = data_dir
+            .or_else(|| portable.then(state::persistence::portable_data_dir))
+            .or_else(|| {
+                db.as_ref()
+                    .and_then(|db| db.parent())
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .map(PathBuf::from)
+            })
+            .or_else(|| std::env::var_os("TODOS_DATA_DIR").map(PathBuf::from))
+            .unwrap_or_else(state::persistence::default_data_dir);
+
+        Ok(Self {
+            data_dir,
+            db,
+            backend,
+            action,
+            format,
+        })
+    }
+}
+
+/// Parses the command line, locks the data directory and opens the storage.
+/// Returns the lock, to be held until the app exits, and whether another
+/// instance already holds it. With `--maintain`, `--import` or `--export`,
+/// does that and exits instead.
+#[cfg(not(target_arch = "wasm32"))]
+fn open_storage() -> (Arc<dyn Storage>, Option<InstanceLock>, bool) {
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // Memory storage has nothing to share, and if locking fails outright it
+    // is better to run unguarded.
+    let (lock, already_running) = match options.backend {
+        Backend::Memory => (None, false),
+        _ => match InstanceLock::acquire(&options.data_dir) {
+            Ok(Some(lock)) => (Some(lock), false),
+            Ok(None) => (None, true),
+            Err(e) => {
+                eprintln!("Could not lock {}: {}", options.data_dir.display(), e);
+                (None, false)
+            }
+        },
+    };
+
+    let storage = options.backend.open(&options.data_dir, options.db);
+
+    if let Some(action) = options.action {
+        let code = match action {
+            Action::Maintain => maintain(storage.as_ref(), already_running),
+            Action::Import(path) => import(storage.as_ref(), &path, options.format, already_running),
+            Action::Export(path) => export(storage.as_ref(), &path, options.format),
+        };
+        std::process::exit(code);
+    }
+
+    (storage, lock, already_running)
+}
+
+/// Runs `future` to completion on a runtime of its own, for the work done
+/// before or instead of the app.
+#[cfg(not(target_arch = "wasm32"))]
+fn block_on<T>(future: impl Future<Output = T>) -> Result<T, String> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map(|runtime| runtime.block_on(future))
+        .map_err(|e| e.to_string())
+}
+
+/// Runs maintenance for `--maintain` and prints the report. Returns the exit
+/// code.
+#[cfg(not(target_arch = "wasm32"))]
+fn maintain(storage: &dyn Storage, already_running: bool) -> i32 {
+    if already_running {
+        eprintln!("Another instance is using {}. Close it first.", storage.location());
+        return 1;
+    }
+
+    if !storage.supports_maintenance() {
+        eprintln!("There is nothing to maintain in {}.", storage.location());
+        return 2;
+    }
+
+    match block_on(storage.maintain()).and_then(|result| result.map_err(|e| e.to_string())) {
+        Ok(report) => {
+            print!("{}", report);
+            if let Some(path) = report.report_path {
+                println!("\nReport saved to {}", path.display());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Maintenance failed: {}", e);
+            1
+        }
+    }
+}
+
+/// Merges the tasks in `path` into the stored ones for `--import`. Returns the
+/// exit code.
+#[cfg(not(target_arch = "wasm32"))]
+fn import(storage: &dyn Storage, path: &Path, format: Option<Format>, already_running: bool) -> i32 {
+    if already_running {
+        eprintln!("Another instance is using {}. Close it first.", storage.location());
+        return 1;
+    }
+
+    let Some(format) = format.or_else(|| Format::from_path(path)) else {
+        eprintln!("Cannot tell the format of {} from its extension, use --format.", path.display());
+        return 2;
+    };
+
+    let imported = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| format.import(&contents).map_err(|e| e.to_string()));
+    let tasks = match imported {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            eprintln!("Could not import {}: {}", path.display(), e);
+            return 1;
+        }
+    };
+
+    let saved = block_on(async {
+        let state = storage.load().await.map_err(|e| e.to_string())?;
+        let merge = interchange::merge(&state.tasks, tasks, interchange::Mode::Merge);
+        let counts = (merge.added, merge.updated);
+
+        let saved = storage
+            .save(SavedChanges {
+                input_value: state.input_value,
+                filter: state.filter,
+                upserted: merge.upserted,
+                deleted: Vec::new(),
+                history: merge.history,
+                preferences: state.preferences,
+                caldav: state.caldav,
+            })
+            .await;
+        storage.close().await;
+
+        saved.map(|()| counts).map_err(|e| e.to_string())
+    });
+
+    match saved {
+        Ok(Ok((added, updated))) => {
+            println!(
+                "Imported {} new and {} updated tasks from {}",
+                added,
+                updated,
+                path.display()
+            );
+            0
+        }
+        Ok(Err(e)) | Err(e) => {
+            eprintln!("Could not import {}: {}", path.display(), e);
+            1
+        }
+    }
+}
+
+/// Writes the stored tasks to `path` for `--export`. Returns the exit code.
+#[cfg(not(target_arch = "wasm32"))]
+fn export(storage: &dyn Storage, path: &Path, format: Option<Format>) -> i32 {
+    let Some(format) = format.or_else(|| Format::from_path(path)) else {
+        eprintln!("Cannot tell the format of {} from its extension, use --format.", path.display());
+        return 2;
+    };
+
+    // Read-only, so exporting is fine while another instance runs
+    let loaded = block_on(async {
+        let state = match storage.load_read_only().await {
+            // Only JSON has a place for the history
+            Ok(state) if format == Format::Json => storage
+                .load_all_history()
+                .await
+                .map(|history| SavedState { history, ..state }),
+            state => state,
+        };
+        storage.close().await;
+        state
+    });
+
+    // Tasks that could not be read are left out rather than failing the
+    // export, which is what to reach for when the data is damaged
+    let written = loaded
+        .and_then(|state| state.map_err(|e| e.to_string()))
+        .and_then(|state| {
+            std::fs::write(path, format.export(&state))
+                .map(|()| state.skipped)
+                .map_err(|e| e.to_string())
+        });
+
+    match written {
+        Ok(0) => {
+            println!("Exported to {}", path.display());
+            0
+        }
+        Ok(skipped) => {
+            eprintln!(
+                "Exported to {}, leaving out {} tasks that could not be read.",
+                path.display(),
+                skipped
+            );
+            1
+        }
+        Err(e) => {
+            eprintln!("Could not export to {}: {}", path.display(), e);
+            1
+        }
+    }
+}
+
+/// Reads the preferences before the window opens, so it opens the way it was
+/// left. Blocks, but only on a small read.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_preferences(storage: &dyn Storage) -> Preferences {
+    block_on(storage.load_preferences()).unwrap_or_default()
+}
+
+/// `localStorage` is synchronous underneath, so the read is done by the first
+/// poll and there is no need for a runtime to block on.
+#[cfg(target_arch = "wasm32")]
+fn load_preferences(storage: &dyn Storage) -> Preferences {
+    use std::task::{Context, Poll, Waker};
+
+    match storage
+        .load_preferences()
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(preferences) => preferences,
+        Poll::Pending => Preferences::default(),
+    }
+}
+
 fn main() -> iced::Result {
     #[cfg(not(target_arch = "wasm32"))]
     tracing_subscriber::fmt::init();
@@ -17,13 +374,40 @@ fn main() -> iced::Result {
     std::sync::LazyLock::force(&i18n::LANGUAGE_LOADER);
     audio::init_audio();
 
-    iced::application(app::Todos::new, app::Todos::update, app::Todos::view)
+    // The lock is held until `run` returns
+    #[cfg(not(target_arch = "wasm32"))]
+    let (storage, _lock, already_running) = open_storage();
+    // The browser has no command line, and no data directory to lock
+    #[cfg(target_arch = "wasm32")]
+    let (storage, already_running) = (Backend::Web.open(std::path::Path::new(""), None), false);
+
+    let preferences = load_preferences(storage.as_ref());
+
+    if let Some(language) = preferences.language {
+        i18n::update_language(language);
+    }
+
+    let (width, height) = preferences.window_size.unwrap_or((500.0, 800.0));
+    let position = preferences
+        .window_position
+        .map(|(x, y)| window::Position::Specific(iced::Point::new(x, y)))
+        .unwrap_or_default();
+
+    iced::application(
+        move || app::Todos::new(storage.clone(), already_running),
+        app::Todos::update,
+        app::Todos::view,
+    )
         .subscription(app::Todos::subscription)
         .title(app::Todos::title)
         .font(app::Todos::ICON_FONT)
         .window(window::Settings {
-            size: (500.0, 800.0).into(),
+            size: (width, height).into(),
+            position,
+            fullscreen: preferences.fullscreen,
             min_size: Some((500.0, 600.0).into()),
+            // Closing goes through `Message::CloseRequested` so storage is shut down cleanly
+            exit_on_close_request: false,
             ..window::Settings::default()
         })
         .run()
@@ -0,0 +1,476 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::Utc;
+use reqwest::{header, Method, RequestBuilder, Response, StatusCode, Url};
+use roxmltree::{Document, Node};
+use uuid::Uuid;
+
+use super::{merge, Collection, Fields, Resource, SyncError, Synced};
+use crate::state::history::HistoryEntry;
+use crate::state::interchange::ical;
+use crate::state::persistence::Storage;
+use crate::task::{Task, TaskMessage};
+
+const DAV: &str = "DAV:";
+
+/// Asks for the ctag, or the sync token on servers without one. Either
+/// changes whenever anything in the collection does.
+const CTAG_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+  <d:prop><cs:getctag/><d:sync-token/></d:prop>
+</d:propfind>"#;
+
+/// Asks for the etag of every VTODO in the collection.
+const ETAG_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+
+/// A resource on the server that is new or changed since the last sync.
+struct Changed {
+    href: String,
+    etag: String,
+    task: Task,
+}
+
+/// Syncs `tasks`, trash included, with the collection. What changed on one
+/// side is taken to the other, and tasks changed on both are merged field by
+/// field. Tasks in the trash here are deleted on the server, and tasks
+/// deleted there go to the trash here. `pending` is the history not saved
+/// yet, which together with the saved history tells when local changes were
+/// made.
+pub async fn sync(
+    mut collection: Collection,
+    tasks: Vec<Task>,
+    storage: Arc<dyn Storage>,
+    pending: Vec<HistoryEntry>,
+) -> Result<Synced, SyncError> {
+    let client = Client::new(&collection)?;
+    let ctag = client.ctag().await?;
+
+    // Resources left out are gone from the server
+    let mut unchanged: HashSet<Uuid> = HashSet::new();
+    let mut changed: HashMap<Uuid, Changed> = HashMap::new();
+
+    if ctag.is_some() && ctag == collection.ctag {
+        unchanged.extend(collection.resources.keys());
+    } else {
+        let known: HashMap<&str, Uuid> = collection
+            .resources
+            .iter()
+            .map(|(id, resource)| (resource.href.as_str(), *id))
+            .collect();
+
+        for (href, etag) in client.list().await? {
+            let id = known.get(href.as_str()).copied();
+            if let Some(id) = id
+                && collection.resources[&id].etag == etag
+            {
+                unchanged.insert(id);
+                continue;
+            }
+
+            let Some((body, fresh_etag)) = client.get(&href).await? else {
+                continue;
+            };
+            match ical::import(&body).ok().and_then(|tasks| tasks.into_iter().next()) {
+                Some(task) => {
+                    changed.entry(task.id()).or_insert(Changed {
+                        href,
+                        etag: fresh_etag.unwrap_or(etag),
+                        task,
+                    });
+                }
+                // Left as it was rather than taken for gone
+                None => unchanged.extend(id),
+            }
+        }
+    }
+
+    let local: HashMap<Uuid, &Task> = tasks.iter().map(|task| (task.id(), task)).collect();
+    let mut ids: Vec<Uuid> = tasks.iter().map(Task::id).collect();
+    ids.extend(collection.resources.keys().filter(|id| !local.contains_key(id)));
+    ids.extend(
+        changed
+            .keys()
+            .filter(|id| !local.contains_key(id) && !collection.resources.contains_key(id)),
+    );
+
+    let mut updated = Vec::new();
+    let mut added = Vec::new();
+
+    for id in ids {
+        let task = local.get(&id).copied();
+        let resource = collection.resources.get(&id).cloned();
+        let remote = changed.remove(&id);
+        let is_unchanged = unchanged.contains(&id);
+
+        match (task, resource, remote) {
+            // Changed on the server, and maybe here too
+            (Some(task), resource, Some(remote)) => {
+                // Into the trash after the server's last change
+                if task.is_deleted() && task.deleted_at() > ical::last_modified(&remote.task) {
+                    if client.delete(&remote.href, &remote.etag).await? {
+                        collection.resources.remove(&id);
+                    }
+                    continue;
+                }
+
+                let history = if Fields::of(task) != Fields::of(&remote.task) {
+                    local_history(storage.as_ref(), &pending, id).await
+                } else {
+                    Vec::new()
+                };
+                let merged = merge(task, &remote.task, resource.as_ref().map(|resource| &resource.synced), &history);
+
+                let etag = if Fields::of(&merged) != Fields::of(&remote.task) {
+                    match client.put(&remote.href, &merged, Some(&remote.etag)).await? {
+                        Some(etag) => etag,
+                        // Changed again meanwhile, left for the next sync
+                        None => continue,
+                    }
+                } else {
+                    remote.etag
+                };
+
+                collection.resources.insert(
+                    id,
+                    Resource {
+                        href: remote.href,
+                        etag,
+                        synced: Fields::of(&merged),
+                    },
+                );
+                if differs(task, &merged) {
+                    updated.push((task.clone(), merged));
+                }
+            }
+            // Synced before and unchanged on the server since
+            (Some(task), Some(resource), None) if is_unchanged => {
+                if task.is_deleted() {
+                    if client.delete(&resource.href, &resource.etag).await? {
+                        collection.resources.remove(&id);
+                    }
+                } else if Fields::of(task) != resource.synced
+                    && let Some(etag) = client.put(&resource.href, task, Some(&resource.etag)).await?
+                {
+                    collection.resources.insert(
+                        id,
+                        Resource {
+                            etag,
+                            synced: Fields::of(task),
+                            ..resource
+                        },
+                    );
+                }
+            }
+            // Deleted from the server. Changes made here since bring it back
+            (Some(task), Some(resource), None) => {
+                collection.resources.remove(&id);
+
+                if task.is_deleted() {
+                    continue;
+                }
+                if Fields::of(task) == resource.synced {
+                    let mut trashed = task.clone();
+                    trashed.update(TaskMessage::Delete);
+                    updated.push((task.clone(), trashed));
+                } else if let Some(etag) = client.put(&resource.href, task, None).await? {
+                    collection.resources.insert(
+                        id,
+                        Resource {
+                            href: resource.href,
+                            etag,
+                            synced: Fields::of(task),
+                        },
+                    );
+                }
+            }
+            // New here
+            (Some(task), None, None) => {
+                if task.is_deleted() {
+                    continue;
+                }
+
+                let href = client.new_href(id);
+                if let Some(etag) = client.put(&href, task, None).await? {
+                    collection.resources.insert(
+                        id,
+                        Resource {
+                            href,
+                            etag,
+                            synced: Fields::of(task),
+                        },
+                    );
+                }
+            }
+            // New on the server, or changed there after it was deleted here
+            (None, _, Some(remote)) => {
+                collection.resources.insert(
+                    id,
+                    Resource {
+                        href: remote.href,
+                        etag: remote.etag,
+                        synced: Fields::of(&remote.task),
+                    },
+                );
+                added.push(remote.task);
+            }
+            // Deleted here for good
+            (None, Some(resource), None) => {
+                if !is_unchanged || client.delete(&resource.href, &resource.etag).await? {
+                    collection.resources.remove(&id);
+                }
+            }
+            (None, None, None) => {}
+        }
+    }
+
+    // As it was before anything was written, so what others wrote meanwhile
+    // is not missed
+    collection.ctag = ctag;
+
+    Ok(Synced {
+        collection,
+        updated,
+        added,
+    })
+}
+
+/// The recorded changes to a task, saved or not. Without any, changes made
+/// here count as older than the server's.
+async fn local_history(storage: &dyn Storage, pending: &[HistoryEntry], id: Uuid) -> Vec<HistoryEntry> {
+    let mut history = storage.load_history(id).await.unwrap_or_default();
+    let unsaved: Vec<HistoryEntry> = pending
+        .iter()
+        .filter(|entry| entry.task_id == id && !history.contains(entry))
+        .cloned()
+        .collect();
+
+    history.extend(unsaved);
+    history
+}
+
+/// Whether a merge changed anything of the task as it is here.
+fn differs(task: &Task, merged: &Task) -> bool {
+    Fields::of(task) != Fields::of(merged)
+        || task.is_deleted()
+        || task.completed_at() != merged.completed_at()
+        || task.created_at() != merged.created_at()
+        || task.extra() != merged.extra()
+}
+
+/// WebDAV methods, which `reqwest` has no constants for.
+fn extension(name: &'static str) -> Method {
+    Method::from_bytes(name.as_bytes()).expect("a valid method name")
+}
+
+fn etag_of(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(String::from)
+}
+
+/// Whether `node` is the DAV element `name`.
+fn is_dav(node: &Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().namespace() == Some(DAV) && node.tag_name().name() == name
+}
+
+fn dav_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| is_dav(child, name))
+}
+
+struct Client {
+    http: reqwest::Client,
+    /// Ends in `/`, so that resources resolve inside it.
+    url: Url,
+    username: String,
+    password: String,
+}
+
+impl Client {
+    fn new(collection: &Collection) -> Result<Self, SyncError> {
+        let mut url = Url::parse(collection.url.trim()).map_err(SyncError::connection)?;
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            url,
+            username: collection.username.clone(),
+            password: collection.password.clone(),
+        })
+    }
+
+    /// Where a task that is not on the server yet goes.
+    fn new_href(&self, id: Uuid) -> String {
+        format!("{}{}.ics", self.url.path(), id)
+    }
+
+    /// The path of `href`, so that hrefs from the server compare equal to the
+    /// stored ones however they are written.
+    fn path(&self, href: &str) -> Result<String, SyncError> {
+        self.url
+            .join(href)
+            .map(|url| url.path().to_string())
+            .map_err(SyncError::format)
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        href: &str,
+        build: impl FnOnce(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, SyncError> {
+        let url = self.url.join(href).map_err(SyncError::format)?;
+        let request = self
+            .http
+            .request(method, url)
+            .basic_auth(&self.username, Some(&self.password));
+
+        let response = build(request).send().await.map_err(SyncError::connection)?;
+        match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(SyncError::Unauthorized),
+            _ => Ok(response),
+        }
+    }
+
+    /// Sends a PROPFIND or REPORT to the collection, and reads the properties
+    /// found in the answer by resource path.
+    async fn properties(
+        &self,
+        method: &'static str,
+        depth: &'static str,
+        query: &'static str,
+    ) -> Result<HashMap<String, HashMap<String, String>>, SyncError> {
+        let response = self
+            .send(extension(method), "", |request| {
+                request
+                    .header("Depth", depth)
+                    .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+                    .body(query)
+            })
+            .await?;
+
+        if response.status() != StatusCode::MULTI_STATUS {
+            return Err(SyncError::Status(response.status().as_u16()));
+        }
+
+        let text = response.text().await.map_err(SyncError::connection)?;
+        let document = Document::parse(&text).map_err(SyncError::format)?;
+        let mut found = HashMap::new();
+
+        for response in document.descendants().filter(|node| is_dav(node, "response")) {
+            let Some(href) = dav_child(response, "href").and_then(|href| href.text()) else {
+                continue;
+            };
+
+            let mut properties = HashMap::new();
+            for propstat in response.children().filter(|node| is_dav(node, "propstat")) {
+                // Properties the server does not have come back as 404
+                let found = dav_child(propstat, "status")
+                    .and_then(|status| status.text())
+                    .is_none_or(|status| status.contains(" 200 "));
+
+                if let Some(prop) = dav_child(propstat, "prop").filter(|_| found) {
+                    for property in prop.children().filter(Node::is_element) {
+                        if let Some(value) = property.text() {
+                            properties.insert(property.tag_name().name().to_string(), value.trim().to_string());
+                        }
+                    }
+                }
+            }
+
+            found.insert(self.path(href.trim())?, properties);
+        }
+
+        Ok(found)
+    }
+
+    async fn ctag(&self) -> Result<Option<String>, SyncError> {
+        let found = self.properties("PROPFIND", "0", CTAG_QUERY).await?;
+
+        Ok(found.into_values().find_map(|mut properties| {
+            properties
+                .remove("getctag")
+                .or_else(|| properties.remove("sync-token"))
+        }))
+    }
+
+    /// The path and etag of every VTODO in the collection.
+    async fn list(&self) -> Result<HashMap<String, String>, SyncError> {
+        let found = self.properties("REPORT", "1", ETAG_QUERY).await?;
+
+        Ok(found
+            .into_iter()
+            .filter(|(path, _)| path != self.url.path())
+            .filter_map(|(path, mut properties)| Some((path, properties.remove("getetag")?)))
+            .collect())
+    }
+
+    /// A resource and its etag, or `None` if it is gone.
+    async fn get(&self, href: &str) -> Result<Option<(String, Option<String>)>, SyncError> {
+        let response = self.send(Method::GET, href, |request| request).await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let etag = etag_of(&response);
+                let body = response.text().await.map_err(SyncError::connection)?;
+                Ok(Some((body, etag)))
+            }
+            status => Err(SyncError::Status(status.as_u16())),
+        }
+    }
+
+    /// Writes the task, if its resource is still at `etag`, or for `None` if
+    /// there is none yet. Returns the new etag, or `None` if the resource
+    /// changed meanwhile.
+    async fn put(&self, href: &str, task: &Task, etag: Option<&str>) -> Result<Option<String>, SyncError> {
+        let mut task = task.clone();
+        ical::set_last_modified(&mut task, Utc::now());
+        let body = ical::export([&task]);
+
+        let response = self
+            .send(Method::PUT, href, |request| {
+                let request = request
+                    .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+                    .body(body);
+                match etag {
+                    Some(etag) => request.header(header::IF_MATCH, etag),
+                    None => request.header(header::IF_NONE_MATCH, "*"),
+                }
+            })
+            .await?;
+
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(None),
+            // Servers that change what they were sent leave the etag out
+            status if status.is_success() => Ok(Some(etag_of(&response).unwrap_or_default())),
+            status => Err(SyncError::Status(status.as_u16())),
+        }
+    }
+
+    /// Deletes a resource if it is still at `etag`. Returns whether it is
+    /// gone, as it also is if someone else deleted it first.
+    async fn delete(&self, href: &str, etag: &str) -> Result<bool, SyncError> {
+        let response = self
+            .send(Method::DELETE, href, |request| request.header(header::IF_MATCH, etag))
+            .await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(true),
+            StatusCode::PRECONDITION_FAILED => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(SyncError::Status(status.as_u16())),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! A CalDAV server for the sync tests, running in the test's own runtime. It
+//! knows just enough of the protocol for what the client sends: PROPFIND for
+//! the ctag, REPORT for the etags, and GET, PUT and DELETE with preconditions.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+pub const COLLECTION: &str = "/dav/calendars/me/tasks/";
+pub const USERNAME: &str = "me";
+pub const PASSWORD: &str = "secret";
+
+/// `me:secret` as Basic authentication sends it.
+const AUTHORIZATION: &str = "Basic bWU6c2VjcmV0";
+
+/// What is on the server, shared with the test.
+#[derive(Debug, Default)]
+pub struct Server {
+    /// The body and etag of every resource, by path.
+    pub resources: BTreeMap<String, (String, String)>,
+    /// Every request as `METHOD path`, in order.
+    pub requests: Vec<String>,
+    /// A resource another client writes to just before the next PUT to it
+    /// is handled, with the body it writes.
+    pub write_first: Option<(String, String)>,
+    ctag: u64,
+    last_etag: u64,
+}
+
+impl Server {
+    /// Writes a resource as another client would.
+    pub fn put(&mut self, path: &str, body: &str) {
+        self.last_etag += 1;
+        self.ctag += 1;
+        self.resources
+            .insert(path.to_string(), (body.to_string(), format!("\"{}\"", self.last_etag)));
+    }
+
+    pub fn delete(&mut self, path: &str) {
+        self.resources.remove(path);
+        self.ctag += 1;
+    }
+
+    pub fn body(&self, path: &str) -> &str {
+        &self.resources[path].0
+    }
+
+    /// The requests since the last call.
+    pub fn take_requests(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.requests)
+    }
+
+    fn handle(&mut self, request: &Request) -> Response {
+        self.requests.push(format!("{} {}", request.method, request.path));
+
+        if request.headers.get("authorization").map(String::as_str) != Some(AUTHORIZATION) {
+            return Response::status("401 Unauthorized");
+        }
+
+        match request.method.as_str() {
+            "PROPFIND" => Response::multistatus(format!(
+                "<d:response><d:href>{}</d:href>\
+                 <d:propstat><d:prop><cs:getctag>{}</cs:getctag></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>\
+                 <d:propstat><d:prop><d:sync-token/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>\
+                 </d:response>",
+                COLLECTION, self.ctag
+            )),
+            "REPORT" => Response::multistatus(
+                self.resources
+                    .iter()
+                    .map(|(path, (_, etag))| {
+                        format!(
+                            "<d:response><d:href>{}</d:href><d:propstat><d:prop><d:getetag>{}</d:getetag></d:prop>\
+                             <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+                            path,
+                            etag.replace('"', "&quot;")
+                        )
+                    })
+                    .collect(),
+            ),
+            "GET" => match self.resources.get(&request.path) {
+                Some((body, etag)) => Response {
+                    status: "200 OK",
+                    etag: Some(etag.clone()),
+                    body: body.clone(),
+                },
+                None => Response::status("404 Not Found"),
+            },
+            "PUT" => {
+                if let Some((path, body)) = self.write_first.take_if(|(path, _)| *path == request.path) {
+                    self.put(&path, &body);
+                }
+
+                let current = self.resources.get(&request.path).map(|(_, etag)| etag);
+                let precondition = match (request.headers.get("if-match"), request.headers.get("if-none-match")) {
+                    (Some(etag), _) => current == Some(etag),
+                    (None, Some(_)) => current.is_none(),
+                    (None, None) => true,
+                };
+                if !precondition {
+                    return Response::status("412 Precondition Failed");
+                }
+
+                let created = current.is_none();
+                self.put(&request.path, &request.body);
+                Response {
+                    status: if created { "201 Created" } else { "204 No Content" },
+                    etag: Some(self.resources[&request.path].1.clone()),
+                    body: String::new(),
+                }
+            }
+            "DELETE" => match self.resources.get(&request.path) {
+                None => Response::status("404 Not Found"),
+                Some((_, etag)) if request.headers.get("if-match").is_some_and(|expected| expected != etag) => {
+                    Response::status("412 Precondition Failed")
+                }
+                Some(_) => {
+                    self.delete(&request.path);
+                    Response::status("204 No Content")
+                }
+            },
+            _ => Response::status("405 Method Not Allowed"),
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    /// By lower-cased name.
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+struct Response {
+    status: &'static str,
+    etag: Option<String>,
+    body: String,
+}
+
+impl Response {
+    fn status(status: &'static str) -> Self {
+        Self {
+            status,
+            etag: None,
+            body: String::new(),
+        }
+    }
+
+    fn multistatus(responses: String) -> Self {
+        Self {
+            status: "207 Multi-Status",
+            etag: None,
+            body: format!(
+                r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">{}</d:multistatus>"#,
+                responses
+            ),
+        }
+    }
+}
+
+/// Starts a server with an empty collection. Returns the collection's URL.
+pub async fn start() -> (String, Arc<Mutex<Server>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}{}", listener.local_addr().unwrap(), COLLECTION);
+    let server = Arc::new(Mutex::new(Server::default()));
+
+    let shared = server.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, shared.clone()));
+        }
+    });
+
+    (url, server)
+}
+
+/// Answers the requests on a connection until the client closes it.
+async fn serve(stream: tokio::net::TcpStream, server: Arc<Mutex<Server>>) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+
+    while let Some(request) = read_request(&mut reader).await {
+        let response = server.lock().unwrap().handle(&request);
+
+        let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", response.status, response.body.len());
+        if let Some(etag) = response.etag {
+            head.push_str(&format!("ETag: {}\r\n", etag));
+        }
+        head.push_str("\r\n");
+
+        if write.write_all((head + &response.body).as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn read_request(reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>) -> Option<Request> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = BTreeMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await.ok()?;
+        let Some((name, value)) = header.trim_end().split_once(':') else {
+            break;
+        };
+        headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let length = headers.get("content-length").map_or(Ok(0), |length| length.parse()).ok()?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.ok()?;
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8(body).ok()?,
+    })
+}
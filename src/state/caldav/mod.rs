@@ -0,0 +1,427 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod client;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod fake_server;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::history::{Change, HistoryEntry};
+use super::interchange::ical;
+use crate::task::Task;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use client::sync;
+
+/// A CalDAV collection the tasks are kept in sync with, and what was on it
+/// after the last sync.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collection {
+    /// E.g. `https://example.com/remote.php/dav/calendars/me/tasks/`.
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    /// The collection's ctag as of the last sync. While it stays the same
+    /// nothing on the server changed, so there is no need to list it.
+    pub ctag: Option<String>,
+    /// What was on the server after the last sync, by task id.
+    pub resources: BTreeMap<Uuid, Resource>,
+}
+
+/// A task's resource on the server, as of the last sync.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resource {
+    /// The path on the server.
+    pub href: String,
+    /// Empty if the server did not give one.
+    pub etag: String,
+    /// The task as both sides had it. What changed since, on either side, is
+    /// worked out against it.
+    pub synced: Fields,
+}
+
+/// What is synced of a task. The trash is not: tasks in it are deleted from
+/// the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fields {
+    pub description: String,
+    pub completed: bool,
+}
+
+impl Fields {
+    pub fn of(task: &Task) -> Self {
+        Self {
+            description: task.description().to_string(),
+            completed: task.completed(),
+        }
+    }
+}
+
+impl Collection {
+    pub fn new(url: String, username: String, password: String) -> Self {
+        Self {
+            url,
+            username,
+            password,
+            ..Self::default()
+        }
+    }
+
+    /// Whether `other` is the same collection signed in as the same user, so
+    /// what was synced so far still holds.
+    pub fn same_account(&self, other: &Collection) -> bool {
+        self.url == other.url && self.username == other.username
+    }
+}
+
+/// What a sync did, to apply to the tasks as they are once it is done.
+#[derive(Debug, Clone)]
+pub struct Synced {
+    /// To keep for the next sync.
+    pub collection: Collection,
+    /// Tasks the sync changed, as they were when it started and as it left
+    /// them.
+    pub updated: Vec<(Task, Task)>,
+    /// Tasks that were new on the server.
+    pub added: Vec<Task>,
+}
+
+/// Why a sync stopped. What it did on the server before that is picked up by
+/// the next one.
+#[derive(Debug, Clone)]
+pub enum SyncError {
+    /// The server could not be reached.
+    Connection(String),
+    /// The server refused the user name or password.
+    Unauthorized,
+    /// The server answered with a status the sync cannot go on from.
+    Status(u16),
+    /// The server's answer could not be read.
+    Format(String),
+}
+
+impl SyncError {
+    pub fn connection(cause: impl fmt::Display) -> Self {
+        SyncError::Connection(cause.to_string())
+    }
+
+    pub fn format(cause: impl fmt::Display) -> Self {
+        SyncError::Format(cause.to_string())
+    }
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Connection(cause) => write!(f, "Could not reach the server: {}", cause),
+            SyncError::Unauthorized => write!(f, "The server refused the user name or password"),
+            SyncError::Status(status) => write!(f, "The server answered with status {}", status),
+            SyncError::Format(cause) => write!(f, "The server's answer is malformed: {}", cause),
+        }
+    }
+}
+
+/// There is no syncing from the browser, which would refuse to talk to most
+/// servers anyway.
+#[cfg(target_arch = "wasm32")]
+pub async fn sync(
+    _collection: Collection,
+    _tasks: Vec<Task>,
+    _storage: std::sync::Arc<dyn super::persistence::Storage>,
+    _pending: Vec<HistoryEntry>,
+) -> Result<Synced, SyncError> {
+    Err(SyncError::connection("not available in the browser"))
+}
+
+/// Combines `local` and `remote`, the same task as each side has it. A field
+/// changed on one side only takes that side's value. One changed on both, or
+/// that differs with nothing synced before, takes the value of the side that
+/// changed it last: `history` tells for this side, the VTODO's LAST-MODIFIED
+/// for the server's. Ties go to the server. The result is out of the trash.
+pub fn merge(local: &Task, remote: &Task, synced: Option<&Fields>, history: &[HistoryEntry]) -> Task {
+    let remote_at = ical::last_modified(remote);
+    let changed_here = |kind: fn(&Change) -> bool, fallback: Option<DateTime<Utc>>| {
+        history
+            .iter()
+            .filter(|entry| kind(&entry.change))
+            .map(|entry| entry.at)
+            .max()
+            .or(fallback)
+    };
+
+    let description = if keep_local(
+        &local.description(),
+        &remote.description(),
+        synced.map(|synced| synced.description.as_str()).as_ref(),
+        || {
+            let kind = |change: &Change| matches!(change, Change::Created { .. } | Change::Renamed { .. });
+            changed_here(kind, local.created_at()) > remote_at
+        },
+    ) {
+        local.description()
+    } else {
+        remote.description()
+    };
+
+    let done = if keep_local(
+        &local.completed(),
+        &remote.completed(),
+        synced.map(|synced| synced.completed).as_ref(),
+        || changed_here(|change| matches!(change, Change::Completed(_)), local.completed_at()) > remote_at,
+    ) {
+        local
+    } else {
+        remote
+    };
+
+    let mut merged = Task::from_parts(
+        local.id(),
+        description.to_string(),
+        done.completed(),
+        local.position(),
+        None,
+        local.created_at().or(remote.created_at()),
+        done.completed_at(),
+    );
+
+    // What other apps keep on the task is the server's to say
+    *merged.extra_mut() = local.extra().clone();
+    match remote.extra().get(ical::EXTRA_KEY) {
+        Some(kept) => merged.extra_mut().insert(ical::EXTRA_KEY.to_string(), kept.clone()),
+        None => merged.extra_mut().remove(ical::EXTRA_KEY),
+    };
+
+    merged
+}
+
+/// Whether a field keeps the local value, given the synced one if there is
+/// any. `local_is_newer` is only asked when both sides changed it.
+fn keep_local<T: PartialEq>(local: &T, remote: &T, synced: Option<&T>, local_is_newer: impl FnOnce() -> bool) -> bool {
+    if local == remote {
+        return true;
+    }
+
+    match synced {
+        Some(synced) if local == synced => false,
+        Some(synced) if remote == synced => true,
+        _ => local_is_newer(),
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::Duration;
+
+    use super::fake_server::{self, Server};
+    use super::*;
+    use crate::state::persistence::memory_persistence::MemoryStorage;
+    use crate::state::persistence::{edit_task, find, rename};
+    use crate::state::State;
+    use crate::task::TaskMessage;
+
+    /// A state with `descriptions` as tasks, synced once with a fresh server.
+    async fn synced_state(descriptions: &[&str]) -> (State, Arc<Mutex<Server>>) {
+        let (url, server) = fake_server::start().await;
+        let mut state = State::new(Arc::new(MemoryStorage::default()));
+        for description in descriptions {
+            state.add_task(Task::new(description.to_string()));
+        }
+        state.caldav = Some(Collection::new(
+            url,
+            fake_server::USERNAME.to_string(),
+            fake_server::PASSWORD.to_string(),
+        ));
+
+        run_sync(&mut state).await;
+        server.lock().unwrap().take_requests();
+
+        (state, server)
+    }
+
+    /// Syncs as the app does, and applies the result.
+    async fn run_sync(state: &mut State) {
+        let pending = state
+            .tasks
+            .iter()
+            .flat_map(|task| state.changes.history_of(task.id()))
+            .cloned()
+            .collect();
+        let synced = sync(state.caldav.clone().unwrap(), state.tasks.clone(), state.storage.clone(), pending)
+            .await
+            .unwrap();
+
+        state.apply_sync(synced);
+    }
+
+    /// Changes a task as the user would, history included.
+    fn edit(state: &mut State, description: &str, messages: Vec<TaskMessage>) {
+        let index = state
+            .tasks
+            .iter()
+            .position(|task| task.description() == description)
+            .unwrap();
+
+        for entry in edit_task(&mut state.tasks[index], messages) {
+            state.record(entry);
+        }
+    }
+
+    fn task<'a>(state: &'a State, description: &str) -> &'a Task {
+        find(&state.tasks, description)
+    }
+
+    fn path_of(state: &State, description: &str) -> String {
+        format!("{}{}.ics", fake_server::COLLECTION, task(state, description).id())
+    }
+
+    /// Sets a property of the VTODO, as another client would write it.
+    fn with_property(body: &str, name: &str, value: &str) -> String {
+        let mut lines: Vec<String> = body
+            .replace("\r\n ", "")
+            .lines()
+            .filter(|line| !line.starts_with(&format!("{}:", name)))
+            .map(String::from)
+            .collect();
+        let end = lines.iter().position(|line| line == "END:VTODO").unwrap();
+        lines.insert(end, format!("{}:{}", name, value));
+
+        lines.join("\r\n") + "\r\n"
+    }
+
+    /// The task renamed on the server, at `at`.
+    fn renamed_remotely(server: &Mutex<Server>, path: &str, description: &str, at: DateTime<Utc>) -> String {
+        let server = server.lock().unwrap();
+        let body = with_property(server.body(path), "SUMMARY", description);
+
+        with_property(&body, "LAST-MODIFIED", &at.format("%Y%m%dT%H%M%SZ").to_string())
+    }
+
+    fn methods(requests: &[String]) -> Vec<&str> {
+        requests
+            .iter()
+            .map(|request| request.split(' ').next().unwrap_or_default())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn only_what_changed_is_fetched() {
+        let (mut state, server) = synced_state(&["Buy milk", "Call mom"]).await;
+        assert_eq!(server.lock().unwrap().resources.len(), 2);
+
+        // The ctag moved with our own writes, but the etags did not
+        run_sync(&mut state).await;
+        assert_eq!(methods(&server.lock().unwrap().take_requests()), ["PROPFIND", "REPORT"]);
+
+        // Nothing changed since, so the collection is not listed
+        run_sync(&mut state).await;
+        assert_eq!(methods(&server.lock().unwrap().take_requests()), ["PROPFIND"]);
+
+        // Only the resource another client changed is fetched
+        let path = path_of(&state, "Call mom");
+        let body = renamed_remotely(&server, &path, "Call mum", Utc::now());
+        server.lock().unwrap().put(&path, &body);
+
+        run_sync(&mut state).await;
+        assert_eq!(
+            server.lock().unwrap().take_requests(),
+            ["PROPFIND /dav/calendars/me/tasks/", "REPORT /dav/calendars/me/tasks/", &format!("GET {}", path)]
+        );
+        task(&state, "Call mum");
+    }
+
+    #[tokio::test]
+    async fn a_resource_changed_meanwhile_is_merged_on_the_next_sync() {
+        let (mut state, server) = synced_state(&["Buy milk"]).await;
+        let path = path_of(&state, "Buy milk");
+
+        // Another client renames it between our listing and our write
+        edit(&mut state, "Buy milk", vec![TaskMessage::Completed(true)]);
+        let theirs = renamed_remotely(&server, &path, "Buy oat milk", Utc::now());
+        server.lock().unwrap().write_first = Some((path.clone(), theirs.clone()));
+
+        run_sync(&mut state).await;
+        let requests = server.lock().unwrap().take_requests();
+        assert_eq!(methods(&requests), ["PROPFIND", "REPORT", "PUT"]);
+        // Refused by If-Match, so theirs stands and ours waits
+        assert_eq!(server.lock().unwrap().body(&path), theirs);
+        assert!(task(&state, "Buy milk").completed());
+
+        run_sync(&mut state).await;
+        let milk = task(&state, "Buy oat milk");
+        assert!(milk.completed());
+        let body = server.lock().unwrap().body(&path).replace("\r\n ", "");
+        assert!(body.contains("SUMMARY:Buy oat milk"), "{}", body);
+        assert!(body.contains("STATUS:COMPLETED"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn fields_are_merged_one_by_one() {
+        let (mut state, server) = synced_state(&["Buy milk"]).await;
+        let path = path_of(&state, "Buy milk");
+
+        // Different fields on each side: both are kept
+        let body = renamed_remotely(&server, &path, "Buy oat milk", Utc::now());
+        server.lock().unwrap().put(&path, &body);
+        edit(&mut state, "Buy milk", vec![TaskMessage::Completed(true)]);
+
+        run_sync(&mut state).await;
+        assert!(task(&state, "Buy oat milk").completed());
+        let body = server.lock().unwrap().body(&path).replace("\r\n ", "");
+        assert!(body.contains("SUMMARY:Buy oat milk") && body.contains("STATUS:COMPLETED"), "{}", body);
+
+        // The same field on both: the server changed it last, so it wins
+        edit(&mut state, "Buy oat milk", rename("Buy soy milk"));
+        let body = renamed_remotely(&server, &path, "Buy rice milk", Utc::now() + Duration::hours(1));
+        server.lock().unwrap().put(&path, &body);
+
+        run_sync(&mut state).await;
+        task(&state, "Buy rice milk");
+
+        // The same field on both: this side changed it last, so it wins and
+        // is written back
+        edit(&mut state, "Buy rice milk", rename("Buy almond milk"));
+        let body = renamed_remotely(&server, &path, "Buy goat milk", Utc::now() - Duration::hours(1));
+        server.lock().unwrap().put(&path, &body);
+
+        run_sync(&mut state).await;
+        assert!(task(&state, "Buy almond milk").completed());
+        let body = server.lock().unwrap().body(&path).replace("\r\n ", "");
+        assert!(body.contains("SUMMARY:Buy almond milk"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn deletes_are_passed_on() {
+        let (mut state, server) = synced_state(&["Buy milk", "Call mom", "Pay rent"]).await;
+        let milk = path_of(&state, "Buy milk");
+        let mom = path_of(&state, "Call mom");
+        let rent = path_of(&state, "Pay rent");
+
+        // Deleted on the server: unchanged here, so it goes to the trash
+        server.lock().unwrap().delete(&milk);
+        // Deleted there but changed here since: it comes back
+        server.lock().unwrap().delete(&mom);
+        edit(&mut state, "Call mom", vec![TaskMessage::Completed(true)]);
+        // Put in the trash here: deleted there
+        edit(&mut state, "Pay rent", vec![TaskMessage::Delete]);
+
+        run_sync(&mut state).await;
+
+        assert!(task(&state, "Buy milk").is_deleted());
+        assert!(!task(&state, "Call mom").is_deleted());
+        let server = server.lock().unwrap();
+        assert!(!server.resources.contains_key(&milk));
+        assert!(server.body(&mom).contains("STATUS:COMPLETED"));
+        assert!(!server.resources.contains_key(&rent));
+
+        let resources = &state.caldav.as_ref().unwrap().resources;
+        assert_eq!(resources.len(), 1);
+        assert!(resources.contains_key(&task(&state, "Call mom").id()));
+    }
+}
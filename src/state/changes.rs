@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+use uuid::Uuid;
+use crate::task::Task;
+use super::history::HistoryEntry;
+
+/// Tracks which tasks changed since the last save, keyed by `Task::id()`,
+/// along with the history entries recorded for those changes.
+///
+/// Changes handed to a save stay in flight until the save is confirmed, so a
+/// failed save can put them back for the next attempt.
+#[derive(Debug, Default)]
+pub struct ChangeSet {
+    upserted: HashSet<Uuid>,
+    deleted: HashSet<Uuid>,
+    in_flight_upserted: HashSet<Uuid>,
+    in_flight_deleted: HashSet<Uuid>,
+    history: Vec<HistoryEntry>,
+    in_flight_history: Vec<HistoryEntry>,
+}
+
+impl ChangeSet {
+    pub fn upsert(&mut self, id: Uuid) {
+        self.deleted.remove(&id);
+        self.upserted.insert(id);
+    }
+
+    pub fn delete(&mut self, id: Uuid) {
+        self.upserted.remove(&id);
+        self.deleted.insert(id);
+    }
+
+    pub fn record(&mut self, entry: HistoryEntry) {
+        self.history.push(entry);
+    }
+
+    /// History entries of the task that are not saved yet, oldest first.
+    pub fn history_of(&self, id: Uuid) -> impl Iterator<Item = &HistoryEntry> {
+        self.in_flight_history
+            .iter()
+            .chain(&self.history)
+            .filter(move |entry| entry.task_id == id)
+    }
+
+    /// Whether the task has changes that are pending or in flight.
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.upserted.contains(&id)
+            || self.deleted.contains(&id)
+            || self.in_flight_upserted.contains(&id)
+            || self.in_flight_deleted.contains(&id)
+    }
+
+    /// Moves the pending changes in flight, returning the current version of
+    /// every upserted task together with the ids that were deleted and the
+    /// history entries to append.
+    pub fn take(&mut self, tasks: &[Task]) -> (Vec<Task>, Vec<Uuid>, Vec<HistoryEntry>) {
+        self.in_flight_upserted.extend(self.upserted.drain());
+        self.in_flight_deleted.extend(self.deleted.drain());
+        self.in_flight_history.append(&mut self.history);
+
+        let upserted = tasks
+            .iter()
+            .filter(|task| self.in_flight_upserted.contains(&task.id()))
+            .cloned()
+            .collect();
+
+        (
+            upserted,
+            self.in_flight_deleted.iter().copied().collect(),
+            self.in_flight_history.clone(),
+        )
+    }
+
+    /// The in-flight changes were saved.
+    pub fn confirm(&mut self) {
+        self.in_flight_upserted.clear();
+        self.in_flight_deleted.clear();
+        self.in_flight_history.clear();
+    }
+
+    /// The in-flight changes failed to save. Queues them again, unless a newer
+    /// change to the same task has replaced them in the meantime.
+    pub fn restore(&mut self) {
+        for id in self.in_flight_upserted.drain() {
+            if !self.deleted.contains(&id) {
+                self.upserted.insert(id);
+            }
+        }
+
+        for id in self.in_flight_deleted.drain() {
+            if !self.upserted.contains(&id) {
+                self.deleted.insert(id);
+            }
+        }
+
+        // Ahead of the entries recorded since, to keep them in order
+        self.in_flight_history.append(&mut self.history);
+        self.history = std::mem::take(&mut self.in_flight_history);
+    }
+}
@@ -7,14 +7,17 @@ pub enum Filter {
     All,
     Active,
     Completed,
+    /// Tasks that were deleted and not purged yet. No other filter shows them.
+    Trash,
 }
 
 impl Filter {
     pub fn matches(self, task: &Task) -> bool {
         match self {
-            Filter::All => true,
-            Filter::Active => !task.completed(),
-            Filter::Completed => task.completed(),
+            Filter::All => !task.is_deleted(),
+            Filter::Active => !task.is_deleted() && !task.completed(),
+            Filter::Completed => !task.is_deleted() && task.completed(),
+            Filter::Trash => task.is_deleted(),
         }
     }
 }
\ No newline at end of file
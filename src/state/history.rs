@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::task::Task;
+
+/// One recorded change to a task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub task_id: Uuid,
+    pub at: DateTime<Utc>,
+    pub change: Change,
+}
+
+/// What changed, with the values before and after where there are any.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Change {
+    Created { description: String },
+    Renamed { from: String, to: String },
+    Completed(bool),
+    Deleted,
+    Restored,
+}
+
+impl HistoryEntry {
+    pub fn new(task: &Task, change: Change) -> Self {
+        Self {
+            task_id: task.id(),
+            at: Utc::now(),
+            change,
+        }
+    }
+}
+
+impl Change {
+    /// The description the task had right after the change, if the change
+    /// set one. These are the versions a description can be reverted to.
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Change::Created { description } => Some(description),
+            Change::Renamed { to, .. } => Some(to),
+            Change::Completed(_) | Change::Deleted | Change::Restored => None,
+        }
+    }
+}
+
+/// The timeline of one task, shown in a panel above the list.
+#[derive(Debug, Clone)]
+pub struct HistoryPanel {
+    pub task_id: Uuid,
+    /// Oldest first. `None` while loading.
+    pub entries: Option<Vec<HistoryEntry>>,
+    pub failed: bool,
+}
+
+impl HistoryPanel {
+    pub fn new(task_id: Uuid) -> Self {
+        Self {
+            task_id,
+            entries: None,
+            failed: false,
+        }
+    }
+
+    /// Adds a change made while the panel is open.
+    pub fn record(&mut self, entry: &HistoryEntry) {
+        if entry.task_id == self.task_id
+            && let Some(entries) = &mut self.entries
+        {
+            entries.push(entry.clone());
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::state::persistence::LoadError;
+use crate::task::Task;
+
+/// The columns written, in order. Imports find them by name, so they may come
+/// in any order and all but `description` may be missing.
+const COLUMNS: [&str; 8] = [
+    "id",
+    "description",
+    "completed",
+    "position",
+    "created_at",
+    "completed_at",
+    "deleted_at",
+    "extra",
+];
+
+/// A header row, then one row per task, the trash included. Times are RFC
+/// 3339 in UTC and `extra` is a JSON object.
+pub fn export<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    let mut csv = record(COLUMNS.iter().map(|column| column.to_string()));
+
+    for task in tasks {
+        let time = |time: Option<DateTime<Utc>>| time.map(|time| time.to_rfc3339()).unwrap_or_default();
+        let extra = if task.extra().is_empty() {
+            String::new()
+        } else {
+            serde_json::to_string(task.extra()).unwrap_or_default()
+        };
+
+        csv.push_str(&record([
+            task.id().to_string(),
+            task.description().to_string(),
+            task.completed().to_string(),
+            task.position().to_string(),
+            time(task.created_at()),
+            time(task.completed_at()),
+            time(task.deleted_at()),
+            extra,
+        ]));
+    }
+
+    csv
+}
+
+/// Reads the rows under the header. Rows without an id get a fresh one.
+pub fn import(contents: &str) -> Result<Vec<Task>, LoadError> {
+    // Spreadsheets like to start with a byte order mark
+    let mut rows = parse(contents.trim_start_matches('\u{feff}'))?.into_iter();
+
+    let Some(header) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let index = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+    };
+    let columns = COLUMNS.map(index);
+    let Some(description) = columns[1] else {
+        return Err(LoadError::format("there is no description column"));
+    };
+
+    rows.enumerate()
+        .filter(|(_, row)| row.iter().any(|field| !field.is_empty()))
+        .map(|(i, row)| {
+            let field = |column: Option<usize>| {
+                column
+                    .and_then(|column| row.get(column))
+                    .map(|field| field.trim())
+                    .filter(|field| !field.is_empty())
+            };
+            // The header is line 1
+            let invalid = |name: &str| LoadError::format(format!("row {} has an invalid {}", i + 2, name));
+
+            let id = match field(columns[0]) {
+                Some(id) => Uuid::parse_str(id).map_err(|_| invalid("id"))?,
+                None => Uuid::new_v4(),
+            };
+            let completed = match field(columns[2]) {
+                Some(completed) => parse_bool(completed).ok_or_else(|| invalid("completed"))?,
+                None => false,
+            };
+            let position = match field(columns[3]) {
+                Some(position) => position.parse().map_err(|_| invalid("position"))?,
+                None => 0,
+            };
+            let time = |column, name: &str| match field(column) {
+                Some(time) => DateTime::parse_from_rfc3339(time)
+                    .map(|time| Some(time.to_utc()))
+                    .map_err(|_| invalid(name)),
+                None => Ok(None),
+            };
+
+            let mut task = Task::from_parts(
+                id,
+                row.get(description).cloned().unwrap_or_default(),
+                completed,
+                position,
+                time(columns[6], "deleted_at")?,
+                time(columns[4], "created_at")?,
+                time(columns[5], "completed_at")?,
+            );
+
+            if let Some(extra) = field(columns[7]) {
+                let extra: BTreeMap<String, String> =
+                    serde_json::from_str(extra).map_err(|_| invalid("extra"))?;
+                *task.extra_mut() = extra;
+            }
+
+            Ok(task)
+        })
+        .collect()
+}
+
+/// Accepts what spreadsheets tend to turn `true` and `false` into.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" | "x" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// One line of fields, quoted where they need to be.
+fn record(fields: impl IntoIterator<Item = String>) -> String {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\r', '\n']) || field.trim() != field {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect();
+
+    fields.join(",") + "\r\n"
+}
+
+/// Splits `contents` into rows of fields as RFC 4180 describes, with quoted
+/// fields spanning lines. Line endings may be CRLF or LF.
+fn parse(contents: &str) -> Result<Vec<Vec<String>>, LoadError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(c),
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(LoadError::format("a quoted field is not closed"));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
@@ -0,0 +1,522 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use uuid::Uuid;
+
+use crate::state::persistence::LoadError;
+use crate::task::Task;
+
+/// Where a task keeps the properties this app does not use, as unfolded
+/// content lines.
+pub const EXTRA_KEY: &str = "ical";
+
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// Longest line allowed before folding, in bytes.
+const LINE_LIMIT: usize = 75;
+
+/// A calendar with one VTODO per task.
+pub fn export<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    let stamp = Utc::now().format(UTC_FORMAT).to_string();
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//todos-iced//EN"),
+    ];
+
+    for task in tasks {
+        lines.extend(todo_lines(task, &stamp));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Reads the VTODOs in `contents`, wherever they are nested. Everything else
+/// in the calendar is skipped.
+pub fn import(contents: &str) -> Result<Vec<Task>, LoadError> {
+    let mut tasks = Vec::new();
+    let mut todo: Option<Todo> = None;
+    // Components nested in the VTODO, such as alarms, are kept whole
+    let mut depth = 0;
+
+    for line in unfold(contents) {
+        let Some((name, value)) = split_line(&line) else {
+            continue;
+        };
+
+        let Some(current) = &mut todo else {
+            if name == "BEGIN" && value.eq_ignore_ascii_case("VTODO") {
+                todo = Some(Todo::default());
+            }
+            continue;
+        };
+
+        match name.as_str() {
+            "END" if depth == 0 => {
+                if let Some(todo) = todo.take() {
+                    tasks.push(todo.into_task());
+                }
+            }
+            "BEGIN" => {
+                depth += 1;
+                current.extra.push(line);
+            }
+            "END" => {
+                depth -= 1;
+                current.extra.push(line);
+            }
+            _ if depth > 0 => current.extra.push(line),
+            "UID" => current.uid = Some(value.to_string()),
+            "SUMMARY" => current.summary = unescape(value),
+            "STATUS" => {
+                let completed = value.eq_ignore_ascii_case("COMPLETED");
+                current.status_completed = Some(completed);
+                // Such as IN-PROCESS, to give back on export. NEEDS-ACTION is
+                // what export writes without one.
+                if !completed && !value.eq_ignore_ascii_case("NEEDS-ACTION") {
+                    current.extra.push(line);
+                }
+            }
+            "COMPLETED" => current.completed_at = parse_time(value),
+            "CREATED" => current.created_at = parse_time(value),
+            // Written fresh on every export
+            "DTSTAMP" => {}
+            _ => current.extra.push(line),
+        }
+    }
+
+    if todo.is_some() {
+        return Err(LoadError::format("a VTODO is not closed"));
+    }
+
+    Ok(tasks)
+}
+
+/// When the VTODO the task was read from was last changed, if it said.
+pub fn last_modified(task: &Task) -> Option<DateTime<Utc>> {
+    let extra = task.extra().get(EXTRA_KEY)?;
+
+    own_lines(extra).find_map(|line| match split_line(line) {
+        Some((name, value)) if name == "LAST-MODIFIED" => parse_time(value),
+        _ => None,
+    })
+}
+
+/// Sets the LAST-MODIFIED the task is exported with.
+pub fn set_last_modified(task: &mut Task, at: DateTime<Utc>) {
+    let extra = task.extra().get(EXTRA_KEY).map(String::as_str).unwrap_or_default();
+    let mut lines = Vec::new();
+    let mut depth = 0;
+
+    for line in extra.lines() {
+        match property_name(line).as_str() {
+            "BEGIN" => depth += 1,
+            "END" => depth -= 1,
+            // An alarm's is its own
+            "LAST-MODIFIED" if depth == 0 => continue,
+            _ => {}
+        }
+        lines.push(line.to_string());
+    }
+    lines.push(format!("LAST-MODIFIED:{}", at.format(UTC_FORMAT)));
+
+    task.extra_mut().insert(EXTRA_KEY.to_string(), lines.join("\n"));
+}
+
+/// The kept lines of the VTODO itself, leaving out those of components
+/// nested in it.
+fn own_lines(extra: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+
+    extra.lines().filter(move |line| match property_name(line).as_str() {
+        "BEGIN" => {
+            depth += 1;
+            false
+        }
+        "END" => {
+            depth -= 1;
+            false
+        }
+        _ => depth == 0,
+    })
+}
+
+/// A VTODO as it is read.
+#[derive(Debug, Default)]
+struct Todo {
+    uid: Option<String>,
+    summary: String,
+    /// Whether STATUS said COMPLETED, if there was one.
+    status_completed: Option<bool>,
+    completed_at: Option<DateTime<Utc>>,
+    created_at: Option<DateTime<Utc>>,
+    extra: Vec<String>,
+}
+
+impl Todo {
+    fn into_task(mut self) -> Task {
+        // Without a STATUS, a completion time is enough
+        let completed = self.status_completed.unwrap_or(self.completed_at.is_some());
+        let id = match &self.uid {
+            Some(uid) => super::stable_id(uid),
+            None => Uuid::new_v4(),
+        };
+
+        // Given back on export as it was, e.g. in upper case
+        if let Some(uid) = self.uid
+            && uid != id.to_string()
+        {
+            self.extra.insert(0, format!("UID:{}", uid));
+        }
+
+        let mut task = Task::from_parts(
+            id,
+            self.summary,
+            completed,
+            0,
+            None,
+            self.created_at,
+            self.completed_at.filter(|_| completed),
+        );
+
+        if !self.extra.is_empty() {
+            task.extra_mut().insert(EXTRA_KEY.to_string(), self.extra.join("\n"));
+        }
+
+        task
+    }
+}
+
+fn todo_lines(task: &Task, stamp: &str) -> Vec<String> {
+    let extra = task.extra().get(EXTRA_KEY).map(String::as_str).unwrap_or_default();
+    let kept = |name: &str| own_lines(extra).find(|line| property_name(line) == name);
+
+    let mut lines = vec![String::from("BEGIN:VTODO")];
+
+    lines.push(match kept("UID") {
+        Some(uid) => uid.to_string(),
+        None => format!("UID:{}", task.id()),
+    });
+    lines.push(format!("DTSTAMP:{}", stamp));
+    if let Some(created_at) = task.created_at() {
+        lines.push(format!("CREATED:{}", created_at.format(UTC_FORMAT)));
+    }
+    lines.push(format!("SUMMARY:{}", escape(task.description())));
+
+    if task.completed() {
+        lines.push(String::from("STATUS:COMPLETED"));
+        if let Some(completed_at) = task.completed_at() {
+            lines.push(format!("COMPLETED:{}", completed_at.format(UTC_FORMAT)));
+        }
+    } else if kept("STATUS").is_none() {
+        lines.push(String::from("STATUS:NEEDS-ACTION"));
+    }
+
+    // In the order they were read, so reading the export back gives the same
+    // task. Those of nested components, such as an alarm's UID, are theirs.
+    let mut depth = 0;
+    lines.extend(
+        extra
+            .lines()
+            .filter(|line| match property_name(line).as_str() {
+                "BEGIN" => {
+                    depth += 1;
+                    true
+                }
+                "END" => {
+                    depth -= 1;
+                    true
+                }
+                _ if depth > 0 => true,
+                "UID" => false,
+                // Such as IN-PROCESS, kept unless the task was completed here
+                // since
+                "STATUS" => !task.completed(),
+                _ => !contradicts(line, task.completed()),
+            })
+            .map(str::to_string),
+    );
+
+    lines.push(String::from("END:VTODO"));
+    lines
+}
+
+/// Whether a kept PERCENT-COMPLETE disagrees with the task being completed or
+/// not, after it changed here.
+fn contradicts(line: &str, completed: bool) -> bool {
+    match split_line(line) {
+        Some((name, value)) if name == "PERCENT-COMPLETE" => (value.trim() == "100") != completed,
+        _ => false,
+    }
+}
+
+/// Joins folded lines back together.
+fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Breaks `line` into lines of at most `LINE_LIMIT` bytes, without splitting
+/// characters, each ending in CRLF.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LIMIT {
+            folded.push_str("\r\n ");
+            // The leading space counts
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+/// The upper-cased name and the value of a content line. Parameters are
+/// skipped, minding quoted ones that contain `:`.
+fn split_line(line: &str) -> Option<(String, &str)> {
+    let name_end = line.find([';', ':'])?;
+    let mut quoted = false;
+
+    let value_start = line[name_end..].char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(name_end + i + 1),
+        _ => None,
+    })?;
+
+    Some((line[..name_end].to_ascii_uppercase(), &line[value_start..]))
+}
+
+fn property_name(line: &str) -> String {
+    let end = line.find([';', ':']).unwrap_or(line.len());
+    line[..end].to_ascii_uppercase()
+}
+
+/// A DATE-TIME in UTC, in local time, or with a TZID this app cannot resolve
+/// and so takes as local too; or a DATE, taken as its local midnight.
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, UTC_FORMAT) {
+        return Some(time.and_utc());
+    }
+
+    let local = NaiveDateTime::parse_from_str(value, LOCAL_FORMAT)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })?;
+
+    local
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THUNDERBIRD: &str = include_str!("../../../tests/fixtures/thunderbird.ics");
+    const APPLE_REMINDERS: &str = include_str!("../../../tests/fixtures/apple-reminders.ics");
+    const NEXTCLOUD_TASKS: &str = include_str!("../../../tests/fixtures/nextcloud-tasks.ics");
+
+    fn time(value: &str) -> Option<DateTime<Utc>> {
+        parse_time(value)
+    }
+
+    /// The unfolded lines of each VTODO in `contents`, in order, leaving out
+    /// the DTSTAMP that every export writes afresh.
+    fn todo_lines_of(contents: &str) -> Vec<Vec<String>> {
+        let mut todos = Vec::new();
+        let mut current: Option<Vec<String>> = None;
+        let mut depth = 0;
+
+        for line in unfold(contents) {
+            match (property_name(&line).as_str(), &mut current) {
+                ("BEGIN", None) if line.eq_ignore_ascii_case("BEGIN:VTODO") => current = Some(Vec::new()),
+                ("BEGIN", Some(lines)) => {
+                    depth += 1;
+                    lines.push(line);
+                }
+                ("END", Some(_)) if depth == 0 => todos.extend(current.take()),
+                ("END", Some(lines)) => {
+                    depth -= 1;
+                    lines.push(line);
+                }
+                ("DTSTAMP", Some(_)) if depth == 0 => {}
+                (_, Some(lines)) => lines.push(line),
+                _ => {}
+            }
+        }
+
+        todos
+    }
+
+    /// Checks that every line of every VTODO in `contents` is written back,
+    /// and that reading the export gives the same tasks.
+    fn assert_round_trip(contents: &str) {
+        let tasks = import(contents).unwrap();
+        let exported = export(&tasks);
+
+        let original = todo_lines_of(contents);
+        let written = todo_lines_of(&exported);
+        assert_eq!(original.len(), written.len());
+        for (original, written) in original.iter().zip(&written) {
+            for line in original {
+                assert!(written.contains(line), "{} is lost in\n{}", line, written.join("\n"));
+            }
+            // Nothing new but the STATUS a task without one gets
+            assert!(written.len() <= original.len() + 1);
+        }
+
+        let again = import(&exported).unwrap();
+        assert_eq!(again.len(), tasks.len());
+        for (again, task) in again.iter().zip(&tasks) {
+            assert_eq!(again.id(), task.id());
+            assert_eq!(again.description(), task.description());
+            assert_eq!(again.completed(), task.completed());
+            assert_eq!(again.created_at(), task.created_at());
+            assert_eq!(again.completed_at(), task.completed_at());
+            assert_eq!(again.extra(), task.extra());
+        }
+    }
+
+    #[test]
+    fn thunderbird_tasks_survive_a_round_trip() {
+        let tasks = import(THUNDERBIRD).unwrap();
+
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(
+            tasks[0].id(),
+            Uuid::parse_str("3b6e0a59-27c1-4f0e-9d8b-1f0f7b1ac2d4").unwrap()
+        );
+        assert_eq!(tasks[0].description(), "Renew the car insurance, before the 15th");
+        assert!(!tasks[0].completed());
+        assert_eq!(tasks[0].created_at(), time("20250301T091500Z"));
+        assert_eq!(last_modified(&tasks[0]), time("20250302T180412Z"));
+        assert!(tasks[1].completed());
+        assert_eq!(tasks[1].completed_at(), time("20250225T192233Z"));
+
+        assert_round_trip(THUNDERBIRD);
+    }
+
+    #[test]
+    fn apple_reminders_survive_a_round_trip() {
+        let tasks = import(APPLE_REMINDERS).unwrap();
+
+        assert_eq!(tasks.len(), 2);
+        // Upper case, so given back as it was rather than as the task id
+        assert_eq!(
+            tasks[0].id(),
+            Uuid::parse_str("6F1C2B7E-93A4-4E0B-B0D2-5C8E1A7F3D49").unwrap()
+        );
+        assert_eq!(tasks[0].description(), "Call the plumber about the kitchen tap");
+        assert!(!tasks[0].completed());
+        assert!(tasks[1].completed());
+        assert_eq!(tasks[1].completed_at(), time("20250301T101120Z"));
+
+        assert_round_trip(APPLE_REMINDERS);
+    }
+
+    #[test]
+    fn nextcloud_tasks_survive_a_round_trip() {
+        let tasks = import(NEXTCLOUD_TASKS).unwrap();
+
+        assert_eq!(tasks.len(), 2);
+        // Not a UUID, so one is made from it
+        assert_eq!(
+            tasks[0].id(),
+            super::super::stable_id("nc-8c41d7e2-2f3a-4b59-a0e6-7d1b9c3f5e28")
+        );
+        assert_eq!(tasks[0].description(), "Plan the team offsite; book rooms");
+        assert!(tasks[1].completed());
+
+        assert_round_trip(NEXTCLOUD_TASKS);
+    }
+
+    #[test]
+    fn an_alarm_uid_stays_the_alarms() {
+        let contents = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:3b6e0a59-27c1-4f0e-9d8b-1f0f7b1ac2d4\r\n\
+            SUMMARY:Water the plants\r\nBEGIN:VALARM\r\nUID:0b3e5f7a-1c2d-4e6f-8a9b-0c1d2e3f4a5b\r\n\
+            ACTION:DISPLAY\r\nEND:VALARM\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+
+        let again = import(&export(&import(contents).unwrap())).unwrap();
+
+        assert_eq!(
+            again[0].id(),
+            Uuid::parse_str("3b6e0a59-27c1-4f0e-9d8b-1f0f7b1ac2d4").unwrap()
+        );
+        assert_round_trip(contents);
+    }
+
+    #[test]
+    fn completing_a_task_drops_the_kept_progress() {
+        let mut tasks = import(THUNDERBIRD).unwrap();
+        let mut task = tasks.remove(0);
+        task.update(crate::task::TaskMessage::Completed(true));
+
+        let written = &todo_lines_of(&export([&task]))[0];
+
+        assert!(written.contains(&String::from("STATUS:COMPLETED")));
+        assert!(!written.iter().any(|line| line.starts_with("STATUS:IN-PROCESS")));
+        assert!(!written.iter().any(|line| line.starts_with("PERCENT-COMPLETE")));
+        // The rest is kept
+        assert!(written.contains(&String::from("X-MOZ-GENERATION:3")));
+        assert!(written.contains(&String::from("CATEGORIES:Car,Paperwork")));
+    }
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::state::persistence::{LoadError, SavedState};
+
+/// Bumped when a change to the layout would mislead older versions reading
+/// it. Fields added with a default do not need it.
+pub const VERSION: u32 = 1;
+
+/// The saved state, with the version it was written in.
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u32,
+    exported_at: DateTime<Utc>,
+    #[serde(flatten)]
+    state: &'a SavedState,
+}
+
+/// Everything in `state`, the trash and any history included, except the
+/// CalDAV password. Exports are shared and copied around, and it would be in
+/// them in plain text.
+pub fn export(state: &SavedState) -> String {
+    let mut state = state.clone();
+    if let Some(caldav) = &mut state.caldav {
+        caldav.password.clear();
+    }
+
+    let document = Document {
+        version: VERSION,
+        exported_at: Utc::now(),
+        state: &state,
+    };
+
+    // Only fails for maps with non-string keys, which the state has none of
+    serde_json::to_string_pretty(&document).unwrap_or_default() + "\n"
+}
+
+/// Reads an export of any version up to `VERSION`. Files without a version,
+/// such as those the JSON storage writes, are read the same way.
+pub fn import(contents: &str) -> Result<SavedState, LoadError> {
+    let document: serde_json::Value = serde_json::from_str(contents).map_err(LoadError::format)?;
+
+    let version = match document.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| LoadError::format("the version is not a number"))?,
+    };
+    if version > u64::from(VERSION) {
+        return Err(LoadError::UnsupportedVersion {
+            found: version as i64,
+            supported: VERSION.into(),
+        });
+    }
+
+    serde_json::from_value(document).map_err(LoadError::format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::caldav::Collection;
+
+    #[test]
+    fn the_caldav_password_is_not_exported() {
+        let state = SavedState {
+            caldav: Some(Collection::new(
+                String::from("https://example.com/dav/tasks/"),
+                String::from("me"),
+                String::from("hunter2"),
+            )),
+            ..SavedState::default()
+        };
+
+        let exported = export(&state);
+        assert!(!exported.contains("hunter2"));
+
+        let caldav = import(&exported).unwrap().caldav.unwrap();
+        assert_eq!(caldav.url, "https://example.com/dav/tasks/");
+        assert_eq!(caldav.username, "me");
+        assert!(caldav.password.is_empty());
+    }
+}
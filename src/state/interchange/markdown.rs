@@ -0,0 +1,86 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::task::Task;
+
+/// A GitHub-style checklist, one `- [ ]` or `- [x]` item per task.
+pub fn export<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    tasks
+        .into_iter()
+        .map(|task| {
+            let mark = if task.completed() { 'x' } else { ' ' };
+            // A line break would end the item
+            let description = task.description().replace(['\r', '\n'], " ");
+            format!("- [{}] {}\n", mark, description)
+        })
+        .collect()
+}
+
+/// Reads the list items as tasks, checked boxes as completed ones. Nested
+/// items become tasks of their own. Without any list items, every non-blank
+/// line is a task.
+pub fn import(contents: &str) -> Vec<Task> {
+    let items: Vec<(bool, &str)> = contents.lines().filter_map(parse_item).collect();
+
+    let items = if items.is_empty() {
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| (false, line))
+            .collect()
+    } else {
+        items
+    };
+
+    // Created now, the list holds no dates
+    let now = Utc::now();
+
+    items
+        .into_iter()
+        .map(|(completed, description)| {
+            Task::from_parts(Uuid::new_v4(), description.to_string(), completed, 0, None, Some(now), None)
+        })
+        .collect()
+}
+
+/// The tasks in pasted text, or `None` for a single line, which is left to
+/// the input as usual.
+pub fn parse_paste(text: &str) -> Option<Vec<Task>> {
+    let lines = text.lines().filter(|line| !line.trim().is_empty()).count();
+
+    (lines > 1).then(|| import(text))
+}
+
+/// Whether the line is a list item, checked, and its text. Items without text
+/// are skipped.
+fn parse_item(line: &str) -> Option<(bool, &str)> {
+    let rest = strip_marker(line.trim_start())?;
+
+    let (completed, rest) = match rest.get(..3) {
+        Some("[ ]") => (false, &rest[3..]),
+        Some("[x]" | "[X]") => (true, &rest[3..]),
+        _ => (false, rest),
+    };
+
+    let description = rest.trim();
+    (!description.is_empty()).then_some((completed, description))
+}
+
+/// Strips a bullet (`-`, `*`, `+`) or number (`1.`, `1)`) and the space after
+/// it.
+fn strip_marker(line: &str) -> Option<&str> {
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+
+    let rest = if digits > 0 {
+        line[digits..].strip_prefix(['.', ')'])?
+    } else {
+        line.strip_prefix(['-', '*', '+'])?
+    };
+
+    // `-foo` is not a list item, but a bare `-` ending the line is an empty one
+    if rest.is_empty() {
+        return Some(rest);
+    }
+    rest.strip_prefix([' ', '\t'])
+}
@@ -0,0 +1,349 @@
+pub mod csv;
+pub mod ical;
+pub mod json;
+pub mod markdown;
+pub mod org;
+pub mod taskwarrior;
+pub mod todo_txt;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use super::history::{Change, HistoryEntry};
+use super::persistence::{LoadError, SaveError, SavedState};
+use crate::task::Task;
+
+/// Turns ids from other apps that are not UUIDs into stable ones, so importing
+/// the same file twice updates the tasks instead of adding them again.
+const ID_NAMESPACE: Uuid = Uuid::from_u128(0x6d1c_55a8_2f0e_4b7d_9a64_3c1e_8f2b_07d4);
+
+/// The task id for an id another app gave it.
+fn stable_id(id: &str) -> Uuid {
+    Uuid::parse_str(id).unwrap_or_else(|_| Uuid::new_v5(&ID_NAMESPACE, id.as_bytes()))
+}
+
+/// A file format tasks can be exported to and imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// One task per line, see <https://github.com/todotxt/todo.txt>.
+    TodoTxt,
+    /// A GitHub-style checklist.
+    Markdown,
+    /// iCalendar, one VTODO per task.
+    ICalendar,
+    /// Everything the app stores about the tasks, with a format version.
+    #[default]
+    Json,
+    /// A table with a header row, for spreadsheets.
+    Csv,
+    /// What `task export` writes and `task import` reads.
+    Taskwarrior,
+    /// TODO and DONE headlines.
+    Org,
+}
+
+impl Format {
+    pub const ALL: [Format; 7] = [
+        Format::Json,
+        Format::Csv,
+        Format::TodoTxt,
+        Format::Markdown,
+        Format::ICalendar,
+        Format::Taskwarrior,
+        Format::Org,
+    ];
+
+    /// Picks the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "txt" => Some(Format::TodoTxt),
+            "md" | "markdown" => Some(Format::Markdown),
+            "ics" | "ical" => Some(Format::ICalendar),
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "org" => Some(Format::Org),
+            _ => None,
+        }
+    }
+
+    /// The extension given to exports. `from_path` recognises all of them.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::TodoTxt => "txt",
+            Format::Markdown => "md",
+            Format::ICalendar => "ics",
+            Format::Json => "json",
+            Format::Csv => "csv",
+            // Told apart from the app's JSON when read back
+            Format::Taskwarrior => "task.json",
+            Format::Org => "org",
+        }
+    }
+
+    /// Writes the tasks in `state`. Only JSON, CSV and Taskwarrior keep those
+    /// in the trash, Taskwarrior as deleted tasks, and only JSON the rest of
+    /// the state.
+    pub fn export(self, state: &SavedState) -> String {
+        let tasks = state.tasks.iter().filter(|task| !task.is_deleted());
+
+        match self {
+            Format::TodoTxt => todo_txt::export(tasks),
+            Format::Markdown => markdown::export(tasks),
+            Format::ICalendar => ical::export(tasks),
+            Format::Json => json::export(state),
+            Format::Csv => csv::export(&state.tasks),
+            Format::Taskwarrior => taskwarrior::export(&state.tasks),
+            Format::Org => org::export(tasks),
+        }
+    }
+
+    /// Reads the tasks in `contents`, ready to be combined with `merge`.
+    /// Formats without ids give every task a fresh one.
+    pub fn import(self, contents: &str) -> Result<Vec<Task>, LoadError> {
+        match self {
+            Format::TodoTxt => Ok(todo_txt::import(contents)),
+            Format::Markdown => Ok(markdown::import(contents)),
+            Format::ICalendar => ical::import(contents),
+            // Both end in .json, so tell them apart by their contents
+            Format::Json if taskwarrior::detect(contents) => taskwarrior::import(contents),
+            Format::Json => json::import(contents).map(|state| state.tasks),
+            Format::Csv => csv::import(contents),
+            Format::Taskwarrior => taskwarrior::import(contents),
+            Format::Org => Ok(org::import(contents)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::TodoTxt => "todo.txt",
+            Format::Markdown => "Markdown",
+            Format::ICalendar => "iCalendar",
+            Format::Json => "JSON",
+            Format::Csv => "CSV",
+            Format::Taskwarrior => "Taskwarrior",
+            Format::Org => "Org",
+        })
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "todo.txt" | "todotxt" | "txt" => Ok(Format::TodoTxt),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "icalendar" | "ical" | "ics" => Ok(Format::ICalendar),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "taskwarrior" | "task" => Ok(Format::Taskwarrior),
+            "org" => Ok(Format::Org),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+/// Reads the tasks in the file at `path`, in the format its extension names.
+/// Taskwarrior exports are told from the app's JSON by their contents.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn read_file(path: &Path) -> Result<Vec<Task>, LoadError> {
+    let format = Format::from_path(path).ok_or_else(|| LoadError::format("unknown file extension"))?;
+    let contents = tokio::fs::read_to_string(path).await.map_err(LoadError::file)?;
+
+    format.import(&contents)
+}
+
+/// There are no files to read in the browser.
+#[cfg(target_arch = "wasm32")]
+pub async fn read_file(_path: &Path) -> Result<Vec<Task>, LoadError> {
+    Err(LoadError::file("there are no files in the browser"))
+}
+
+/// Writes an export to `path`, giving the path back.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn write_file(path: PathBuf, contents: String) -> Result<PathBuf, SaveError> {
+    tokio::fs::write(&path, contents)
+        .await
+        .map(|()| path)
+        .map_err(SaveError::write)
+}
+
+/// There are no files to write in the browser.
+#[cfg(target_arch = "wasm32")]
+pub async fn write_file(_path: PathBuf, _contents: String) -> Result<PathBuf, SaveError> {
+    Err(SaveError::write("there are no files in the browser"))
+}
+
+/// Where the import and export dialog suggests a file at first.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_path() -> PathBuf {
+    let dir = directories::UserDirs::new()
+        .and_then(|dirs| dirs.document_dir().map(Path::to_path_buf))
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+
+    dir.join("todos.json")
+}
+
+/// There are no files to pick in the browser.
+#[cfg(target_arch = "wasm32")]
+pub fn default_path() -> PathBuf {
+    PathBuf::from("todos.json")
+}
+
+/// How imported tasks are combined with the stored ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Tasks are matched by id. Stored tasks missing from the import stay.
+    #[default]
+    Merge,
+    /// The import becomes the whole list, in its order. Stored tasks missing
+    /// from it are deleted for good.
+    Replace,
+}
+
+/// Imported tasks combined with the stored ones.
+#[derive(Debug, Clone, Default)]
+pub struct Merge {
+    /// The new and changed tasks, to save.
+    pub upserted: Vec<Task>,
+    pub history: Vec<HistoryEntry>,
+    /// Stored tasks missing from the import, when replacing.
+    pub removed: Vec<Uuid>,
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+/// Combines `imported` with `existing` by id. When merging, new tasks go
+/// after the stored ones and tasks already stored take the imported content
+/// but keep their place. The first of several imported tasks with the same id
+/// wins.
+pub fn merge(existing: &[Task], imported: Vec<Task>, mode: Mode) -> Merge {
+    let stored: HashMap<Uuid, &Task> = existing.iter().map(|task| (task.id(), task)).collect();
+    let mut seen = HashSet::new();
+    let mut next = match mode {
+        Mode::Merge => existing
+            .iter()
+            .map(Task::position)
+            .max()
+            .map_or(0, |last| last + 1),
+        Mode::Replace => 0,
+    };
+    let mut merge = Merge::default();
+
+    for mut task in imported {
+        if !seen.insert(task.id()) {
+            continue;
+        }
+
+        match stored.get(&task.id()) {
+            Some(stored) => {
+                match mode {
+                    Mode::Merge => task.set_position(stored.position()),
+                    Mode::Replace => {
+                        task.set_position(next);
+                        next += 1;
+                    }
+                }
+
+                let changes = changes_between(stored, &task);
+                if changes.is_empty() && !differs_quietly(stored, &task) {
+                    merge.unchanged += 1;
+                    continue;
+                }
+
+                merge
+                    .history
+                    .extend(changes.into_iter().map(|change| HistoryEntry::new(&task, change)));
+                merge.updated += 1;
+            }
+            None => {
+                task.set_position(next);
+                next += 1;
+
+                merge.history.push(HistoryEntry::new(
+                    &task,
+                    Change::Created {
+                        description: task.description().to_string(),
+                    },
+                ));
+                merge.added += 1;
+            }
+        }
+
+        merge.upserted.push(task);
+    }
+
+    if mode == Mode::Replace {
+        merge.removed = existing
+            .iter()
+            .map(Task::id)
+            .filter(|id| !seen.contains(id))
+            .collect();
+    }
+
+    merge
+}
+
+/// The changes the history records, from `stored` to `imported`.
+pub(super) fn changes_between(stored: &Task, imported: &Task) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    if stored.description() != imported.description() {
+        changes.push(Change::Renamed {
+            from: stored.description().to_string(),
+            to: imported.description().to_string(),
+        });
+    }
+    if stored.completed() != imported.completed() {
+        changes.push(Change::Completed(imported.completed()));
+    }
+    match (stored.is_deleted(), imported.is_deleted()) {
+        (true, false) => changes.push(Change::Restored),
+        (false, true) => changes.push(Change::Deleted),
+        _ => {}
+    }
+
+    changes
+}
+
+/// Whether anything the history does not record differs.
+fn differs_quietly(stored: &Task, imported: &Task) -> bool {
+    stored.position() != imported.position()
+        || stored.deleted_at() != imported.deleted_at()
+        || stored.created_at() != imported.created_at()
+        || stored.completed_at() != imported.completed_at()
+        || stored.extra() != imported.extra()
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::state::persistence::test_dir;
+
+    #[tokio::test]
+    async fn exports_are_written_and_read_back() {
+        let dir = test_dir();
+        let state = SavedState {
+            tasks: vec![Task::new(String::from("Buy milk"))],
+            ..SavedState::default()
+        };
+
+        let path = dir.join("todos").with_extension(Format::TodoTxt.extension());
+        let written = write_file(path.clone(), Format::TodoTxt.export(&state)).await.unwrap();
+        assert_eq!(written, path);
+
+        let tasks = read_file(&path).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id(), state.tasks[0].id());
+
+        assert!(read_file(&dir.join("missing.txt")).await.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,347 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::task::Task;
+
+/// Where a task keeps what its headline had besides the title and state, as
+/// JSON.
+pub const EXTRA_KEY: &str = "org";
+
+/// An inactive timestamp, in local time as Org writes them.
+const TIMESTAMP_FORMAT: &str = "[%Y-%m-%d %a %H:%M]";
+
+/// What a headline had that this app has no use for.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Kept {
+    /// The number of stars, if more than one.
+    level: Option<usize>,
+    /// A letter, or a number in newer versions of Org.
+    priority: Option<String>,
+    /// As written, e.g. `:work:urgent:`.
+    tags: Option<String>,
+    /// SCHEDULED and DEADLINE, as written.
+    planning: Option<String>,
+    /// Properties other than CREATED, in order. ID only if it is not the
+    /// task's id as written.
+    properties: Vec<(String, String)>,
+    body: Vec<String>,
+}
+
+/// A headline per task, with its id in the property drawer so that importing
+/// the file again updates the tasks.
+pub fn export<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    let mut lines = Vec::new();
+
+    for task in tasks {
+        let kept: Kept = task
+            .extra()
+            .get(EXTRA_KEY)
+            .and_then(|kept| serde_json::from_str(kept).ok())
+            .unwrap_or_default();
+
+        let mut headline = format!(
+            "{} {}",
+            "*".repeat(kept.level.unwrap_or(1)),
+            if task.completed() { "DONE" } else { "TODO" }
+        );
+        if let Some(priority) = kept.priority {
+            headline.push_str(&format!(" [#{}]", priority));
+        }
+        // A headline is a single line
+        headline.push(' ');
+        headline.push_str(&task.description().replace(['\r', '\n'], " "));
+        if let Some(tags) = &kept.tags {
+            headline.push(' ');
+            headline.push_str(tags);
+        }
+        lines.push(headline);
+
+        let closed = task
+            .completed_at()
+            .filter(|_| task.completed())
+            .map(|completed_at| format!("CLOSED: {}", timestamp(completed_at)));
+        let planning: Vec<&str> = closed.iter().map(String::as_str).chain(kept.planning.as_deref()).collect();
+        if !planning.is_empty() {
+            lines.push(planning.join(" "));
+        }
+
+        lines.push(String::from(":PROPERTIES:"));
+        let id = kept.properties.iter().find(|(name, _)| name.eq_ignore_ascii_case("ID"));
+        match id {
+            Some((name, id)) => lines.push(format!(":{}: {}", name, id)),
+            None => lines.push(format!(":ID: {}", task.id())),
+        }
+        if let Some(created_at) = task.created_at() {
+            lines.push(format!(":CREATED: {}", timestamp(created_at)));
+        }
+        for (name, value) in &kept.properties {
+            if !name.eq_ignore_ascii_case("ID") {
+                lines.push(format!(":{}: {}", name, value));
+            }
+        }
+        lines.push(String::from(":END:"));
+
+        lines.extend(kept.body);
+    }
+
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+/// Reads the TODO and DONE headlines, at any level. Other headlines, and the
+/// text around them, are skipped.
+pub fn import(contents: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut current: Option<Headline> = None;
+    let mut in_drawer = false;
+
+    for line in contents.lines() {
+        if let Some((level, title)) = split_headline(line) {
+            tasks.extend(current.take().map(Headline::into_task));
+            current = Headline::parse(level, title);
+            in_drawer = false;
+            continue;
+        }
+
+        let Some(headline) = &mut current else {
+            continue;
+        };
+        let trimmed = line.trim();
+
+        if in_drawer {
+            if trimmed.eq_ignore_ascii_case(":END:") {
+                in_drawer = false;
+            } else if let Some((name, value)) = split_property(trimmed) {
+                headline.property(name, value);
+            }
+        } else if trimmed.eq_ignore_ascii_case(":PROPERTIES:") {
+            in_drawer = true;
+        } else if ["CLOSED:", "SCHEDULED:", "DEADLINE:"]
+            .iter()
+            .any(|keyword| trimmed.starts_with(keyword))
+        {
+            headline.planning(trimmed);
+        } else {
+            headline.kept.body.push(line.to_string());
+        }
+    }
+
+    tasks.extend(current.map(Headline::into_task));
+    tasks
+}
+
+/// A TODO or DONE headline as it is read.
+#[derive(Debug, Default)]
+struct Headline {
+    title: String,
+    done: bool,
+    id: Option<String>,
+    closed_at: Option<DateTime<Utc>>,
+    created_at: Option<DateTime<Utc>>,
+    kept: Kept,
+}
+
+impl Headline {
+    /// `None` for headlines without a TODO or DONE keyword.
+    fn parse(level: usize, title: &str) -> Option<Self> {
+        let (keyword, title) = title.split_once(' ').unwrap_or((title, ""));
+        let done = match keyword {
+            "TODO" => false,
+            "DONE" => true,
+            _ => return None,
+        };
+
+        let mut headline = Headline {
+            done,
+            ..Headline::default()
+        };
+        if level > 1 {
+            headline.kept.level = Some(level);
+        }
+
+        let mut title = title.trim();
+        if let Some(rest) = title.strip_prefix("[#")
+            && let Some((priority, rest)) = rest.split_once(']')
+            && !priority.is_empty()
+            && !priority.contains(' ')
+        {
+            headline.kept.priority = Some(priority.to_string());
+            title = rest.trim_start();
+        }
+
+        if let Some((rest, tags)) = title.rsplit_once([' ', '\t'])
+            && tags.len() > 1
+            && tags.starts_with(':')
+            && tags.ends_with(':')
+        {
+            headline.kept.tags = Some(tags.to_string());
+            title = rest.trim_end();
+        }
+
+        headline.title = title.to_string();
+        Some(headline)
+    }
+
+    fn property(&mut self, name: &str, value: &str) {
+        match name.to_ascii_uppercase().as_str() {
+            "ID" => self.id = Some(value.to_string()),
+            "CREATED" => self.created_at = parse_timestamp(value),
+            _ => self.kept.properties.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Takes CLOSED from a planning line and keeps the rest of it.
+    fn planning(&mut self, line: &str) {
+        let mut rest = line.to_string();
+
+        if let Some(start) = line.find("CLOSED:")
+            && let Some(length) = line[start..].find(']')
+        {
+            self.closed_at = parse_timestamp(line["CLOSED:".len() + start..start + length + 1].trim());
+            rest.replace_range(start..start + length + 1, "");
+        }
+
+        let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !rest.is_empty() {
+            self.kept.planning = Some(rest);
+        }
+    }
+
+    fn into_task(mut self) -> Task {
+        let id = match &self.id {
+            Some(id) => super::stable_id(id),
+            None => Uuid::new_v4(),
+        };
+
+        // Given back on export as it was
+        if let Some(original) = self.id
+            && original != id.to_string()
+        {
+            self.kept.properties.insert(0, (String::from("ID"), original));
+        }
+
+        // Blank lines before the next headline belong to neither
+        while self.kept.body.last().is_some_and(|line| line.trim().is_empty()) {
+            self.kept.body.pop();
+        }
+
+        let mut task = Task::from_parts(
+            id,
+            self.title,
+            self.done,
+            0,
+            None,
+            self.created_at,
+            self.closed_at.filter(|_| self.done),
+        );
+
+        if self.kept != Kept::default()
+            && let Ok(kept) = serde_json::to_string(&self.kept)
+        {
+            task.extra_mut().insert(EXTRA_KEY.to_string(), kept);
+        }
+
+        task
+    }
+}
+
+/// The number of stars and the rest of a headline.
+fn split_headline(line: &str) -> Option<(usize, &str)> {
+    let title = line.trim_start_matches('*');
+    let level = line.len() - title.len();
+
+    (level > 0 && title.starts_with(' ')).then(|| (level, title.trim()))
+}
+
+/// The name and value of a `:NAME: value` line in a property drawer.
+fn split_property(line: &str) -> Option<(&str, &str)> {
+    let (name, value) = line.strip_prefix(':')?.split_once(':')?;
+
+    (!name.is_empty() && !name.contains(' ')).then(|| (name, value.trim()))
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// An Org timestamp, active or not, with or without a time of day.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let inner = value.trim().trim_start_matches(['[', '<']).trim_end_matches([']', '>']);
+    let mut parts = inner.split_whitespace();
+
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    let time = parts
+        .find(|part| part.contains(':'))
+        .and_then(|part| NaiveTime::parse_from_str(part.split('-').next()?, "%H:%M").ok())
+        .unwrap_or(NaiveTime::MIN);
+
+    date.and_time(time)
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/tasks.org");
+
+    fn local(date: &str, time: &str) -> Option<DateTime<Utc>> {
+        parse_timestamp(&format!("[{} {}]", date, time))
+    }
+
+    #[test]
+    fn headlines_are_read_field_by_field() {
+        let tasks = import(FIXTURE);
+
+        assert_eq!(
+            tasks.iter().map(Task::description).collect::<Vec<_>>(),
+            ["Renew the passport", "Water the plants", "Send the agenda", "Book the rooms"]
+        );
+
+        assert_eq!(tasks[0].id(), Uuid::parse_str("5f0c1b2e-8a3d-4e7f-9b6a-2c4d8e0f1a3b").unwrap());
+        assert!(!tasks[0].completed());
+        assert_eq!(tasks[0].created_at(), local("2025-03-01", "09:15"));
+
+        assert!(tasks[1].completed());
+        assert_eq!(tasks[1].completed_at(), local("2025-03-03", "18:00"));
+        assert_eq!(tasks[1].created_at(), local("2025-03-02", "08:00"));
+
+        assert_eq!(tasks[2].id(), super::super::stable_id("agenda-2025-03"));
+        assert!(tasks[3].completed());
+        assert_eq!(tasks[3].completed_at(), local("2025-03-04", "10:30"));
+    }
+
+    #[test]
+    fn headlines_survive_a_round_trip() {
+        let tasks = import(FIXTURE);
+        let exported = export(&tasks);
+
+        for line in [
+            "** TODO [#A] Renew the passport :errand:paperwork:",
+            "SCHEDULED: <2025-03-10 Mon> DEADLINE: <2025-04-01 Tue>",
+            ":EFFORT: 0:30",
+            "Bring the old one and two photos.",
+            "** DONE Water the plants",
+            "CLOSED: [2025-03-03 Mon 18:00] SCHEDULED: <2025-03-03 Mon>",
+            ":ID: agenda-2025-03",
+            "*** DONE Book the rooms",
+            "CLOSED: [2025-03-04 Tue 10:30]",
+        ] {
+            assert!(exported.lines().any(|exported| exported == line), "{} is lost in\n{}", line, exported);
+        }
+
+        let again = import(&exported);
+        assert_eq!(again.len(), tasks.len());
+        for (again, task) in again.iter().zip(&tasks) {
+            assert_eq!(again.id(), task.id());
+            assert_eq!(again.description(), task.description());
+            assert_eq!(again.completed(), task.completed());
+            assert_eq!(again.created_at(), task.created_at());
+            assert_eq!(again.completed_at(), task.completed_at());
+            assert_eq!(again.extra(), task.extra());
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::state::persistence::LoadError;
+use crate::task::Task;
+
+/// Where a task keeps the Taskwarrior attributes this app does not use, as a
+/// JSON object.
+pub const EXTRA_KEY: &str = "taskwarrior";
+
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Worked out by Taskwarrior from the rest, so not worth keeping.
+const DERIVED: [&str; 3] = ["id", "urgency", "modified"];
+
+/// A JSON array with a task per line, as `task export` writes it and
+/// `task import` reads it.
+pub fn export<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    let now = Utc::now();
+    let lines: Vec<String> = tasks
+        .into_iter()
+        .map(|task| Value::Object(to_object(task, now)).to_string())
+        .collect();
+
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+/// Whether `contents` looks like `task export` output rather than the app's
+/// own JSON, which is a single object with a version.
+pub fn detect(contents: &str) -> bool {
+    let contents = contents.trim_start();
+
+    contents.starts_with('[')
+        || contents
+            .lines()
+            .next()
+            .and_then(|line| serde_json::from_str::<Map<String, Value>>(line.trim_end_matches(',')).ok())
+            .is_some_and(|object| object.contains_key("uuid"))
+}
+
+/// Reads `task export` output: a JSON array, or one object per line as older
+/// versions write it. Deleted tasks go to the trash.
+pub fn import(contents: &str) -> Result<Vec<Task>, LoadError> {
+    let objects: Vec<Map<String, Value>> = if contents.trim_start().starts_with('[') {
+        serde_json::from_str(contents).map_err(LoadError::format)?
+    } else {
+        contents
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_str(line).map_err(LoadError::format))
+            .collect::<Result<_, _>>()?
+    };
+
+    objects.into_iter().map(from_object).collect()
+}
+
+fn from_object(mut object: Map<String, Value>) -> Result<Task, LoadError> {
+    let mut text = |name: &str| match object.remove(name) {
+        Some(Value::String(value)) => Some(value),
+        _ => None,
+    };
+
+    let id = match text("uuid") {
+        Some(uuid) => Uuid::parse_str(&uuid).map_err(|_| LoadError::format(format!("invalid uuid {}", uuid)))?,
+        None => Uuid::new_v4(),
+    };
+    let description = text("description").unwrap_or_default();
+    let created_at = text("entry").as_deref().and_then(parse_time);
+    let end = text("end").as_deref().and_then(parse_time);
+    let status = text("status").unwrap_or_default();
+
+    let (completed, deleted_at) = match status.as_str() {
+        "completed" => (true, None),
+        "deleted" => (false, Some(end.unwrap_or_else(Utc::now))),
+        _ => (false, None),
+    };
+
+    for name in DERIVED {
+        object.remove(name);
+    }
+    // Such as waiting, to give back on export
+    if !matches!(status.as_str(), "pending" | "completed" | "deleted" | "") {
+        object.insert("status".into(), Value::String(status));
+    }
+
+    let mut task = Task::from_parts(
+        id,
+        description,
+        completed,
+        0,
+        deleted_at,
+        created_at,
+        end.filter(|_| completed),
+    );
+
+    if !object.is_empty() {
+        task.extra_mut()
+            .insert(EXTRA_KEY.to_string(), Value::Object(object).to_string());
+    }
+
+    Ok(task)
+}
+
+fn to_object(task: &Task, now: DateTime<Utc>) -> Map<String, Value> {
+    let mut object: Map<String, Value> = task
+        .extra()
+        .get(EXTRA_KEY)
+        .and_then(|kept| serde_json::from_str(kept).ok())
+        .unwrap_or_default();
+    let time = |time: DateTime<Utc>| Value::String(time.format(TIME_FORMAT).to_string());
+
+    let status = if let Some(deleted_at) = task.deleted_at() {
+        object.insert("end".into(), time(deleted_at));
+        "deleted".into()
+    } else if task.completed() {
+        object.insert("end".into(), time(task.completed_at().unwrap_or(now)));
+        "completed".into()
+    } else {
+        object.remove("end");
+        match object.remove("status") {
+            Some(Value::String(status)) => status,
+            _ => "pending".into(),
+        }
+    };
+
+    object.insert("uuid".into(), Value::String(task.id().to_string()));
+    object.insert("description".into(), Value::String(task.description().to_string()));
+    object.insert("status".into(), Value::String(status));
+    object.insert("entry".into(), time(task.created_at().unwrap_or(now)));
+    object.insert("modified".into(), time(now));
+
+    object
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, TIME_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::interchange::Format;
+    use crate::state::persistence::SavedState;
+    use crate::task::TaskMessage;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/taskwarrior.json");
+
+    /// The objects in `contents`, leaving out what Taskwarrior works out.
+    fn objects(contents: &str) -> Vec<Map<String, Value>> {
+        let mut objects: Vec<Map<String, Value>> = serde_json::from_str(contents).unwrap();
+        for object in &mut objects {
+            for name in DERIVED {
+                object.remove(name);
+            }
+        }
+        objects
+    }
+
+    #[test]
+    fn tasks_are_read_field_by_field() {
+        assert!(detect(FIXTURE));
+        let tasks = import(FIXTURE).unwrap();
+        let time = |value| parse_time(value);
+
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(tasks[0].id(), Uuid::parse_str("5f0c1b2e-8a3d-4e7f-9b6a-2c4d8e0f1a3b").unwrap());
+        assert_eq!(tasks[0].description(), "Renew the passport");
+        assert_eq!(tasks[0].created_at(), time("20250301T091500Z"));
+        assert!(!tasks[1].completed());
+        assert!(tasks[2].completed());
+        assert_eq!(tasks[2].completed_at(), time("20250303T180000Z"));
+        assert_eq!(tasks[3].deleted_at(), time("20250228T120000Z"));
+        assert!(!tasks[3].completed());
+    }
+
+    #[test]
+    fn tasks_survive_a_round_trip() {
+        let exported = export(&import(FIXTURE).unwrap());
+
+        // Every attribute, such as due, tags, wait and annotations
+        assert_eq!(objects(&exported), objects(FIXTURE));
+        // And as tasks, when read back
+        let again = import(&exported).unwrap();
+        let tasks = import(FIXTURE).unwrap();
+        for (again, task) in again.iter().zip(&tasks) {
+            assert_eq!(again.id(), task.id());
+            assert_eq!(again.description(), task.description());
+            assert_eq!(again.completed(), task.completed());
+            assert_eq!(again.created_at(), task.created_at());
+            assert_eq!(again.completed_at(), task.completed_at());
+            assert_eq!(again.deleted_at(), task.deleted_at());
+            assert_eq!(again.extra(), task.extra());
+        }
+    }
+
+    #[test]
+    fn trashed_tasks_are_exported_as_deleted() {
+        let mut tasks = import(FIXTURE).unwrap();
+        tasks[0].update(TaskMessage::Delete);
+        let state = SavedState {
+            tasks,
+            ..SavedState::default()
+        };
+
+        let exported = Format::Taskwarrior.export(&state);
+
+        let statuses: Vec<_> = objects(&exported)
+            .iter()
+            .map(|object| object["status"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(statuses, ["deleted", "waiting", "completed", "deleted"]);
+        let again = import(&exported).unwrap();
+        // To the second, as Taskwarrior keeps times
+        assert_eq!(
+            again[0].deleted_at().map(|at| at.timestamp()),
+            state.tasks[0].deleted_at().map(|at| at.timestamp())
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
+use uuid::Uuid;
+
+use crate::task::Task;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The `key:value` tag that carries the task id, so importing an export again
+/// updates the tasks instead of adding them twice. Other tools use the same key
+/// for their own ids, so only a UUID is taken for ours.
+const ID_KEY: &str = "id:";
+
+/// One line per task. Completed tasks start with `x` and their completion
+/// date, then comes the creation date and the description, and the id tag
+/// last. Priorities, `+project`, `@context` and `key:value` tokens stay in the
+/// description as written.
+pub fn export<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> String {
+    tasks
+        .into_iter()
+        .map(|task| format_line(task) + "\n")
+        .collect()
+}
+
+/// Reads every non-blank line as a task.
+pub fn import(contents: &str) -> Vec<Task> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+fn format_line(task: &Task) -> String {
+    let (priority, description) = split_priority(task.description());
+    let mut fields = Vec::new();
+
+    if task.completed() {
+        fields.push(String::from("x"));
+    }
+    fields.extend(priority.map(str::to_string));
+
+    // The creation date can only follow a completion date, the format has no
+    // way to mark a lone date as either. Without a completion date the task
+    // is taken to have been done the day it was created.
+    let dates = match (task.completed(), task.completed_at()) {
+        (false, _) => vec![task.created_at()],
+        (true, completed_at) => vec![completed_at.or(task.created_at()), task.created_at()],
+    };
+    fields.extend(dates.into_iter().flatten().map(local_date));
+
+    // A line break would start another task
+    let description = description.replace(['\r', '\n'], " ");
+    if !description.is_empty() {
+        fields.push(escape(&description));
+    }
+
+    fields.push(format!("{}{}", ID_KEY, task.id()));
+
+    fields.join(" ")
+}
+
+fn parse_line(line: &str) -> Task {
+    let mut rest = line;
+
+    let completed = take_token(&mut rest, |token| token == "x").is_some();
+    let priority = take_token(&mut rest, is_priority);
+    let first = take_token(&mut rest, is_date).and_then(parse_date);
+    let (completed_at, created_at) = if completed && first.is_some() {
+        (first, take_token(&mut rest, is_date).and_then(parse_date))
+    } else {
+        (None, first)
+    };
+
+    let (id, rest) = take_id(rest);
+    let rest = unescape(&rest);
+    let description = match priority {
+        Some(priority) => format!("{} {}", priority, rest),
+        None => rest.to_string(),
+    };
+
+    Task::from_parts(
+        id.unwrap_or_else(Uuid::new_v4),
+        description,
+        completed,
+        0,
+        None,
+        created_at.map(start_of_day),
+        completed_at.map(start_of_day),
+    )
+}
+
+/// Takes the last id tag holding a UUID out of `rest`. An export puts it after
+/// the description, which may have tags of the same name, and other apps may
+/// have added tags after it. Any other id tag stays in the description.
+fn take_id(rest: &str) -> (Option<Uuid>, String) {
+    let mut tokens: Vec<&str> = rest.split(' ').collect();
+    let last = tokens.iter().rposition(|token| parse_id(token).is_some());
+
+    let id = last.and_then(|last| parse_id(tokens.remove(last)));
+    (id, tokens.join(" "))
+}
+
+fn parse_id(token: &str) -> Option<Uuid> {
+    token.strip_prefix(ID_KEY).and_then(|id| Uuid::parse_str(id).ok())
+}
+
+/// Whether `description` would be read back as starting with a completion
+/// mark or a date, leading backslashes aside.
+fn needs_escape(description: &str) -> bool {
+    let first = description.split(' ').next().unwrap_or_default();
+    let first = first.trim_start_matches('\\');
+
+    first == "x" || is_date(first)
+}
+
+/// Puts a backslash before a description that would otherwise be misread.
+fn escape(description: &str) -> String {
+    if needs_escape(description) {
+        format!("\\{}", description)
+    } else {
+        description.to_string()
+    }
+}
+
+fn unescape(description: &str) -> &str {
+    match description.strip_prefix('\\') {
+        Some(escaped) if needs_escape(escaped) => escaped,
+        _ => description,
+    }
+}
+
+/// Removes the first space-separated token of `rest` if `accept` likes it.
+fn take_token<'a>(rest: &mut &'a str, accept: impl Fn(&str) -> bool) -> Option<&'a str> {
+    let (token, after) = rest.split_once(' ').unwrap_or((rest, ""));
+
+    if !accept(token) {
+        return None;
+    }
+
+    *rest = after;
+    Some(token)
+}
+
+/// Splits a leading priority such as `(A)` off `description`.
+fn split_priority(description: &str) -> (Option<&str>, &str) {
+    match description.split_once(' ') {
+        Some((token, rest)) if is_priority(token) => (Some(token), rest),
+        _ => (None, description),
+    }
+}
+
+fn is_priority(token: &str) -> bool {
+    matches!(token.as_bytes(), [b'(', b'A'..=b'Z', b')'])
+}
+
+fn is_date(token: &str) -> bool {
+    parse_date(token).is_some()
+}
+
+fn parse_date(token: &str) -> Option<NaiveDate> {
+    // Strictly `YYYY-MM-DD`, chrono would also take unpadded numbers
+    (token.len() == 10)
+        .then(|| NaiveDate::parse_from_str(token, DATE_FORMAT).ok())
+        .flatten()
+}
+
+/// The start of `date` in local time, so exporting gives back the same date.
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    // Midnight may fall into a daylight saving gap
+    [NaiveTime::MIN, NaiveTime::from_hms_opt(1, 0, 0).unwrap_or_default()]
+        .into_iter()
+        .find_map(|time| date.and_time(time).and_local_timezone(Local).earliest())
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| date.and_time(NaiveTime::MIN).and_utc())
+}
+
+fn local_date(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format(DATE_FORMAT).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::state::interchange::{merge, Mode};
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/todo.txt");
+
+    fn date(date: &str) -> Option<DateTime<Utc>> {
+        parse_date(date).map(start_of_day)
+    }
+
+    fn task(description: &str, completed: bool, created_at: &str, completed_at: &str) -> Task {
+        Task::from_parts(
+            Uuid::new_v4(),
+            description.to_string(),
+            completed,
+            0,
+            None,
+            date(created_at),
+            date(completed_at),
+        )
+    }
+
+    /// Everything todo.txt keeps of a task.
+    fn fields(task: &Task) -> (Uuid, String, bool, Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        (
+            task.id(),
+            task.description().to_string(),
+            task.completed(),
+            task.created_at(),
+            task.completed_at(),
+        )
+    }
+
+    #[test]
+    fn tasks_survive_a_round_trip() {
+        let tasks = [
+            task("Buy milk", false, "", ""),
+            task("(A) Call mom @phone", false, "2025-03-01", ""),
+            task("Pay rent", true, "2025-03-01", "2025-03-02"),
+            task("Done without a creation date", true, "", "2025-03-02"),
+            task("x marks the spot", false, "", ""),
+            task("x marks the spot", true, "", ""),
+            task("2025-04-01 is the deadline", false, "", ""),
+            task("2025-04-01 is the deadline", true, "", "2025-03-02"),
+            task("(B) 2025-04-01 is the deadline", false, "", ""),
+            task("\\x is a backslash and an x", false, "", ""),
+            task("\\\\2025-04-01 has two", false, "2025-03-01", ""),
+            task("Refers to id:42 and ends with a space ", false, "", ""),
+            task("", false, "2025-03-01", ""),
+            task("(C) ", false, "", ""),
+        ];
+
+        let imported = import(&export(&tasks));
+
+        assert_eq!(
+            imported.iter().map(fields).collect::<Vec<_>>(),
+            tasks.iter().map(fields).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn a_missing_completion_date_becomes_the_creation_date() {
+        let done = task("Water the plants", true, "2025-03-01", "");
+
+        let line = export([&done]);
+        assert!(line.starts_with("x 2025-03-01 2025-03-01 Water the plants id:"));
+
+        let imported = &import(&line)[0];
+        assert_eq!(imported.created_at(), done.created_at());
+        assert_eq!(imported.completed_at(), done.created_at());
+    }
+
+    #[test]
+    fn importing_an_export_again_changes_nothing() {
+        let tasks: Vec<Task> = import(FIXTURE)
+            .into_iter()
+            .enumerate()
+            .map(|(position, mut task)| {
+                task.set_position(position as i64);
+                task
+            })
+            .collect();
+
+        let merge = merge(&tasks, import(&export(&tasks)), Mode::Merge);
+
+        assert_eq!((merge.added, merge.updated, merge.unchanged), (0, 0, tasks.len()));
+    }
+
+    #[test]
+    fn other_apps_files_are_read_field_by_field() {
+        let tasks = import(FIXTURE);
+        let read: Vec<_> = tasks
+            .iter()
+            .map(|task| (task.description(), task.completed(), task.created_at(), task.completed_at()))
+            .collect();
+
+        assert_eq!(
+            read,
+            [
+                ("(A) Thank Mom for the meatballs @phone", false, None, None),
+                ("(B) Schedule Goodwill pickup +GarageSale @phone", false, None, None),
+                ("Document +TodoTxt task format", false, date("2011-03-02"), None),
+                ("(A) Call Mom", false, date("2011-03-02"), None),
+                ("Call Mom", true, None, date("2011-03-03")),
+                (
+                    "Review Tim's pull request +TodoTxtTouch @github",
+                    true,
+                    date("2011-03-01"),
+                    date("2011-03-02")
+                ),
+                ("Post signs around the neighborhood +GarageSale due:2011-03-05", false, None, None),
+                ("Find the spare keys id:1", false, None, None),
+                ("Call Bob about the keys id:2 dep:1", false, None, None),
+                ("Lend Bob the keys id:2", false, None, None),
+            ]
+        );
+        // Another tool's ids are its own, even when they repeat
+        let ids: HashSet<Uuid> = tasks.iter().map(Task::id).collect();
+        assert_eq!(ids.len(), tasks.len());
+
+        // Written back as they were, with the ids added
+        let exported = export(&tasks);
+        for (line, original) in exported.lines().zip(FIXTURE.lines()) {
+            let (without_id, id) = line.rsplit_once(' ').unwrap();
+            assert_eq!(without_id, original);
+            assert!(id.starts_with(ID_KEY));
+        }
+    }
+}
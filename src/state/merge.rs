@@ -0,0 +1,96 @@
+//! Combining two versions of a task that both changed since a version they
+//! share, field by field. Used where the task list is kept in more than one
+//! place: a CalDAV sync that ran while the task changed here, and a git pull
+//! that brings in a task changed on another machine.
+
+use crate::task::Task;
+
+/// The changes from `before` to `after` applied on top of `current`, which
+/// also started from `before`. Fields `current` changed keep its value, the
+/// rest take `after`'s. The place in the list counts as one of them, so a
+/// reorder on either side is kept.
+pub fn rebase(current: &Task, before: &Task, after: &Task) -> Task {
+    let pick = |unchanged: bool| if unchanged { after } else { current };
+
+    let description = pick(current.description() == before.description());
+    let done = pick(current.completed() == before.completed() && current.completed_at() == before.completed_at());
+    let trash = pick(current.deleted_at() == before.deleted_at());
+    let created = pick(current.created_at() == before.created_at());
+    let extra = pick(current.extra() == before.extra());
+    let place = pick(current.position() == before.position());
+
+    let mut task = Task::from_parts(
+        current.id(),
+        description.description().to_string(),
+        done.completed(),
+        place.position(),
+        trash.deleted_at(),
+        created.created_at(),
+        done.completed_at(),
+    );
+    *task.extra_mut() = extra.extra().clone();
+
+    task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::persistence::{edit_task, rename};
+    use crate::task::TaskMessage;
+
+    fn renamed(task: &Task, to: &str) -> Task {
+        let mut task = task.clone();
+        edit_task(&mut task, rename(to));
+        task
+    }
+
+    fn completed(task: &Task) -> Task {
+        let mut task = task.clone();
+        edit_task(&mut task, vec![TaskMessage::Completed(true)]);
+        task
+    }
+
+    #[test]
+    fn each_side_keeps_the_fields_it_changed() {
+        let before = Task::new(String::from("Buy milk"));
+        let current = completed(&before);
+        let after = renamed(&before, "Buy oat milk");
+
+        let task = rebase(&current, &before, &after);
+
+        assert_eq!(task.description(), "Buy oat milk");
+        assert!(task.completed());
+        assert_eq!(task.completed_at(), current.completed_at());
+    }
+
+    #[test]
+    fn a_field_changed_on_both_sides_keeps_the_current_value() {
+        let before = Task::new(String::from("Buy milk"));
+        let current = renamed(&before, "Buy oat milk");
+        let after = completed(&renamed(&before, "Buy soy milk"));
+
+        let task = rebase(&current, &before, &after);
+
+        assert_eq!(task.description(), "Buy oat milk");
+        assert!(task.completed());
+    }
+
+    #[test]
+    fn a_reorder_on_either_side_is_kept() {
+        let before = Task::new(String::from("Buy milk"));
+        let mut moved = before.clone();
+        moved.set_position(3);
+        let renamed = renamed(&before, "Buy oat milk");
+
+        assert_eq!(rebase(&moved, &before, &renamed).position(), 3);
+        let task = rebase(&renamed, &before, &moved);
+        assert_eq!(task.position(), 3);
+        assert_eq!(task.description(), "Buy oat milk");
+
+        // Moved on both sides, `current` wins like any other field
+        let mut moved_too = renamed.clone();
+        moved_too.set_position(7);
+        assert_eq!(rebase(&moved_too, &before, &moved).position(), 7);
+    }
+}
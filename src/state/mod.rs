@@ -1,17 +1,503 @@
+pub mod caldav;
+pub mod changes;
 pub mod filter;
+pub mod history;
+pub mod interchange;
+pub mod merge;
 pub mod persistence;
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+use iced::time::Instant;
+use uuid::Uuid;
+
 use crate::i18n::Language;
-use crate::task::Task;
+use crate::task::{Task, TaskState};
+use caldav::{Collection, SyncError, Synced};
+use interchange::{Format, Merge, Mode};
+use persistence::{MaintenanceReport, Preferences, Snapshot};
+pub use changes::ChangeSet;
 pub use filter::Filter;
+pub use history::{HistoryEntry, HistoryPanel};
+use history::Change;
+use persistence::{SaveError, Storage};
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct State {
+    pub storage: Arc<dyn Storage>,
     pub input_value: String,
     pub filter: Filter,
     pub tasks: Vec<Task>,
+    pub changes: ChangeSet,
+    /// There are changes that have not been handed to storage yet.
     pub dirty: bool,
     pub saving: bool,
+    pub last_mutation: Option<Instant>,
+    /// The window is closing and waits for pending changes to be saved.
+    pub closing: bool,
+    pub last_saved: Option<DateTime<Local>>,
+    pub save_failure: Option<SaveFailure>,
+    /// A check for, or reload of, changes made by other processes is running.
+    pub reloading: bool,
     pub input_hovered: bool,
+    pub selected: Option<Uuid>,
+    pub drag: Option<Drag>,
+    pub notice: Option<Notice>,
+    /// The stored data is encrypted with a passphrase.
+    pub encrypted: bool,
+    pub passphrase_dialog: Option<PassphraseDialog>,
+    pub history_panel: Option<HistoryPanel>,
+    /// Nothing is saved while this is set.
+    pub read_only: Option<ReadOnly>,
     pub language: Language,
-}
\ No newline at end of file
+    /// Last size and position of the window while not fullscreen.
+    pub window_size: Option<(f32, f32)>,
+    pub window_position: Option<(f32, f32)>,
+    pub fullscreen: bool,
+    pub trash_retention_days: Option<u32>,
+    pub backup_interval_hours: u32,
+    pub backup_generations: u32,
+    pub backup_dialog: Option<BackupDialog>,
+    pub interchange_dialog: Option<InterchangeDialog>,
+    /// The CalDAV collection the tasks are synced with, if any.
+    pub caldav: Option<Collection>,
+    pub sync_status: SyncStatus,
+    pub sync_dialog: Option<SyncDialog>,
+}
+
+/// Why the data was opened read-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadOnly {
+    /// The normal load failed and the user chose to look at what is left.
+    LoadFailed,
+    /// Another instance holds the data directory's lock.
+    AlreadyRunning,
+}
+
+/// The dialog for encrypting the data or changing its passphrase.
+#[derive(Debug, Clone, Default)]
+pub struct PassphraseDialog {
+    pub passphrase: String,
+    pub confirmation: String,
+    /// Translation key of the problem with the last submission.
+    pub error: Option<&'static str>,
+    /// The data is being re-encrypted.
+    pub working: bool,
+}
+
+/// The dialog listing the backups that can be restored.
+#[derive(Debug, Clone, Default)]
+pub struct BackupDialog {
+    /// Newest first. `None` while loading.
+    pub snapshots: Option<Vec<Snapshot>>,
+    /// Translation key of the problem with the last action.
+    pub error: Option<&'static str>,
+    /// A backup, restore or maintenance run is in progress.
+    pub working: bool,
+    /// The last maintenance run started from the dialog.
+    pub maintenance: Option<Maintenance>,
+}
+
+/// The dialog for exporting the tasks to a file and importing them from one.
+#[derive(Debug, Clone, Default)]
+pub struct InterchangeDialog {
+    /// The file to import from. Exports go next to it, named for the format.
+    pub path: String,
+    /// What exports are written as.
+    pub format: Format,
+    pub mode: Mode,
+    /// What importing the file would do, once it has been read.
+    pub preview: Option<ImportPreview>,
+    /// Translation key of the problem with the last action.
+    pub error: Option<&'static str>,
+    /// Where the last export was written.
+    pub exported: Option<PathBuf>,
+    /// A file is being read or written.
+    pub working: bool,
+}
+
+/// The dialog for connecting to a CalDAV collection.
+#[derive(Debug, Clone, Default)]
+pub struct SyncDialog {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    /// Translation key of the problem with the last submission.
+    pub error: Option<&'static str>,
+}
+
+/// How syncing with the CalDAV collection is going.
+#[derive(Debug, Clone, Default)]
+pub enum SyncStatus {
+    /// Not synced since the app started.
+    #[default]
+    Idle,
+    Syncing,
+    /// The last sync finished, at this time.
+    Synced(DateTime<Local>),
+    /// The last sync stopped with this error. What it did before stays.
+    Failed(SyncError),
+}
+
+/// A file read for import, not applied yet.
+#[derive(Debug, Clone)]
+pub struct ImportPreview {
+    pub tasks: Vec<Task>,
+    /// Worked out against the tasks as they were when the file was read.
+    pub merge: Merge,
+}
+
+/// A maintenance run started from the UI.
+#[derive(Debug, Clone)]
+pub enum Maintenance {
+    Running,
+    Done(MaintenanceReport),
+    Failed,
+}
+
+/// The most recent save failed. Kept until a save succeeds.
+#[derive(Debug, Clone)]
+pub struct SaveFailure {
+    pub error: SaveError,
+    pub attempts: u32,
+    /// A retry is scheduled; saving holds off until it fires.
+    pub retry_pending: bool,
+    /// Closing was asked for and the save made for it failed as well. The
+    /// window stays open until the user chooses to close without saving.
+    pub close_blocked: bool,
+}
+
+impl SaveFailure {
+    /// Failed attempts after which the failure is shown as a banner.
+    pub const PERSISTENT: u32 = 3;
+
+    /// Exponential backoff, capped at a minute.
+    pub fn retry_delay(&self) -> Duration {
+        Duration::from_secs(2u64.saturating_pow(self.attempts).min(60))
+    }
+
+    /// Whether the failure is shown as a banner rather than in the status bar.
+    pub fn is_persistent(&self) -> bool {
+        self.attempts >= Self::PERSISTENT || self.close_blocked
+    }
+}
+
+/// A task being dragged to a new place in the list, by index into `tasks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Drag {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// A one-off message shown above the task list until dismissed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notice {
+    LegacyImported(usize),
+    BackedUp(String),
+}
+
+impl State {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            input_value: String::new(),
+            filter: Filter::default(),
+            tasks: Vec::new(),
+            changes: ChangeSet::default(),
+            dirty: false,
+            saving: false,
+            last_mutation: None,
+            closing: false,
+            last_saved: None,
+            save_failure: None,
+            reloading: false,
+            input_hovered: false,
+            selected: None,
+            drag: None,
+            notice: None,
+            encrypted: false,
+            passphrase_dialog: None,
+            history_panel: None,
+            read_only: None,
+            language: Language::default(),
+            window_size: None,
+            window_position: None,
+            fullscreen: false,
+            trash_retention_days: Some(Preferences::DEFAULT_TRASH_RETENTION_DAYS),
+            backup_interval_hours: Preferences::DEFAULT_BACKUP_INTERVAL_HOURS,
+            backup_generations: Preferences::DEFAULT_BACKUP_GENERATIONS,
+            backup_dialog: None,
+            interchange_dialog: None,
+            caldav: None,
+            sync_status: SyncStatus::default(),
+            sync_dialog: None,
+        }
+    }
+
+    /// Restores the preferences loaded with the data.
+    pub fn with_preferences(self, preferences: Preferences) -> Self {
+        Self {
+            language: preferences.language.unwrap_or(self.language),
+            window_size: preferences.window_size,
+            window_position: preferences.window_position,
+            fullscreen: preferences.fullscreen,
+            trash_retention_days: preferences.trash_retention_days,
+            backup_interval_hours: preferences.backup_interval_hours,
+            backup_generations: preferences.backup_generations,
+            ..self
+        }
+    }
+
+    pub fn preferences(&self) -> Preferences {
+        Preferences {
+            language: Some(self.language),
+            window_size: self.window_size,
+            window_position: self.window_position,
+            fullscreen: self.fullscreen,
+            trash_retention_days: self.trash_retention_days,
+            backup_interval_hours: self.backup_interval_hours,
+            backup_generations: self.backup_generations,
+        }
+    }
+
+    /// A restore or maintenance run is replacing the stored data, so nothing
+    /// may read or write it meanwhile.
+    pub fn replacing_data(&self) -> bool {
+        self.backup_dialog.as_ref().is_some_and(|dialog| dialog.working)
+    }
+
+    /// Queues every task for saving, e.g. after a repair may have lost some.
+    pub fn save_all(&mut self) {
+        for task in &self.tasks {
+            self.changes.upsert(task.id());
+        }
+        self.dirty = true;
+    }
+
+    /// Applies an import worked out by `interchange::merge`.
+    pub fn apply_import(&mut self, merge: Merge) {
+        for id in merge.removed {
+            self.tasks.retain(|task| task.id() != id);
+            self.changes.delete(id);
+        }
+
+        for task in merge.upserted {
+            self.changes.upsert(task.id());
+            match self.tasks.iter_mut().find(|stored| stored.id() == task.id()) {
+                Some(stored) => *stored = task,
+                None => self.tasks.push(task),
+            }
+        }
+
+        for entry in merge.history {
+            self.record(entry);
+        }
+
+        self.tasks.sort_by_key(Task::position);
+        self.drag = None;
+        if let Some(id) = self.selected
+            && !self.tasks.iter().any(|task| task.id() == id)
+        {
+            self.selected = None;
+        }
+    }
+
+    /// Applies what a sync did. Tasks changed here while it ran keep those
+    /// changes, which go out with the next sync.
+    pub fn apply_sync(&mut self, synced: Synced) {
+        for (before, after) in synced.updated {
+            let Some(task) = self.tasks.iter_mut().find(|task| task.id() == after.id()) else {
+                // Deleted for good meanwhile, which the next sync passes on
+                continue;
+            };
+
+            let rebased = merge::rebase(task, &before, &after);
+            let entries: Vec<HistoryEntry> = interchange::changes_between(task, &rebased)
+                .into_iter()
+                .map(|change| HistoryEntry::new(task, change))
+                .collect();
+
+            task.replace_content(rebased);
+            self.changes.upsert(task.id());
+            for entry in entries {
+                self.record(entry);
+            }
+        }
+
+        for task in synced.added {
+            if !self.tasks.iter().any(|existing| existing.id() == task.id()) {
+                self.add_task(task);
+            }
+        }
+
+        self.caldav = Some(synced.collection);
+    }
+
+    /// Queues a change for the history, showing it in the panel if open.
+    pub fn record(&mut self, entry: HistoryEntry) {
+        if let Some(panel) = &mut self.history_panel {
+            panel.record(&entry);
+        }
+        self.changes.record(entry);
+    }
+
+    /// Deletes for good the tasks that have been in the trash for longer than
+    /// the retention period. Returns whether there were any.
+    pub fn purge_trash(&mut self, now: DateTime<Utc>) -> bool {
+        let Some(days) = self.trash_retention_days else {
+            return false;
+        };
+        let cutoff = now - chrono::Duration::days(days.into());
+
+        self.remove_trashed(|deleted_at| deleted_at <= cutoff)
+    }
+
+    /// Deletes every task in the trash for good.
+    pub fn empty_trash(&mut self) -> bool {
+        self.remove_trashed(|_| true)
+    }
+
+    fn remove_trashed(&mut self, expired: impl Fn(DateTime<Utc>) -> bool) -> bool {
+        let before = self.tasks.len();
+        let changes = &mut self.changes;
+
+        self.tasks.retain(|task| match task.deleted_at() {
+            Some(deleted_at) if expired(deleted_at) => {
+                changes.delete(task.id());
+                false
+            }
+            _ => true,
+        });
+
+        self.tasks.len() != before
+    }
+
+    /// Appends a new task, recording its creation.
+    pub fn add_task(&mut self, mut task: Task) {
+        task.set_position(self.next_position());
+        self.changes.upsert(task.id());
+        self.record(HistoryEntry::new(
+            &task,
+            Change::Created {
+                description: task.description().to_string(),
+            },
+        ));
+        self.tasks.push(task);
+    }
+
+    /// Position for a task appended to the end of the list.
+    pub fn next_position(&self) -> i64 {
+        self.tasks.last().map_or(0, |task| task.position() + 1)
+    }
+
+    /// Renumbers every task if positions are not strictly increasing, as in
+    /// data saved before manual ordering existed. Returns whether it did.
+    pub fn normalize_positions(&mut self) -> bool {
+        let ordered = self
+            .tasks
+            .windows(2)
+            .all(|pair| pair[0].position() < pair[1].position());
+
+        if ordered {
+            return false;
+        }
+
+        for (position, task) in self.tasks.iter_mut().enumerate() {
+            task.set_position(position as i64);
+            self.changes.upsert(task.id());
+        }
+
+        true
+    }
+
+    /// Moves the task at `from` to `to`, shifting the ones in between.
+    ///
+    /// The positions already used by the affected range are handed out again
+    /// in the new order, so only the tasks in that range need saving.
+    pub fn move_task(&mut self, from: usize, to: usize) {
+        if from == to || from >= self.tasks.len() || to >= self.tasks.len() {
+            return;
+        }
+
+        let task = self.tasks.remove(from);
+        self.tasks.insert(to, task);
+
+        let range = from.min(to)..=from.max(to);
+        let mut positions: Vec<i64> = self.tasks[range.clone()]
+            .iter()
+            .map(Task::position)
+            .collect();
+        positions.sort_unstable();
+
+        for (task, position) in self.tasks[range].iter_mut().zip(positions) {
+            task.set_position(position);
+            self.changes.upsert(task.id());
+        }
+    }
+
+    /// Moves the selected task past its neighbour in the given direction,
+    /// skipping tasks hidden by the current filter.
+    pub fn move_selected(&mut self, up: bool) {
+        let Some(from) = self
+            .selected
+            .and_then(|id| self.tasks.iter().position(|task| task.id() == id))
+        else {
+            return;
+        };
+
+        let filter = self.filter;
+        let to = if up {
+            self.tasks[..from].iter().rposition(|task| filter.matches(task))
+        } else {
+            self.tasks[from + 1..]
+                .iter()
+                .position(|task| filter.matches(task))
+                .map(|offset| from + 1 + offset)
+        };
+
+        if let Some(to) = to {
+            self.move_task(from, to);
+        }
+    }
+
+    /// Takes the tasks as another process saved them. Local tasks with unsaved
+    /// changes, and tasks being edited, are kept as they are; everything else
+    /// follows `saved`, including deletions. Returns whether positions had to
+    /// be renumbered, which leaves changes to save.
+    pub fn merge_external(&mut self, saved: Vec<Task>) -> bool {
+        let keep_local = |changes: &ChangeSet, task: &Task| {
+            changes.contains(task.id()) || matches!(task.state(), TaskState::Editing { .. })
+        };
+
+        let mut local: HashMap<Uuid, Task> =
+            self.tasks.drain(..).map(|task| (task.id(), task)).collect();
+        let mut merged = Vec::with_capacity(saved.len());
+
+        for task in saved {
+            match local.remove(&task.id()) {
+                Some(existing) if keep_local(&self.changes, &existing) => merged.push(existing),
+                // Deleted here, not saved yet
+                None if self.changes.contains(task.id()) => {}
+                _ => merged.push(task),
+            }
+        }
+
+        // Created here and not saved yet
+        merged.extend(local.into_values().filter(|task| keep_local(&self.changes, task)));
+        merged.sort_by_key(Task::position);
+
+        self.tasks = merged;
+        self.drag = None;
+        if let Some(id) = self.selected
+            && !self.tasks.iter().any(|task| task.id() == id)
+        {
+            self.selected = None;
+        }
+
+        self.normalize_positions()
+    }
+}
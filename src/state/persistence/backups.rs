@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use super::sqlite_persistence::SqlitePersistence;
+use super::{LoadError, SaveError, Snapshot};
+
+const PREFIX: &str = "todos-";
+const SUFFIX: &str = ".db";
+/// Sorts the same as the time it encodes, and is safe in file names.
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// `path` with `suffix` appended to its file name, e.g. for `-wal` files.
+pub fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+fn created_at(path: &Path) -> Option<DateTime<Utc>> {
+    let time = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix(PREFIX)?
+        .strip_suffix(SUFFIX)?;
+
+    NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// The snapshot files in `dir` with their creation time, newest first.
+async fn files(dir: &Path) -> Result<Vec<(DateTime<Utc>, PathBuf)>, std::io::Error> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        // Nothing backed up yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if let Some(created_at) = created_at(&path) {
+            files.push((created_at, path));
+        }
+    }
+    files.sort_by_key(|(created_at, _)| std::cmp::Reverse(*created_at));
+
+    Ok(files)
+}
+
+/// Snapshots the open database into `dir`. If nothing changed since the
+/// newest snapshot, that one is returned instead of taking another, so
+/// restarting without editing anything never rotates older snapshots out.
+pub async fn create(persistence: &SqlitePersistence, dir: &Path) -> Result<Snapshot, SaveError> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(SaveError::write)?;
+
+    // Whole seconds, so the time survives the round trip through the name
+    let mut created_at = DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap_or_default();
+    let mut path = dir.join(format!("{}{}{}", PREFIX, created_at.format(TIME_FORMAT), SUFFIX));
+
+    // Taken within the same second, nothing new to keep
+    if !tokio::fs::try_exists(&path).await.map_err(SaveError::write)? {
+        let newest = files(dir).await.map_err(SaveError::write)?.into_iter().next();
+        persistence.vacuum_into(&path).await?;
+
+        // A vacuumed copy is the same bytes for the same data
+        if let Some((newest_created_at, newest_path)) = newest
+            && same_contents(&newest_path, &path).await?
+        {
+            tokio::fs::remove_file(&path)
+                .await
+                .map_err(SaveError::write)?;
+            created_at = newest_created_at;
+            path = newest_path;
+        }
+    }
+
+    Ok(Snapshot {
+        path,
+        created_at,
+        task_count: persistence.task_count().await.ok(),
+    })
+}
+
+async fn same_contents(a: &Path, b: &Path) -> Result<bool, SaveError> {
+    let a = tokio::fs::read(a).await.map_err(SaveError::write)?;
+    let b = tokio::fs::read(b).await.map_err(SaveError::write)?;
+
+    Ok(a == b)
+}
+
+/// The snapshots in `dir`, newest first, with their task counts.
+pub async fn list(dir: &Path) -> Result<Vec<Snapshot>, LoadError> {
+    let mut snapshots = Vec::new();
+
+    for (created_at, path) in files(dir).await.map_err(LoadError::file)? {
+        let task_count = match SqlitePersistence::open_read_only(&path).await {
+            Ok(persistence) => {
+                let count = persistence.task_count().await.ok();
+                persistence.close().await;
+                count
+            }
+            Err(_) => None,
+        };
+
+        snapshots.push(Snapshot {
+            path,
+            created_at,
+            task_count,
+        });
+    }
+
+    Ok(snapshots)
+}
+
+/// Deletes all but the `keep` newest snapshots in `dir`. At least one is
+/// always kept.
+pub async fn rotate(dir: &Path, keep: u32) -> Result<(), SaveError> {
+    let files = files(dir).await.map_err(SaveError::write)?;
+
+    for (_, path) in files.into_iter().skip(keep.max(1) as usize) {
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(SaveError::write)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the snapshot at `path` could be loaded.
+pub async fn check(path: &Path) -> Result<(), LoadError> {
+    let persistence = SqlitePersistence::open_read_only(path).await?;
+    let result = persistence.check().await;
+    persistence.close().await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::persistence::{test_dir, SavedChanges};
+    use crate::task::Task;
+
+    async fn add_task(persistence: &SqlitePersistence, description: &str) {
+        persistence
+            .save(
+                SavedChanges {
+                    upserted: vec![Task::new(description.to_string())],
+                    ..SavedChanges::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    /// Waits for the next second, which snapshot names go by.
+    async fn next_second() {
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    }
+
+    #[tokio::test]
+    async fn unchanged_data_does_not_rotate_snapshots_out() {
+        let dir = test_dir();
+        let backup_dir = dir.join("backups");
+        let persistence = SqlitePersistence::new(&dir.join("todos.db")).await.unwrap();
+
+        add_task(&persistence, "Buy milk").await;
+        let first = create(&persistence, &backup_dir).await.unwrap();
+        assert_eq!(first.task_count, Some(1));
+
+        // Restarts without an edit in between
+        for _ in 0..2 {
+            next_second().await;
+            let again = create(&persistence, &backup_dir).await.unwrap();
+            rotate(&backup_dir, 1).await.unwrap();
+            assert_eq!(again, first);
+        }
+        assert_eq!(list(&backup_dir).await.unwrap(), std::slice::from_ref(&first));
+
+        next_second().await;
+        add_task(&persistence, "Call mom").await;
+        let changed = create(&persistence, &backup_dir).await.unwrap();
+        assert!(changed.created_at > first.created_at);
+        assert_eq!(changed.task_count, Some(2));
+        assert_eq!(list(&backup_dir).await.unwrap(), [changed, first]);
+
+        persistence.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,99 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use super::LoadError;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Encrypted along with the key info, so a wrong passphrase can be told apart
+/// from damaged data.
+const CHECK: &[u8] = b"todos";
+
+/// What is stored about the key: enough to derive it again from the
+/// passphrase and to tell whether a passphrase is the right one.
+#[derive(Debug, Clone)]
+pub struct KeyInfo {
+    pub salt: Vec<u8>,
+    pub check: Vec<u8>,
+}
+
+/// Encrypts task data with a key derived from the user's passphrase.
+///
+/// Each value is sealed on its own as the nonce followed by the ciphertext,
+/// so rows can still be written one at a time.
+#[derive(Clone)]
+pub struct Cipher {
+    cipher: ChaCha20Poly1305,
+    salt: Vec<u8>,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cipher { .. }")
+    }
+}
+
+impl Cipher {
+    /// Derives a key for `passphrase` with a fresh salt. Slow on purpose, so
+    /// run it off the UI thread.
+    pub fn generate(passphrase: &str) -> Result<Self, argon2::Error> {
+        let mut salt = vec![0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        Self::derive(passphrase, salt)
+    }
+
+    /// Derives the key described by `key_info`, failing with
+    /// `LoadError::WrongPassphrase` if `passphrase` does not match it.
+    pub fn unlock(passphrase: &str, key_info: &KeyInfo) -> Result<Self, LoadError> {
+        let cipher = Self::derive(passphrase, key_info.salt.clone()).map_err(LoadError::format)?;
+
+        if cipher.open(&key_info.check).as_deref() != Some(CHECK) {
+            return Err(LoadError::WrongPassphrase);
+        }
+
+        Ok(cipher)
+    }
+
+    fn derive(passphrase: &str, salt: Vec<u8>) -> Result<Self, argon2::Error> {
+        let mut key = Key::default();
+        Argon2::default().hash_password_into(passphrase.as_bytes(), &salt, &mut key)?;
+
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(&key),
+            salt,
+        })
+    }
+
+    pub fn key_info(&self) -> KeyInfo {
+        KeyInfo {
+            salt: self.salt.clone(),
+            check: self.seal(CHECK),
+        }
+    }
+
+    pub fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .expect("task data is far below the size limit of ChaCha20-Poly1305");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        sealed
+    }
+
+    /// Returns `None` if `sealed` was not sealed with this key, or was altered.
+    pub fn open(&self, sealed: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+    }
+}
@@ -0,0 +1,917 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::{LoadError, Preferences, SaveError, SavedChanges, SavedState, Storage, StorageFuture};
+use crate::state::caldav::Collection;
+use crate::state::history::HistoryEntry;
+use crate::state::merge::rebase;
+use crate::state::Filter;
+use crate::task::Task;
+
+/// How often the remote is checked for changes, unless a save is waiting to
+/// be pushed.
+const PULL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What belongs to this machine rather than the task list: the window, the
+/// input and the CalDAV password. Kept out of the repository.
+const LOCAL_FILE: &str = "local.json";
+
+const GITIGNORE: &str = "local.json\n*.tmp\n";
+
+/// Task files are never merged line by line, which could leave them invalid
+/// JSON; a task changed on both sides is resolved as a whole instead. History
+/// files only ever grow, so both sides' lines are kept.
+const GITATTRIBUTES: &str = "tasks/*.json merge=binary\nhistory/*.jsonl merge=union\n";
+
+/// Stores each task as a JSON file in a git repository, with its history next
+/// to it, and commits on every save. If the repository has an `origin`
+/// remote, it is pulled from and pushed to while the app runs.
+#[derive(Debug, Clone)]
+pub struct GitStorage {
+    dir: PathBuf,
+    /// Held while git works on the repository, so a save and a pull never
+    /// run into each other.
+    repository: Arc<Mutex<Repository>>,
+}
+
+#[derive(Debug, Default)]
+struct Repository {
+    /// The commit the app last loaded or saved. Any other commit at HEAD came
+    /// from elsewhere, e.g. a pull.
+    known_head: Option<String>,
+    last_pull: Option<Instant>,
+    /// A save was committed and not pushed yet.
+    push_due: bool,
+    /// Looked up when the repository is opened, rather than on every commit.
+    author: Option<Author>,
+}
+
+impl Repository {
+    async fn author(&mut self, dir: &Path) -> Author {
+        match self.author {
+            Some(author) => author,
+            None => *self.author.insert(Author::resolve(dir).await),
+        }
+    }
+}
+
+/// Who commits are made as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Author {
+    /// Whoever git is configured with.
+    Configured,
+    /// A stand-in for machines where git has no author configured, as
+    /// committing would fail otherwise.
+    Fallback,
+}
+
+impl Author {
+    /// Committing needs both a name and an email, neither of them empty.
+    async fn resolve(dir: &Path) -> Self {
+        for key in ["user.name", "user.email"] {
+            match git(dir, &["config", key]).await {
+                Ok(value) if !value.trim().is_empty() => {}
+                _ => return Author::Fallback,
+            }
+        }
+
+        Author::Configured
+    }
+
+    /// Runs git in `dir` as this author, to commit or merge.
+    async fn git(self, dir: &Path, args: &[&str]) -> Result<String, String> {
+        match self {
+            Author::Configured => git(dir, args).await,
+            Author::Fallback => {
+                let mut with_author = vec!["-c", "user.name=Todos", "-c", "user.email=todos@localhost"];
+                with_author.extend_from_slice(args);
+                git(dir, &with_author).await
+            }
+        }
+    }
+}
+
+/// What is kept in `LOCAL_FILE`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Local {
+    input_value: String,
+    filter: Filter,
+    preferences: Preferences,
+    caldav: Option<Collection>,
+}
+
+impl Local {
+    fn into_state(self, tasks: Vec<Task>) -> SavedState {
+        SavedState {
+            input_value: self.input_value,
+            filter: self.filter,
+            tasks,
+            preferences: self.preferences,
+            caldav: self.caldav,
+            ..SavedState::default()
+        }
+    }
+}
+
+impl GitStorage {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            repository: Arc::default(),
+        }
+    }
+
+    fn task_path(dir: &Path, id: Uuid) -> PathBuf {
+        dir.join("tasks").join(format!("{}.json", id))
+    }
+
+    fn history_path(dir: &Path, id: Uuid) -> PathBuf {
+        dir.join("history").join(format!("{}.jsonl", id))
+    }
+
+    /// Reads the tasks and the local settings. The history is left to
+    /// `load_history`. With `lenient`, files that fail to parse are skipped.
+    async fn read(dir: &Path, lenient: bool) -> Result<SavedState, LoadError> {
+        let local = match tokio::fs::read_to_string(dir.join(LOCAL_FILE)).await {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(local) => local,
+                Err(_) if lenient => Local::default(),
+                Err(e) => return Err(LoadError::format(format!("{}: {}", LOCAL_FILE, e))),
+            },
+            // First run, nothing saved yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Local::default(),
+            Err(e) => return Err(LoadError::file(e)),
+        };
+
+        let mut tasks = Vec::new();
+        let mut skipped = 0;
+        let mut entries = match tokio::fs::read_dir(dir.join("tasks")).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(local.into_state(tasks)),
+            Err(e) => return Err(LoadError::file(e)),
+        };
+
+        while let Some(entry) = entries.next_entry().await.map_err(LoadError::file)? {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+
+            let contents = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(LoadError::file)?;
+            match serde_json::from_str::<Task>(&contents) {
+                Ok(task) => tasks.push(task),
+                Err(_) if lenient => skipped += 1,
+                Err(e) => {
+                    return Err(LoadError::format(format!("{}: {}", path.display(), e)));
+                }
+            }
+        }
+
+        // The directory lists in no set order, so ties go by id
+        tasks.sort_by_key(Task::id);
+        tasks.sort_by_key(Task::position);
+
+        Ok(SavedState {
+            skipped,
+            ..local.into_state(tasks)
+        })
+    }
+
+    /// Creates the repository on first use.
+    async fn init(dir: &Path) -> Result<(), String> {
+        if !tokio::fs::try_exists(dir.join(".git"))
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|e| e.to_string())?;
+            git(dir, &["init", "-q"]).await?;
+        }
+
+        for (name, contents) in [(".gitignore", GITIGNORE), (".gitattributes", GITATTRIBUTES)] {
+            let path = dir.join(name);
+            if !tokio::fs::try_exists(&path).await.map_err(|e| e.to_string())? {
+                tokio::fs::write(&path, contents)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the changes to the files and commits them.
+    async fn write(dir: &Path, changes: SavedChanges, author: Author) -> Result<bool, String> {
+        for subdir in ["tasks", "history"] {
+            tokio::fs::create_dir_all(dir.join(subdir))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        for task in &changes.upserted {
+            write_file(&Self::task_path(dir, task.id()), task_json(task)).await?;
+        }
+
+        for id in &changes.deleted {
+            for path in [Self::task_path(dir, *id), Self::history_path(dir, *id)] {
+                match tokio::fs::remove_file(&path).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        for entry in changes.history.iter().filter(|entry| !changes.deleted.contains(&entry.task_id)) {
+            use tokio::io::AsyncWriteExt;
+
+            let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+            line.push('\n');
+            tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(Self::history_path(dir, entry.task_id))
+                .await
+                .map_err(|e| e.to_string())?
+                .write_all(line.as_bytes())
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        let local = Local {
+            input_value: changes.input_value,
+            filter: changes.filter,
+            preferences: changes.preferences,
+            caldav: changes.caldav,
+        };
+        write_file(
+            &dir.join(LOCAL_FILE),
+            serde_json::to_string_pretty(&local).map_err(|e| e.to_string())?,
+        )
+        .await?;
+
+        git(dir, &["add", "-A"]).await?;
+
+        // Only the local settings changed, which are not committed
+        if git(dir, &["diff", "--cached", "--quiet"]).await.is_ok() {
+            return Ok(false);
+        }
+
+        let count = |n: usize| if n == 1 { String::from("1 task") } else { format!("{} tasks", n) };
+        let message = match (changes.upserted.len(), changes.deleted.len()) {
+            (saved, 0) => format!("Save {}", count(saved)),
+            (0, deleted) => format!("Delete {}", count(deleted)),
+            (saved, deleted) => format!("Save {}, delete {}", count(saved), count(deleted)),
+        };
+        author.git(dir, &["commit", "-q", "-m", &message]).await?;
+
+        Ok(true)
+    }
+
+    /// Merges what was fetched from the remote into the current branch,
+    /// resolving conflicts per task.
+    async fn merge(dir: &Path, author: Author) -> Result<(), String> {
+        let branch = git(dir, &["symbolic-ref", "--short", "HEAD"]).await?;
+        let mut upstream = format!("origin/{}", branch.trim());
+        let head = rev(dir, "HEAD").await;
+
+        if rev(dir, &upstream).await.is_none() {
+            // Not on the remote yet, which the next push takes care of
+            if head.is_some() {
+                return Ok(());
+            }
+
+            // A new repository takes whichever branch the remote has
+            let branches = git(dir, &["for-each-ref", "--format=%(refname:short)", "refs/remotes/origin"]).await?;
+            let Some(name) = branches
+                .lines()
+                .filter_map(|name| name.strip_prefix("origin/"))
+                .find(|name| *name != "HEAD")
+            else {
+                return Ok(());
+            };
+            git(dir, &["symbolic-ref", "HEAD", &format!("refs/heads/{}", name)]).await?;
+            upstream = format!("origin/{}", name);
+        }
+
+        let Some(head) = head else {
+            // The remote has its own, and git will not overwrite files it
+            // does not track yet
+            for name in [".gitignore", ".gitattributes"] {
+                let _ = tokio::fs::remove_file(dir.join(name)).await;
+            }
+            git(dir, &["merge", "-q", "--ff-only", &upstream]).await?;
+            return Ok(());
+        };
+
+        let remote = rev(dir, &upstream).await;
+        if remote.as_deref() == Some(head.as_str())
+            || git(dir, &["merge-base", "--is-ancestor", &upstream, "HEAD"]).await.is_ok()
+        {
+            return Ok(());
+        }
+
+        // Histories are unrelated if something was saved here before the
+        // first pull
+        if author
+            .git(dir, &["merge", "-q", "--no-edit", "--allow-unrelated-histories", &upstream])
+            .await
+            .is_err()
+        {
+            let resolved = match Self::resolve_conflicts(dir).await {
+                Ok(()) => author.git(dir, &["commit", "-q", "--no-edit"]).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = resolved {
+                // Leave the repository as it was, to try again next time
+                let _ = git(dir, &["merge", "--abort"]).await;
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the files of an interrupted merge. A task changed on both
+    /// sides takes each field from the side that changed it; a field changed
+    /// on both takes the side whose history is the newer. A task changed on
+    /// one side and deleted for good on the other is kept.
+    async fn resolve_conflicts(dir: &Path) -> Result<(), String> {
+        let conflicted = git(dir, &["diff", "--name-only", "--diff-filter=U"]).await?;
+        let base = git(dir, &["merge-base", "HEAD", "MERGE_HEAD"])
+            .await
+            .ok()
+            .map(|base| base.trim().to_string());
+
+        for path in conflicted.lines() {
+            let ours = show(dir, "HEAD", path).await;
+            let theirs = show(dir, "MERGE_HEAD", path).await;
+
+            let id = path
+                .strip_prefix("tasks/")
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|id| Uuid::parse_str(id).ok());
+
+            let resolved = match (id, ours, theirs) {
+                (Some(id), Some(ours), Some(theirs)) => {
+                    Some(Self::merge_task(dir, id, base.as_deref(), path, &ours, &theirs).await?)
+                }
+                // Changed on one side and deleted on the other; history files
+                // end up here too, as their lines are merged otherwise
+                (_, kept, None) | (_, None, kept) => kept,
+                // Not a task, e.g. `.gitignore` edited by hand
+                (None, Some(ours), Some(_)) => Some(ours),
+            };
+
+            match resolved {
+                Some(contents) => {
+                    tokio::fs::write(dir.join(path), contents)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    git(dir, &["add", "--", path]).await?;
+                }
+                None => {
+                    git(dir, &["rm", "-q", "--", path]).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Merges the two sides' versions of a task file, against the version
+    /// both started from if there is one.
+    async fn merge_task(
+        dir: &Path,
+        id: Uuid,
+        base: Option<&str>,
+        path: &str,
+        ours: &str,
+        theirs: &str,
+    ) -> Result<String, String> {
+        let parse = |contents: &str| serde_json::from_str::<Task>(contents).map_err(|e| format!("{}: {}", path, e));
+        let ours = parse(ours)?;
+        let theirs = parse(theirs)?;
+
+        let base = match base {
+            Some(base) => show(dir, base, path).await.and_then(|contents| parse(&contents).ok()),
+            None => None,
+        };
+
+        let history = format!("history/{}.jsonl", id);
+        let last_changed = async |rev: &str| {
+            show(dir, rev, &history)
+                .await
+                .and_then(|contents| parse_history(&contents).last().map(|entry| entry.at))
+        };
+        let theirs_newer = last_changed("MERGE_HEAD").await > last_changed("HEAD").await;
+
+        // Each side's changes since the base, on top of the other's
+        let merged = match (base, theirs_newer) {
+            (Some(base), true) => rebase(&theirs, &base, &ours),
+            (Some(base), false) => rebase(&ours, &base, &theirs),
+            (None, true) => theirs,
+            (None, false) => ours,
+        };
+
+        Ok(task_json(&merged))
+    }
+
+    /// Fetches from `origin`, merges and pushes what is left to push. Does
+    /// nothing if the repository has no remote.
+    async fn pull(dir: &Path, repository: &Mutex<Repository>) -> Result<(), String> {
+        if git(dir, &["remote", "get-url", "origin"]).await.is_err() {
+            return Ok(());
+        }
+
+        // Fetching and pushing leave the branch alone, so saves can go on
+        git(dir, &["fetch", "-q", "origin"]).await?;
+
+        let head = {
+            let mut repository = repository.lock().await;
+            let author = repository.author(dir).await;
+            Self::merge(dir, author).await?;
+            repository.push_due = false;
+            rev(dir, "HEAD").await
+        };
+
+        let branch = git(dir, &["symbolic-ref", "--short", "HEAD"]).await?;
+        if head.is_some()
+            && head != rev(dir, &format!("origin/{}", branch.trim())).await
+            && let Err(e) = git(dir, &["push", "-q", "origin", "HEAD"]).await
+        {
+            repository.lock().await.push_due = true;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage for GitStorage {
+    fn load(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        let dir = self.dir.clone();
+        let repository = self.repository.clone();
+
+        Box::pin(async move {
+            let mut repository = repository.lock().await;
+            Self::init(&dir).await.map_err(LoadError::file)?;
+            repository.author(&dir).await;
+
+            let state = Self::read(&dir, false).await?;
+            repository.known_head = rev(&dir, "HEAD").await;
+            Ok(state)
+        })
+    }
+
+    fn save(&self, changes: SavedChanges) -> StorageFuture<Result<(), SaveError>> {
+        let dir = self.dir.clone();
+        let repository = self.repository.clone();
+
+        Box::pin(async move {
+            let mut repository = repository.lock().await;
+            Self::init(&dir).await.map_err(SaveError::write)?;
+            let author = repository.author(&dir).await;
+            let head = rev(&dir, "HEAD").await;
+
+            if Self::write(&dir, changes, author).await.map_err(SaveError::write)? {
+                // Unless something else was committed since, which
+                // `changed` still has to report
+                if repository.known_head == head {
+                    repository.known_head = rev(&dir, "HEAD").await;
+                }
+                repository.push_due = true;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Reads the files without touching the repository, so it works without
+    /// git too.
+    fn load_read_only(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        let dir = self.dir.clone();
+
+        Box::pin(async move { Self::read(&dir, true).await })
+    }
+
+    fn load_history(&self, task_id: Uuid) -> StorageFuture<Result<Vec<HistoryEntry>, LoadError>> {
+        let path = Self::history_path(&self.dir, task_id);
+
+        Box::pin(async move {
+            match tokio::fs::read_to_string(&path).await {
+                Ok(contents) => Ok(parse_history(&contents)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+                Err(e) => Err(LoadError::file(e)),
+            }
+        })
+    }
+
+    fn load_all_history(&self) -> StorageFuture<Result<Vec<HistoryEntry>, LoadError>> {
+        let dir = self.dir.join("history");
+
+        Box::pin(async move {
+            let mut history = Vec::new();
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(history),
+                Err(e) => return Err(LoadError::file(e)),
+            };
+
+            while let Some(entry) = entries.next_entry().await.map_err(LoadError::file)? {
+                let path = entry.path();
+                if path.extension().is_some_and(|extension| extension == "jsonl") {
+                    let contents = tokio::fs::read_to_string(&path)
+                        .await
+                        .map_err(LoadError::file)?;
+                    history.extend(parse_history(&contents));
+                }
+            }
+
+            // Stable, so each task's entries keep their order
+            history.sort_by_key(|entry| entry.at);
+            Ok(history)
+        })
+    }
+
+    fn reset(&self) -> StorageFuture<Result<Option<String>, SaveError>> {
+        let dir = self.dir.clone();
+        let repository = self.repository.clone();
+
+        Box::pin(async move {
+            let mut repository = repository.lock().await;
+            let backup = super::move_to_backup(&dir).await?;
+            *repository = Repository::default();
+            Ok(backup.map(|backup| backup.display().to_string()))
+        })
+    }
+
+    /// Pulls from the remote when due, then tells whether HEAD moved past
+    /// what the app last loaded or saved.
+    fn changed(&self) -> StorageFuture<Result<bool, LoadError>> {
+        let dir = self.dir.clone();
+        let repository = self.repository.clone();
+
+        Box::pin(async move {
+            let due = {
+                let mut repository = repository.lock().await;
+                let due = repository.push_due
+                    || repository
+                        .last_pull
+                        .is_none_or(|at| at.elapsed() >= PULL_INTERVAL);
+                if due {
+                    repository.last_pull = Some(Instant::now());
+                }
+                due
+            };
+
+            // Offline is no reason to stop; the next pull catches up
+            if due && let Err(e) = Self::pull(&dir, &repository).await {
+                eprintln!("Could not sync {}: {}", dir.display(), e);
+            }
+
+            let mut repository = repository.lock().await;
+            let head = rev(&dir, "HEAD").await;
+            if head == repository.known_head {
+                Ok(false)
+            } else {
+                repository.known_head = head;
+                Ok(true)
+            }
+        })
+    }
+
+    fn location(&self) -> String {
+        self.dir.display().to_string()
+    }
+}
+
+/// Runs git in `dir` and returns what it printed, or what it complained
+/// about if it failed.
+async fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        // Fail rather than wait for credentials no one is there to type
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("could not run git: {}", e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// The commit `name` points to, if it exists.
+async fn rev(dir: &Path, name: &str) -> Option<String> {
+    git(dir, &["rev-parse", "-q", "--verify", &format!("{}^{{commit}}", name)])
+        .await
+        .ok()
+        .map(|commit| commit.trim().to_string())
+}
+
+/// The file at `path` as of commit `rev`, if it is there.
+async fn show(dir: &Path, rev: &str, path: &str) -> Option<String> {
+    git(dir, &["show", &format!("{}:{}", rev, path)]).await.ok()
+}
+
+fn task_json(task: &Task) -> String {
+    let mut json = serde_json::to_string_pretty(task).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// One entry per line. Merging keeps the lines of both sides, so they are put
+/// back in order, oldest first. Lines that fail to parse are skipped.
+fn parse_history(contents: &str) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    entries.sort_by_key(|entry| entry.at);
+    entries.dedup();
+    entries
+}
+
+/// Writes next to `path` and renames, so a crash never leaves half a file.
+async fn write_file(path: &Path, contents: String) -> Result<(), String> {
+    let temp_path = path.with_extension("tmp");
+    tokio::fs::write(&temp_path, contents)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::fs::rename(&temp_path, path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::history::Change;
+    use crate::state::persistence::{edit_task, find, rename, test_dir};
+    use crate::task::TaskMessage;
+
+    /// Runs git outside the storage, as the user would set things up.
+    fn git_here(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    /// A bare repository for two clones to share, and the clones' directories.
+    fn remote_and_clones() -> (PathBuf, PathBuf, PathBuf) {
+        let root = test_dir();
+        git_here(&root, &["init", "-q", "--bare", "remote.git"]);
+        (root.clone(), root.join("a"), root.join("b"))
+    }
+
+    /// Points the repository in `dir` at the shared remote.
+    fn add_remote(dir: &Path) {
+        git_here(dir, &["remote", "add", "origin", "../remote.git"]);
+    }
+
+    async fn save(storage: &GitStorage, upserted: Vec<Task>, history: Vec<HistoryEntry>) {
+        storage
+            .save(SavedChanges {
+                upserted,
+                history,
+                ..SavedChanges::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn create(storage: &GitStorage, descriptions: &[&str]) {
+        let tasks: Vec<Task> = descriptions
+            .iter()
+            .enumerate()
+            .map(|(position, description)| {
+                let mut task = Task::new(description.to_string());
+                task.set_position(position as i64);
+                task
+            })
+            .collect();
+        let history = tasks
+            .iter()
+            .map(|task| {
+                HistoryEntry::new(
+                    task,
+                    Change::Created {
+                        description: task.description().to_string(),
+                    },
+                )
+            })
+            .collect();
+
+        save(storage, tasks, history).await;
+    }
+
+    /// Changes a stored task as the user would and saves it.
+    async fn edit(storage: &GitStorage, description: &str, messages: Vec<TaskMessage>) {
+        let mut task = find(&storage.load().await.unwrap().tasks, description).clone();
+        let history = edit_task(&mut task, messages);
+
+        save(storage, vec![task], history).await;
+    }
+
+    /// Opens the repository again, as on the next start, which pulls first
+    /// thing.
+    async fn reopen_and_pull(dir: &Path) -> GitStorage {
+        let storage = GitStorage::new(dir.to_path_buf());
+        storage.load().await.unwrap();
+        storage.changed().await.unwrap();
+        storage
+    }
+
+    /// Two clones of a remote that has `descriptions` as its tasks.
+    async fn shared(descriptions: &[&str]) -> (PathBuf, GitStorage, GitStorage) {
+        let (root, a_dir, b_dir) = remote_and_clones();
+
+        let a = GitStorage::new(a_dir.clone());
+        a.load().await.unwrap();
+        create(&a, descriptions).await;
+        add_remote(&a_dir);
+        // Pushes the save
+        assert!(!a.changed().await.unwrap());
+
+        let b = GitStorage::new(b_dir.clone());
+        b.load().await.unwrap();
+        add_remote(&b_dir);
+        assert!(b.changed().await.unwrap());
+
+        (root, a, b)
+    }
+
+    #[tokio::test]
+    async fn commits_fall_back_to_a_stand_in_without_a_name_or_an_email() {
+        let dir = test_dir();
+        git_here(&dir, &["init", "-q"]);
+        git_here(&dir, &["config", "user.name", "Ada"]);
+        git_here(&dir, &["config", "user.email", "ada@example.com"]);
+        assert_eq!(Author::resolve(&dir).await, Author::Configured);
+
+        // An empty name is as good as none, and git refuses it
+        git_here(&dir, &["config", "user.name", ""]);
+        assert_eq!(Author::resolve(&dir).await, Author::Fallback);
+
+        let storage = GitStorage::new(dir.clone());
+        storage.load().await.unwrap();
+        create(&storage, &["Buy milk"]).await;
+        assert_eq!(git(&dir, &["log", "-1", "--format=%an"]).await.unwrap().trim(), "Todos");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn saves_are_pushed_and_pulled() {
+        let (root, a, b) = shared(&["Buy milk", "Call mom"]).await;
+
+        let state = b.load().await.unwrap();
+        assert_eq!(
+            state.tasks.iter().map(Task::description).collect::<Vec<_>>(),
+            ["Buy milk", "Call mom"]
+        );
+
+        // Saved on B, pushed with the next check and pulled by A when it
+        // starts again
+        edit(&b, "Buy milk", vec![TaskMessage::Completed(true)]).await;
+        assert!(!b.changed().await.unwrap());
+        drop(a);
+
+        let a = reopen_and_pull(&root.join("a")).await;
+        assert!(find(&a.load().await.unwrap().tasks, "Buy milk").completed());
+        // The history came along
+        let milk = find(&a.load().await.unwrap().tasks, "Buy milk").clone();
+        assert_eq!(a.load_history(milk.id()).await.unwrap().len(), 2);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn saving_before_the_first_pull_keeps_both_sides() {
+        let (root, a_dir, b_dir) = remote_and_clones();
+
+        let a = GitStorage::new(a_dir.clone());
+        a.load().await.unwrap();
+        create(&a, &["From A"]).await;
+        add_remote(&a_dir);
+        a.changed().await.unwrap();
+
+        let b = GitStorage::new(b_dir.clone());
+        b.load().await.unwrap();
+        create(&b, &["From B"]).await;
+        add_remote(&b_dir);
+        assert!(b.changed().await.unwrap());
+
+        let state = b.load().await.unwrap();
+        find(&state.tasks, "From A");
+        find(&state.tasks, "From B");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn a_task_changed_on_both_clones_is_merged_field_by_field() {
+        let (root, a, b) = shared(&["Call mom", "Pay rent"]).await;
+
+        // Different fields: both changes are kept
+        edit(&b, "Call mom", vec![TaskMessage::Completed(true)]).await;
+        b.changed().await.unwrap();
+        edit(&a, "Call mom", rename("Call mum")).await;
+        assert!(a.changed().await.unwrap());
+
+        let mum = find(&a.load().await.unwrap().tasks, "Call mum").clone();
+        assert!(mum.completed());
+        // Both sides' history, in order
+        let history = a.load_history(mum.id()).await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert!(history.windows(2).all(|pair| pair[0].at <= pair[1].at));
+
+        // The same field: the side that changed it last wins
+        edit(&a, "Pay rent", rename("Pay the rent")).await;
+        let b = reopen_and_pull(&root.join("b")).await;
+        edit(&b, "Pay rent", rename("Pay rent today")).await;
+        b.changed().await.unwrap();
+        assert!(a.changed().await.unwrap());
+
+        let state = a.load().await.unwrap();
+        find(&state.tasks, "Pay rent today");
+        assert!(find(&state.tasks, "Call mum").completed());
+        // Nothing left half merged
+        assert!(!root.join("a/.git/MERGE_HEAD").exists());
+
+        // And B gets the same
+        let b = reopen_and_pull(&root.join("b")).await;
+        let state = b.load().await.unwrap();
+        find(&state.tasks, "Pay rent today");
+        assert!(find(&state.tasks, "Call mum").completed());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn a_reorder_on_one_clone_and_a_rename_on_the_other_are_both_kept() {
+        let (root, a, b) = shared(&["Buy milk", "Call mom", "Pay rent"]).await;
+
+        // Moved to the end, which records no history, so the rename is the
+        // newer side
+        let mut milk = find(&b.load().await.unwrap().tasks, "Buy milk").clone();
+        milk.set_position(3);
+        save(&b, vec![milk], Vec::new()).await;
+        b.changed().await.unwrap();
+        edit(&a, "Buy milk", rename("Buy oat milk")).await;
+        assert!(a.changed().await.unwrap());
+
+        let state = a.load().await.unwrap();
+        assert_eq!(
+            state.tasks.iter().map(Task::description).collect::<Vec<_>>(),
+            ["Call mom", "Pay rent", "Buy oat milk"]
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn a_task_changed_on_one_clone_and_deleted_on_the_other_is_kept() {
+        let (root, a, b) = shared(&["Buy milk"]).await;
+        let milk = find(&a.load().await.unwrap().tasks, "Buy milk").clone();
+
+        a.save(SavedChanges {
+            deleted: vec![milk.id()],
+            ..SavedChanges::default()
+        })
+        .await
+        .unwrap();
+        a.changed().await.unwrap();
+        edit(&b, "Buy milk", rename("Buy oat milk")).await;
+        assert!(b.changed().await.unwrap());
+
+        find(&b.load().await.unwrap().tasks, "Buy oat milk");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,36 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::Path;
+
+/// An exclusive lock on `todos.lock` in the data directory, held for as long
+/// as the app runs. The OS drops it if the process dies, so a crash never
+/// leaves a stale lock behind.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Takes the lock, or returns `None` if another instance holds it.
+    pub fn acquire(data_dir: &Path) -> std::io::Result<Option<Self>> {
+        std::fs::create_dir_all(data_dir)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(data_dir.join("todos.lock"))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+
+        // Only informational, for whoever finds the file
+        file.set_len(0)?;
+        writeln!(file, "{}", std::process::id())?;
+
+        Ok(Some(Self { _file: file }))
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+use crate::task::Task;
+use super::{SavedChanges, SavedState, LoadError, SaveError, Storage, StorageFuture};
+
+/// Stores the whole state as a single pretty-printed JSON file.
+#[derive(Debug, Clone)]
+pub struct JsonFileStorage {
+    path: PathBuf,
+}
+
+impl JsonFileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    async fn read(path: &Path) -> Result<SavedState, LoadError> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(contents) => serde_json::from_str(&contents).map_err(LoadError::format),
+            // First run, nothing saved yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SavedState::default()),
+            Err(e) => Err(LoadError::file(e)),
+        }
+    }
+
+    /// Like `read`, but skips tasks and fields that fail to deserialize.
+    async fn read_lenient(path: &Path) -> Result<SavedState, LoadError> {
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(LoadError::file)?;
+        let value: serde_json::Value =
+            serde_json::from_str(&contents).map_err(LoadError::format)?;
+
+        let field = |name| value.get(name).cloned().unwrap_or_default();
+
+        let tasks = match field("tasks") {
+            serde_json::Value::Array(tasks) => tasks
+                .into_iter()
+                .filter_map(|task| serde_json::from_value::<Task>(task).ok())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(SavedState {
+            input_value: serde_json::from_value(field("input_value")).unwrap_or_default(),
+            filter: serde_json::from_value(field("filter")).unwrap_or_default(),
+            tasks,
+            preferences: serde_json::from_value(field("preferences")).unwrap_or_default(),
+            history: serde_json::from_value(field("history")).unwrap_or_default(),
+            caldav: serde_json::from_value(field("caldav")).unwrap_or_default(),
+            ..SavedState::default()
+        })
+    }
+}
+
+impl Storage for JsonFileStorage {
+    fn load(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        let path = self.path.clone();
+
+        Box::pin(async move { Self::read(&path).await })
+    }
+
+    fn save(&self, changes: SavedChanges) -> StorageFuture<Result<(), SaveError>> {
+        let path = self.path.clone();
+
+        Box::pin(async move {
+            let mut state = Self::read(&path).await.map_err(SaveError::write)?;
+            state.apply(changes);
+
+            let json = serde_json::to_string_pretty(&state).map_err(SaveError::write)?;
+
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .map_err(SaveError::write)?;
+            }
+
+            // Write next to the target and rename, so a crash never leaves half a file
+            let temp_path = path.with_extension("json.tmp");
+            tokio::fs::write(&temp_path, json)
+                .await
+                .map_err(SaveError::write)?;
+            tokio::fs::rename(&temp_path, &path)
+                .await
+                .map_err(SaveError::write)
+        })
+    }
+
+    fn load_read_only(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        let path = self.path.clone();
+
+        Box::pin(async move { Self::read_lenient(&path).await })
+    }
+
+    fn reset(&self) -> StorageFuture<Result<Option<String>, SaveError>> {
+        let path = self.path.clone();
+
+        Box::pin(async move {
+            let backup = super::move_to_backup(&path).await?;
+            Ok(backup.map(|backup| backup.display().to_string()))
+        })
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
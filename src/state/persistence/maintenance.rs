@@ -0,0 +1,332 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::Utc;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection};
+
+use super::backups::sibling;
+use super::sqlite_persistence::SqlitePersistence;
+use super::{MaintenanceReport, Salvage, SaveError};
+
+/// The tables worth salvaging, in the order they are copied. The schema
+/// version is not among them; the fresh database has its own.
+const TABLES: [&str; 4] = ["app_state", "encryption", "tasks", "task_history"];
+
+async fn connect(db_path: &Path) -> Result<SqliteConnection, sqlx::Error> {
+    SqliteConnectOptions::from_str(&format!("sqlite://{}", db_path.display()))?
+        .connect()
+        .await
+}
+
+/// Checks, compacts and if need be rebuilds the database at `db_path`, then
+/// writes a report into `report_dir`. Nothing else may have it open.
+pub async fn run(db_path: &Path, report_dir: &Path) -> Result<MaintenanceReport, SaveError> {
+    let mut report = MaintenanceReport::default();
+
+    if !tokio::fs::try_exists(db_path).await.map_err(SaveError::write)? {
+        return Ok(report);
+    }
+
+    report.problems = integrity_check(db_path).await?;
+
+    if report.problems.is_empty() {
+        optimize(db_path).await?;
+    } else {
+        report.salvage = Some(salvage(db_path).await?);
+    }
+
+    let now = Utc::now();
+    let path = report_dir.join(format!("maintenance-{}.txt", now.format("%Y%m%dT%H%M%SZ")));
+    let contents = format!(
+        "Maintenance of {} at {}\n\n{}",
+        db_path.display(),
+        now.format("%Y-%m-%d %H:%M:%S UTC"),
+        report
+    );
+
+    // The work is done either way, the report is a courtesy
+    if tokio::fs::create_dir_all(report_dir).await.is_ok()
+        && tokio::fs::write(&path, contents).await.is_ok()
+    {
+        report.report_path = Some(path);
+    }
+
+    Ok(report)
+}
+
+/// What `PRAGMA integrity_check` reports, empty if the database is fine.
+/// Failing to open or query it is an error rather than a problem: it may be
+/// locked or unreadable for now, and salvaging would throw away a good file.
+async fn integrity_check(db_path: &Path) -> Result<Vec<String>, SaveError> {
+    let mut connection = connect(db_path).await.map_err(SaveError::write)?;
+
+    let rows = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+        .fetch_all(&mut connection)
+        .await;
+    let _ = connection.close().await;
+
+    match rows.map_err(SaveError::write)? {
+        rows if rows == ["ok"] => Ok(Vec::new()),
+        rows => Ok(rows),
+    }
+}
+
+/// Rebuilds the indexes and compacts the file.
+async fn optimize(db_path: &Path) -> Result<(), SaveError> {
+    let mut connection = connect(db_path).await.map_err(SaveError::write)?;
+
+    for statement in ["REINDEX", "VACUUM"] {
+        sqlx::query(statement)
+            .execute(&mut connection)
+            .await
+            .map_err(SaveError::write)?;
+    }
+
+    connection.close().await.map_err(SaveError::write)
+}
+
+/// Copies every row that can still be read into a fresh database, then puts
+/// it in place of the damaged one.
+async fn salvage(db_path: &Path) -> Result<Salvage, SaveError> {
+    let fresh_path = sibling(db_path, ".salvaged");
+    remove_if_exists(&fresh_path).await?;
+
+    // Created and migrated like any new database
+    SqlitePersistence::new(&fresh_path)
+        .await
+        .map_err(SaveError::write)?
+        .close()
+        .await;
+
+    let mut salvage = Salvage::default();
+    let mut connection = connect(&fresh_path).await.map_err(SaveError::write)?;
+
+    let attached = sqlx::query("ATTACH DATABASE ? AS damaged")
+        .bind(db_path.display().to_string())
+        .execute(&mut connection)
+        .await
+        .is_ok();
+
+    if attached {
+        for table in TABLES {
+            let (copied, unreadable) = copy_rows(&mut connection, table).await;
+            salvage.unreadable += unreadable;
+
+            match table {
+                "app_state" => salvage.settings = copied > 0,
+                "encryption" => salvage.encryption = copied > 0,
+                "tasks" => salvage.tasks = copied,
+                _ => salvage.history = copied,
+            }
+        }
+
+        let _ = sqlx::query("DETACH DATABASE damaged")
+            .execute(&mut connection)
+            .await;
+    }
+
+    connection.close().await.map_err(SaveError::write)?;
+
+    // Kept aside with its log, in case more can be got out of it by hand
+    salvage.damaged_copy = super::move_to_backup(db_path).await?;
+    for suffix in ["-wal", "-shm"] {
+        let from = sibling(db_path, suffix);
+        match &salvage.damaged_copy {
+            Some(copy) if tokio::fs::try_exists(&from).await.map_err(SaveError::write)? => {
+                tokio::fs::rename(&from, sibling(copy, suffix))
+                    .await
+                    .map_err(SaveError::write)?;
+            }
+            _ => remove_if_exists(&from).await?,
+        }
+    }
+
+    tokio::fs::rename(&fresh_path, db_path)
+        .await
+        .map_err(SaveError::write)?;
+
+    Ok(salvage)
+}
+
+/// Copies `table` from the damaged database one row at a time, so a bad page
+/// only costs the rows on it. Returns how many rows were copied and how many
+/// could not be read.
+async fn copy_rows(connection: &mut SqliteConnection, table: &str) -> (usize, usize) {
+    let columns = match shared_columns(connection, table).await {
+        Ok(columns) if !columns.is_empty() => columns.join(", "),
+        // Missing from an older database, or its schema is unreadable
+        _ => return (0, 0),
+    };
+
+    let last_row: Option<i64> = match sqlx::query_scalar(&format!("SELECT max(rowid) FROM damaged.{}", table))
+        .fetch_one(&mut *connection)
+        .await
+    {
+        Ok(last_row) => last_row,
+        Err(_) => return (0, 1),
+    };
+
+    let copy = format!(
+        "INSERT OR REPLACE INTO main.{table} ({columns}) SELECT {columns} FROM damaged.{table} WHERE rowid = ?"
+    );
+
+    let (mut copied, mut unreadable) = (0, 0);
+    for rowid in 1..=last_row.unwrap_or(0) {
+        match sqlx::query(&copy).bind(rowid).execute(&mut *connection).await {
+            Ok(result) => copied += result.rows_affected() as usize,
+            Err(_) => unreadable += 1,
+        }
+    }
+
+    (copied, unreadable)
+}
+
+/// The columns of `table` that both databases have.
+async fn shared_columns(connection: &mut SqliteConnection, table: &str) -> Result<Vec<String>, sqlx::Error> {
+    let columns = |schema: &'static str| {
+        sqlx::query_scalar::<_, String>("SELECT name FROM pragma_table_info(?, ?)")
+            .bind(table.to_string())
+            .bind(schema)
+    };
+
+    let fresh = columns("main").fetch_all(&mut *connection).await?;
+    let damaged = columns("damaged").fetch_all(&mut *connection).await?;
+
+    Ok(fresh
+        .into_iter()
+        .filter(|column| damaged.contains(column))
+        .collect())
+}
+
+async fn remove_if_exists(path: &Path) -> Result<(), SaveError> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SaveError::write(e)),
+        _ => Ok(()),
+    }
+}
+
+impl fmt::Display for MaintenanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.problems.is_empty() {
+            writeln!(f, "Integrity check: ok")?;
+        } else {
+            writeln!(f, "Integrity check found {} problem(s):", self.problems.len())?;
+            for problem in &self.problems {
+                writeln!(f, "  {}", problem)?;
+            }
+        }
+
+        let Some(salvage) = &self.salvage else {
+            return writeln!(f, "Rebuilt the indexes and compacted the database.");
+        };
+
+        let recovered = |found| if found { "recovered" } else { "not recovered" };
+
+        writeln!(f, "\nSalvaged into a fresh database:")?;
+        writeln!(f, "  tasks: {}", salvage.tasks)?;
+        writeln!(f, "  history entries: {}", salvage.history)?;
+        writeln!(f, "  settings: {}", recovered(salvage.settings))?;
+        if salvage.encryption {
+            writeln!(f, "  encryption key info: recovered")?;
+        }
+        writeln!(f, "  unreadable rows: {}", salvage.unreadable)?;
+
+        match &salvage.damaged_copy {
+            Some(path) => writeln!(f, "\nThe damaged database was kept as {}", path.display()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::persistence::{test_dir, SavedChanges};
+    use crate::task::Task;
+
+    async fn create_database(db_path: &Path, descriptions: &[&str]) {
+        let persistence = SqlitePersistence::new(db_path).await.unwrap();
+        persistence
+            .save(
+                SavedChanges {
+                    upserted: descriptions
+                        .iter()
+                        .map(|description| Task::new(description.to_string()))
+                        .collect(),
+                    ..SavedChanges::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        persistence.close().await;
+    }
+
+    #[tokio::test]
+    async fn a_healthy_database_is_only_compacted() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        create_database(&db_path, &["Buy milk", "Call mom"]).await;
+
+        let report = run(&db_path, &dir).await.unwrap();
+
+        assert!(report.problems.is_empty());
+        assert!(report.salvage.is_none());
+        assert!(report.report_path.is_some());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn an_unreadable_file_is_an_error_and_left_alone() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        let contents = b"not a database, or not one sqlite can read today".repeat(100);
+        std::fs::write(&db_path, &contents).unwrap();
+
+        assert!(run(&db_path, &dir).await.is_err());
+
+        assert_eq!(std::fs::read(&db_path).unwrap(), contents);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn a_corrupt_database_is_salvaged() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        create_database(&db_path, &["Buy milk", "Call mom"]).await;
+
+        // An index that no longer matches its table, which integrity_check
+        // reports row by row
+        let mut connection = connect(&db_path).await.unwrap();
+        sqlx::raw_sql(
+            "CREATE INDEX tasks_by_description ON tasks (description);
+             PRAGMA writable_schema = ON;
+             UPDATE sqlite_master SET sql = 'CREATE INDEX tasks_by_description ON tasks (completed)'
+                 WHERE name = 'tasks_by_description';
+             PRAGMA writable_schema = OFF;",
+        )
+        .execute(&mut connection)
+        .await
+        .unwrap();
+        connection.close().await.unwrap();
+
+        let report = run(&db_path, &dir).await.unwrap();
+
+        assert!(!report.problems.is_empty());
+        let salvage = report.salvage.unwrap();
+        assert_eq!(salvage.tasks, 2);
+        assert!(salvage.settings);
+        assert!(salvage.damaged_copy.unwrap().exists());
+
+        let persistence = SqlitePersistence::new(&db_path).await.unwrap();
+        assert_eq!(persistence.task_count().await.unwrap(), 2);
+        persistence.close().await;
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
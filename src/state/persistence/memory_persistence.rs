@@ -0,0 +1,46 @@
+use std::sync::{Arc, Mutex};
+use super::{SavedChanges, SavedState, LoadError, SaveError, Storage, StorageFuture};
+
+/// Keeps the state in memory only. Nothing survives a restart, which makes it
+/// handy for trying the app out or exercising `Todos::update` in isolation.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    state: Arc<Mutex<SavedState>>,
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        let result = self
+            .state
+            .lock()
+            .map(|state| state.clone())
+            .map_err(LoadError::file);
+
+        Box::pin(async move { result })
+    }
+
+    fn save(&self, changes: SavedChanges) -> StorageFuture<Result<(), SaveError>> {
+        let result = self
+            .state
+            .lock()
+            .map(|mut state| state.apply(changes))
+            .map_err(SaveError::write);
+
+        Box::pin(async move { result })
+    }
+
+    fn reset(&self) -> StorageFuture<Result<Option<String>, SaveError>> {
+        let result = self
+            .state
+            .lock()
+            .map(|mut state| *state = SavedState::default())
+            .map(|_| None)
+            .map_err(SaveError::write);
+
+        Box::pin(async move { result })
+    }
+
+    fn location(&self) -> String {
+        String::from(":memory:")
+    }
+}
@@ -0,0 +1,284 @@
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection, Row, SqlitePool};
+use std::path::Path;
+use super::LoadError;
+
+/// Forward migrations, applied in order. A database's schema version is the
+/// number of migrations that have been applied to it, so entries must never be
+/// edited or reordered once released — add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: baseline schema
+    r#"
+    CREATE TABLE IF NOT EXISTS app_state (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        input_value TEXT NOT NULL DEFAULT '',
+        filter INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE IF NOT EXISTS tasks (
+        id TEXT PRIMARY KEY,
+        description TEXT NOT NULL,
+        completed BOOLEAN NOT NULL DEFAULT FALSE,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    INSERT OR IGNORE INTO app_state (id, input_value, filter) VALUES (1, '', 0);
+    "#,
+    // 2: manual ordering, seeded from the previous creation order
+    r#"
+    ALTER TABLE tasks ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+    UPDATE tasks SET position = (
+        SELECT COUNT(*) FROM tasks AS earlier
+        WHERE earlier.created_at < tasks.created_at
+           OR (earlier.created_at = tasks.created_at AND earlier.rowid < tasks.rowid)
+    );
+    "#,
+    // 3: passphrase encryption. Without a row, the data is stored in plain text
+    r#"
+    CREATE TABLE IF NOT EXISTS encryption (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        salt BLOB NOT NULL,
+        check_value BLOB NOT NULL
+    );
+    "#,
+    // 4: preferences restored before the first frame
+    r#"
+    ALTER TABLE app_state ADD COLUMN language TEXT;
+    ALTER TABLE app_state ADD COLUMN window_width REAL;
+    ALTER TABLE app_state ADD COLUMN window_height REAL;
+    ALTER TABLE app_state ADD COLUMN window_x REAL;
+    ALTER TABLE app_state ADD COLUMN window_y REAL;
+    ALTER TABLE app_state ADD COLUMN fullscreen BOOLEAN NOT NULL DEFAULT FALSE;
+    "#,
+    // 5: trash. NULL retention keeps trashed tasks forever
+    r#"
+    ALTER TABLE tasks ADD COLUMN deleted_at INTEGER;
+    ALTER TABLE app_state ADD COLUMN trash_retention_days INTEGER DEFAULT 30;
+    "#,
+    // 6: per-task change history. Values are encrypted like descriptions
+    r#"
+    CREATE TABLE IF NOT EXISTS task_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        task_id TEXT NOT NULL,
+        changed_at INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        old_value TEXT,
+        new_value TEXT
+    );
+
+    CREATE INDEX IF NOT EXISTS task_history_task_id ON task_history (task_id);
+    "#,
+    // 7: automatic backups
+    r#"
+    ALTER TABLE app_state ADD COLUMN backup_interval_hours INTEGER NOT NULL DEFAULT 6;
+    ALTER TABLE app_state ADD COLUMN backup_generations INTEGER NOT NULL DEFAULT 10;
+    "#,
+    // 8: completion time, for interchange formats that keep it. Tasks
+    // completed before it have none
+    r#"
+    ALTER TABLE tasks ADD COLUMN completed_at INTEGER;
+    "#,
+    // 9: data kept for other apps, as JSON. Encrypted like descriptions
+    r#"
+    ALTER TABLE tasks ADD COLUMN extra TEXT;
+    "#,
+    // 10: the CalDAV collection synced with, as JSON. Encrypted like
+    // descriptions, as it holds a password
+    r#"
+    ALTER TABLE app_state ADD COLUMN caldav TEXT;
+    "#,
+];
+
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Refuses a database written by a newer version of the app. Only reads it,
+/// so a refused database is left exactly as it was, journal mode included.
+pub async fn check_version(db_path: &Path) -> Result<(), LoadError> {
+    if !tokio::fs::try_exists(db_path).await.map_err(LoadError::file)? {
+        return Ok(());
+    }
+
+    let mut connection = SqliteConnectOptions::new()
+        .filename(db_path)
+        .read_only(true)
+        .connect()
+        .await
+        .map_err(LoadError::file)?;
+
+    let versioned: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+    )
+    .fetch_one(&mut connection)
+    .await
+    .map_err(LoadError::file)?;
+
+    let found: i64 = if versioned {
+        sqlx::query_scalar("SELECT version FROM schema_version WHERE id = 1")
+            .fetch_optional(&mut connection)
+            .await
+            .map_err(LoadError::file)?
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    let _ = connection.close().await;
+
+    if found > SCHEMA_VERSION {
+        return Err(LoadError::UnsupportedVersion {
+            found,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    Ok(())
+}
+
+/// Brings the database up to `SCHEMA_VERSION` in a single transaction.
+///
+/// Databases created before versioning have no `schema_version` row and are
+/// treated as version 0; the baseline migration is idempotent for them.
+pub async fn migrate(pool: &SqlitePool) -> Result<(), LoadError> {
+    let mut tx = pool.begin().await.map_err(LoadError::file)?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            version INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(LoadError::file)?;
+
+    let current: i64 = sqlx::query("SELECT version FROM schema_version WHERE id = 1")
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(LoadError::file)?
+        .map(|row| row.get("version"))
+        .unwrap_or(0);
+
+    if current > SCHEMA_VERSION {
+        return Err(LoadError::UnsupportedVersion {
+            found: current,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    if current == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    for &migration in &MIGRATIONS[current as usize..] {
+        sqlx::query(migration)
+            .execute(&mut *tx)
+            .await
+            .map_err(LoadError::format)?;
+    }
+
+    sqlx::query(
+        "INSERT INTO schema_version (id, version) VALUES (1, ?)
+         ON CONFLICT(id) DO UPDATE SET version = excluded.version",
+    )
+    .bind(SCHEMA_VERSION)
+    .execute(&mut *tx)
+    .await
+    .map_err(LoadError::file)?;
+
+    tx.commit().await.map_err(LoadError::file)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Filter;
+    use crate::state::persistence::sqlite_persistence::SqlitePersistence;
+    use crate::state::persistence::{test_dir, Preferences};
+    use chrono::{TimeZone, Utc};
+
+    /// A `todos.db` as the version before migrations wrote it.
+    const BASELINE: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/baseline.db"));
+
+    async fn open(db_path: &std::path::Path) -> SqlitePool {
+        SqlitePool::connect_with(SqliteConnectOptions::new().filename(db_path))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn baseline_data_survives_every_migration() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        std::fs::write(&db_path, BASELINE).unwrap();
+
+        let pool = open(&db_path).await;
+        migrate(&pool).await.unwrap();
+        let version: i64 = sqlx::query_scalar("SELECT version FROM schema_version WHERE id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        pool.close().await;
+
+        let persistence = SqlitePersistence::new(&db_path).await.unwrap();
+        let state = persistence.load(None).await.unwrap();
+
+        assert_eq!(state.input_value, "Water the");
+        assert_eq!(state.filter, Filter::Active);
+        assert_eq!(state.preferences, Preferences::default());
+        assert!(state.caldav.is_none());
+
+        // Positions follow the creation order, ties going by insertion
+        let tasks: Vec<_> = state
+            .tasks
+            .iter()
+            .map(|task| (task.description(), task.completed(), task.position()))
+            .collect();
+        assert_eq!(
+            tasks,
+            [("Buy milk", false, 0), ("Call mom", true, 1), ("Pay rent ☂", false, 2)]
+        );
+        assert_eq!(
+            state.tasks[2].created_at(),
+            Some(Utc.with_ymd_and_hms(2025, 3, 2, 18, 40, 12).unwrap())
+        );
+        assert!(state.tasks.iter().all(|task| {
+            !task.is_deleted() && task.completed_at().is_none() && task.extra().is_empty()
+        }));
+        assert!(persistence.load_history(Some(state.tasks[0].id()), None).await.unwrap().is_empty());
+
+        persistence.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn newer_schema_is_refused_untouched() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        std::fs::write(&db_path, BASELINE).unwrap();
+
+        let pool = open(&db_path).await;
+        migrate(&pool).await.unwrap();
+        sqlx::query("UPDATE schema_version SET version = ? WHERE id = 1")
+            .bind(SCHEMA_VERSION + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+        let before = std::fs::read(&db_path).unwrap();
+
+        let result = SqlitePersistence::new(&db_path).await;
+        assert!(matches!(
+            result,
+            Err(LoadError::UnsupportedVersion { found, supported })
+                if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION
+        ));
+        assert_eq!(std::fs::read(&db_path).unwrap(), before);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,85 +1,515 @@
+use super::caldav::Collection;
+use super::history::HistoryEntry;
 use super::Filter;
+use crate::i18n::Language;
 use crate::task::Task;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use uuid::Uuid;
 
+pub mod memory_persistence;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod backups;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod encryption;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod git_persistence;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod instance_lock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod json_persistence;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod maintenance;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod migrations;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod sqlite_persistence;
+// Only the browser stores data this way; natively it is built to be tested
+#[cfg(any(target_arch = "wasm32", test))]
+pub mod web_persistence;
+
+/// A fresh directory under the system temp directory, for tests that need
+/// files of their own.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub fn test_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todos-test-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The messages that rename a task to `to`, as editing it in the list sends
+/// them.
+#[cfg(test)]
+pub fn rename(to: &str) -> Vec<crate::task::TaskMessage> {
+    use crate::task::TaskMessage;
+
+    vec![
+        TaskMessage::Edit,
+        TaskMessage::DescriptionEdited(to.to_string()),
+        TaskMessage::FinishEdition,
+    ]
+}
+
+/// Changes `task` as the user would, returning the history to record.
+#[cfg(test)]
+pub fn edit_task(task: &mut Task, messages: Vec<crate::task::TaskMessage>) -> Vec<HistoryEntry> {
+    let mut history = Vec::new();
+    for message in messages {
+        for change in task.update(message) {
+            history.push(HistoryEntry::new(task, change));
+        }
+    }
+    history
+}
+
+/// The task with `description`, for tests to look tasks up by what they see.
+#[cfg(test)]
+pub fn find<'a>(tasks: &'a [Task], description: &str) -> &'a Task {
+    tasks
+        .iter()
+        .find(|task| task.description() == description)
+        .unwrap_or_else(|| panic!("no task {}", description))
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SavedState {
     pub input_value: String,
     pub filter: Filter,
     pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub preferences: Preferences,
+    /// Every recorded change, oldest first. Only kept here by backends that
+    /// store the whole state; others load it per task with `load_history`,
+    /// or all of it with `load_all_history`.
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+    /// The CalDAV collection synced with, if any. Kept with the data rather
+    /// than the preferences, as it holds a password.
+    #[serde(default)]
+    pub caldav: Option<Collection>,
+    /// Number of tasks imported from the legacy `todos.json` during this load.
+    #[serde(skip)]
+    pub legacy_imported: Option<usize>,
+    /// Number of tasks a lenient load could not read and left out.
+    #[serde(skip)]
+    pub skipped: usize,
+}
+
+/// Tasks that changed since the last save, written as targeted upserts and
+/// deletes instead of rewriting the whole table.
+#[derive(Debug, Clone, Default)]
+pub struct SavedChanges {
+    pub input_value: String,
+    pub filter: Filter,
+    pub upserted: Vec<Task>,
+    pub deleted: Vec<Uuid>,
+    /// Appended to the history. The history of deleted tasks is removed.
+    pub history: Vec<HistoryEntry>,
+    pub preferences: Preferences,
+    pub caldav: Option<Collection>,
+}
+
+/// Settings restored before the first frame. Never encrypted, so they can be
+/// read before the data is unlocked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// `None` until saved once; the desktop locale is used until then.
+    pub language: Option<Language>,
+    /// Window size and position while not fullscreen, in logical pixels.
+    pub window_size: Option<(f32, f32)>,
+    pub window_position: Option<(f32, f32)>,
+    pub fullscreen: bool,
+    /// Days a task stays in the trash before it is purged at startup. `None`
+    /// keeps it until it is deleted by hand.
+    pub trash_retention_days: Option<u32>,
+    /// Hours between automatic backups, on top of the one at startup.
+    pub backup_interval_hours: u32,
+    /// Number of backups kept before the oldest are deleted.
+    pub backup_generations: u32,
 }
 
+impl Preferences {
+    pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+    pub const DEFAULT_BACKUP_INTERVAL_HOURS: u32 = 6;
+    pub const DEFAULT_BACKUP_GENERATIONS: u32 = 10;
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            language: None,
+            window_size: None,
+            window_position: None,
+            fullscreen: false,
+            trash_retention_days: Some(Self::DEFAULT_TRASH_RETENTION_DAYS),
+            backup_interval_hours: Self::DEFAULT_BACKUP_INTERVAL_HOURS,
+            backup_generations: Self::DEFAULT_BACKUP_GENERATIONS,
+        }
+    }
+}
+
+/// A copy of the stored data taken by `Storage::back_up`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+    /// Tasks outside the trash, if the snapshot could be read.
+    pub task_count: Option<usize>,
+}
+
+/// What `Storage::maintain` found and did.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceReport {
+    /// What the integrity check reported. Empty if it found nothing wrong.
+    pub problems: Vec<String>,
+    /// Set if the data had to be rebuilt from the rows that could be read.
+    pub salvage: Option<Salvage>,
+    /// Where the report was written, if it could be.
+    pub report_path: Option<PathBuf>,
+}
+
+/// Rows recovered from damaged data into a fresh copy.
+#[derive(Debug, Clone, Default)]
+pub struct Salvage {
+    pub tasks: usize,
+    pub history: usize,
+    /// The settings row was recovered.
+    pub settings: bool,
+    /// The key info was recovered, so encrypted rows can still be opened.
+    pub encryption: bool,
+    /// Rows that could not be read, across all tables.
+    pub unreadable: usize,
+    /// Where the damaged original was kept.
+    pub damaged_copy: Option<PathBuf>,
+}
+
+/// Why loading failed. `File` and `Format` carry the underlying cause.
 #[derive(Debug, Clone)]
 pub enum LoadError {
-    File,
-    Format,
+    File(String),
+    Format(String),
+    /// The database was written by a newer version of the app.
+    UnsupportedVersion { found: i64, supported: i64 },
+    /// The data is encrypted and `Storage::unlock` has not been called yet.
+    Locked,
+    /// The passphrase given to `Storage::unlock` does not match the data.
+    WrongPassphrase,
 }
 
 #[derive(Debug, Clone)]
 pub enum SaveError {
-    Write,
+    Write(String),
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-impl SavedState {
-    pub fn path() -> std::path::PathBuf {
-        let mut path =
-            if let Some(project_dirs) = directories::ProjectDirs::from("rs", "Iced", "Todos") {
-                project_dirs.data_dir().into()
-            } else {
-                std::env::current_dir().unwrap_or_default()
-            };
+impl LoadError {
+    pub fn file(cause: impl std::fmt::Display) -> Self {
+        LoadError::File(cause.to_string())
+    }
 
-        path.push("todos.json");
-        path
+    pub fn format(cause: impl std::fmt::Display) -> Self {
+        LoadError::Format(cause.to_string())
     }
+}
 
-    pub async fn load() -> Result<SavedState, LoadError> {
-        let persistence = sqlite_persistence::SqlitePersistence::new()
-            .await
-            .map_err(|_| LoadError::File)?;
-        persistence.load().await
+impl SaveError {
+    pub fn write(cause: impl std::fmt::Display) -> Self {
+        SaveError::Write(cause.to_string())
     }
+}
 
-    pub async fn save(self) -> Result<(), SaveError> {
-        let persistence = sqlite_persistence::SqlitePersistence::new()
-            .await
-            .map_err(|_| SaveError::Write)?;
-        persistence.save(self).await
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::File(cause) => write!(f, "Could not read the data: {}", cause),
+            LoadError::Format(cause) => write!(f, "The data is malformed: {}", cause),
+            LoadError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Schema version {} is newer than the supported version {}",
+                found, supported
+            ),
+            LoadError::Locked => write!(f, "The data is encrypted and needs a passphrase"),
+            LoadError::WrongPassphrase => write!(f, "The passphrase is wrong"),
+        }
     }
 }
 
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Write(cause) => write!(f, "Could not write the data: {}", cause),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type StorageFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
 #[cfg(target_arch = "wasm32")]
-impl SavedState {
-    fn storage() -> Option<web_sys::Storage> {
-        let window = web_sys::window()?;
-        window.local_storage().ok()?
+pub type StorageFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// A place the app can load its state from and save changes to.
+///
+/// Futures are `'static` so they can be handed straight to `Command::perform`;
+/// implementations clone whatever they need out of `self`.
+pub trait Storage: std::fmt::Debug + Send + Sync {
+    fn load(&self) -> StorageFuture<Result<SavedState, LoadError>>;
+
+    fn save(&self, changes: SavedChanges) -> StorageFuture<Result<(), SaveError>>;
+
+    /// Loads whatever can still be read without creating, migrating or
+    /// otherwise writing to the stored data. Used after a failed `load`.
+    fn load_read_only(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        self.load()
+    }
+
+    /// Reads just the preferences, without writing anything, before the
+    /// window opens. Falls back to the defaults if they cannot be read.
+    fn load_preferences(&self) -> StorageFuture<Preferences> {
+        let load = self.load_read_only();
+
+        Box::pin(async move { load.await.map(|state| state.preferences).unwrap_or_default() })
+    }
+
+    /// The recorded changes to a task, oldest first.
+    fn load_history(&self, task_id: Uuid) -> StorageFuture<Result<Vec<HistoryEntry>, LoadError>> {
+        let load = self.load_read_only();
+
+        Box::pin(async move {
+            Ok(load
+                .await?
+                .history
+                .into_iter()
+                .filter(|entry| entry.task_id == task_id)
+                .collect())
+        })
     }
 
-    pub async fn load() -> Result<SavedState, LoadError> {
-        let storage = Self::storage().ok_or(LoadError::File)?;
+    /// Every recorded change, oldest first, for exports.
+    fn load_all_history(&self) -> StorageFuture<Result<Vec<HistoryEntry>, LoadError>> {
+        let load = self.load_read_only();
 
-        let contents = storage
-            .get_item("state")
-            .map_err(|_| LoadError::File)?
-            .ok_or(LoadError::File)?;
+        Box::pin(async move { Ok(load.await?.history) })
+    }
+
+    /// Unlocks encrypted data after `load` failed with `LoadError::Locked`.
+    /// Loading again afterwards decrypts it with the derived key.
+    fn unlock(&self, _passphrase: String) -> StorageFuture<Result<(), LoadError>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Whether the data can be encrypted with `set_passphrase`.
+    fn supports_encryption(&self) -> bool {
+        false
+    }
+
+    /// Whether the data is stored encrypted.
+    fn is_encrypted(&self) -> bool {
+        false
+    }
+
+    /// Encrypts the data with `passphrase`, or re-encrypts it if it already
+    /// was. `None` decrypts it and stores it in plain text again.
+    fn set_passphrase(&self, _passphrase: Option<String>) -> StorageFuture<Result<(), SaveError>> {
+        Box::pin(async { Err(SaveError::write("this storage does not support encryption")) })
+    }
+
+    /// Whether the data can be snapshotted with `back_up` and restored.
+    fn supports_backups(&self) -> bool {
+        false
+    }
+
+    /// Snapshots the stored data, then deletes all but the `keep` newest
+    /// snapshots.
+    fn back_up(&self, _keep: u32) -> StorageFuture<Result<Option<Snapshot>, SaveError>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// The snapshots taken so far, newest first.
+    fn snapshots(&self) -> StorageFuture<Result<Vec<Snapshot>, LoadError>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    /// Replaces the stored data with `snapshot`, after snapshotting the data
+    /// it replaces. Load again afterwards, which checks the restored data
+    /// like any other.
+    fn restore(&self, _snapshot: Snapshot, _keep: u32) -> StorageFuture<Result<(), SaveError>> {
+        Box::pin(async { Err(SaveError::write("this storage does not support backups")) })
+    }
+
+    /// Whether the data can be checked and repaired with `maintain`.
+    fn supports_maintenance(&self) -> bool {
+        false
+    }
+
+    /// Checks the stored data for corruption and compacts it. Damaged data is
+    /// rebuilt from what can still be read, with the original kept aside. A
+    /// report is written next to the data. Load again after a repair.
+    fn maintain(&self) -> StorageFuture<Result<MaintenanceReport, SaveError>> {
+        Box::pin(async { Err(SaveError::write("this storage does not support maintenance")) })
+    }
 
-        serde_json::from_str(&contents).map_err(|_| LoadError::Format)
+    /// Moves the stored data aside to a timestamped backup so the next save
+    /// starts from scratch. Returns the backup's location, if there was
+    /// anything to back up.
+    fn reset(&self) -> StorageFuture<Result<Option<String>, SaveError>>;
+
+    /// Whether the stored data was written since the last load or check, e.g.
+    /// by another process. The app's own saves may count too, so callers merge
+    /// what they reload rather than replacing their state with it.
+    fn changed(&self) -> StorageFuture<Result<bool, LoadError>> {
+        Box::pin(async { Ok(false) })
+    }
+
+    /// Releases any open handles. Called once, right before the app exits.
+    fn close(&self) -> StorageFuture<()> {
+        Box::pin(async {})
     }
 
-    pub async fn save(self) -> Result<(), SaveError> {
-        let storage = Self::storage().ok_or(SaveError::Write)?;
+    /// Human readable location of the data, e.g. a file path.
+    fn location(&self) -> String;
+}
 
-        let json = serde_json::to_string_pretty(&self).map_err(|_| SaveError::Format)?;
+/// The storage backend picked at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    JsonFile,
+    Git,
+    Memory,
+    Web,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            Backend::Web
+        } else {
+            Backend::Sqlite
+        }
+    }
+}
+
+impl Backend {
+    /// Reads the backend from `TODOS_STORAGE`, falling back to the default for
+    /// the target when it is unset or unknown.
+    pub fn from_env() -> Self {
+        std::env::var("TODOS_STORAGE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Opens the backend inside `data_dir`. `file` overrides the default file
+    /// name for backends that store their data in a single file, and the
+    /// repository's directory for git.
+    pub fn open(self, data_dir: &Path, file: Option<PathBuf>) -> Arc<dyn Storage> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Backend::Sqlite => Arc::new(sqlite_persistence::SqliteStorage::new(
+                file.unwrap_or_else(|| data_dir.join("todos.db")),
+                data_dir.join("todos.json"),
+                data_dir.join("backups"),
+            )),
+            #[cfg(not(target_arch = "wasm32"))]
+            Backend::JsonFile => Arc::new(json_persistence::JsonFileStorage::new(
+                file.unwrap_or_else(|| data_dir.join("tasks.json")),
+            )),
+            #[cfg(not(target_arch = "wasm32"))]
+            Backend::Git => Arc::new(git_persistence::GitStorage::new(
+                file.unwrap_or_else(|| data_dir.join("repository")),
+            )),
+            #[cfg(target_arch = "wasm32")]
+            Backend::Web => Arc::new(web_persistence::WebStorage::new(web_persistence::LocalStorage)),
+            Backend::Memory => Arc::new(memory_persistence::MemoryStorage::default()),
+            // Not available on this target
+            #[allow(unreachable_patterns)]
+            _ => Self::default().open(data_dir, file),
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sqlite" => Ok(Backend::Sqlite),
+            "json" => Ok(Backend::JsonFile),
+            "git" => Ok(Backend::Git),
+            "memory" => Ok(Backend::Memory),
+            "web" if cfg!(target_arch = "wasm32") => Ok(Backend::Web),
+            // Natively it could only keep the data in memory, and lose it
+            "web" => Err(String::from("The web storage backend only runs in the browser")),
+            _ => Err(format!("Unknown storage backend: {}", s)),
+        }
+    }
+}
+
+/// The per-user data directory, used when nothing else is configured.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_data_dir() -> PathBuf {
+    if let Some(project_dirs) = directories::ProjectDirs::from("rs", "Iced", "Todos") {
+        project_dirs.data_dir().into()
+    } else {
+        std::env::current_dir().unwrap_or_default()
+    }
+}
+
+/// The directory containing the executable, for running from removable media.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn portable_data_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+}
+
+/// Renames `path` to `<name>.<unix time>.bak` next to it, if it exists.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn move_to_backup(path: &Path) -> Result<Option<PathBuf>, SaveError> {
+    if !tokio::fs::try_exists(path).await.map_err(SaveError::write)? {
+        return Ok(None);
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    let backup = backups::sibling(path, &format!(".{}.bak", timestamp));
+
+    tokio::fs::rename(path, &backup)
+        .await
+        .map_err(SaveError::write)?;
+
+    Ok(Some(backup))
+}
+
+impl SavedState {
+    /// Applies saved changes in place, for backends that store the whole state.
+    pub fn apply(&mut self, changes: SavedChanges) {
+        self.input_value = changes.input_value;
+        self.filter = changes.filter;
+        self.preferences = changes.preferences;
+        self.caldav = changes.caldav;
 
-        storage
-            .set_item("state", &json)
-            .map_err(|_| SaveError::Write)?;
+        self.tasks.retain(|task| !changes.deleted.contains(&task.id()));
+        self.history.extend(changes.history);
+        self.history.retain(|entry| !changes.deleted.contains(&entry.task_id));
 
-        wasmtimer::tokio::sleep(std::time::Duration::from_secs(2)).await;
+        for task in changes.upserted {
+            match self.tasks.iter_mut().find(|existing| existing.id() == task.id()) {
+                Some(existing) => *existing = task,
+                None => self.tasks.push(task),
+            }
+        }
 
-        Ok(())
+        // Stable, so tasks saved before manual ordering keep their order
+        self.tasks.sort_by_key(Task::position);
     }
 }
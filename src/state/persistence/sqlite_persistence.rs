@@ -1,162 +1,1318 @@
-use sqlx::{sqlite::SqliteConnectOptions, SqlitePool, Row};
+use sqlx::{sqlite::{SqliteArguments, SqliteConnectOptions, SqliteJournalMode, SqliteRow, SqliteSynchronous}, query::Query, ConnectOptions, Connection, Sqlite, SqliteConnection, SqlitePool, Row, ValueRef};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
+use chrono::{DateTime, NaiveDateTime};
+use crate::i18n::Language;
+use crate::state::caldav::Collection;
+use crate::state::history::{Change, HistoryEntry};
 use crate::task::Task;
-use super::{SavedState, Filter, LoadError, SaveError};
+use super::encryption::{Cipher, KeyInfo};
+use super::{backups, maintenance, migrations, SavedChanges, SavedState, Filter, LoadError, MaintenanceReport, Preferences, SaveError, Snapshot, Storage, StorageFuture};
 
+/// How SQLite's `CURRENT_TIMESTAMP` writes `tasks.created_at`, in UTC.
+const CREATED_AT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Reads a text column, decrypting it if the data is encrypted.
+fn get_text(row: &SqliteRow, column: &str, cipher: Option<&Cipher>) -> Result<String, LoadError> {
+    let Some(cipher) = cipher else {
+        return row.try_get(column).map_err(LoadError::format);
+    };
+
+    let sealed: Vec<u8> = row.try_get(column).map_err(LoadError::format)?;
+    cipher
+        .open(&sealed)
+        .and_then(|text| String::from_utf8(text).ok())
+        .ok_or_else(|| LoadError::format(format!("{} could not be decrypted", column)))
+}
+
+/// Like `get_text`, for a column that may be NULL.
+fn get_optional_text(row: &SqliteRow, column: &str, cipher: Option<&Cipher>) -> Result<Option<String>, LoadError> {
+    if row.try_get_raw(column).map_err(LoadError::format)?.is_null() {
+        return Ok(None);
+    }
+
+    get_text(row, column, cipher).map(Some)
+}
+
+/// Binds a text value, encrypted if the data is encrypted.
+fn bind_text<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    text: &'q str,
+    cipher: Option<&Cipher>,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match cipher {
+        Some(cipher) => query.bind(cipher.seal(text.as_bytes())),
+        None => query.bind(text),
+    }
+}
+
+/// Like `bind_text`, binding NULL for `None`.
+fn bind_optional_text<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    text: Option<&'q str>,
+    cipher: Option<&Cipher>,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match text {
+        Some(text) => bind_text(query, text, cipher),
+        None => query.bind(None::<String>),
+    }
+}
+
+/// The `kind`, `old_value` and `new_value` columns of a history row.
+fn change_columns(change: &Change) -> (&'static str, Option<&str>, Option<&str>) {
+    match change {
+        Change::Created { description } => ("created", None, Some(description)),
+        Change::Renamed { from, to } => ("renamed", Some(from), Some(to)),
+        Change::Completed(true) => ("completed", Some("false"), Some("true")),
+        Change::Completed(false) => ("completed", Some("true"), Some("false")),
+        Change::Deleted => ("deleted", None, None),
+        Change::Restored => ("restored", None, None),
+    }
+}
+
+fn change_from_columns(kind: &str, old_value: Option<String>, new_value: Option<String>) -> Option<Change> {
+    match (kind, old_value, new_value) {
+        ("created", _, Some(description)) => Some(Change::Created { description }),
+        ("renamed", Some(from), Some(to)) => Some(Change::Renamed { from, to }),
+        ("completed", _, Some(completed)) => Some(Change::Completed(completed == "true")),
+        ("deleted", _, _) => Some(Change::Deleted),
+        ("restored", _, _) => Some(Change::Restored),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SqlitePersistence {
     pool: SqlitePool,
 }
 
 impl SqlitePersistence {
-    pub async fn new() -> Result<Self, String> {
-        let db_path = Self::db_path();
-        
+    pub async fn new(db_path: &Path) -> Result<Self, LoadError> {
         // Create directory if it doesn't exist
         if let Some(dir) = db_path.parent() {
             tokio::fs::create_dir_all(dir)
                 .await
-                .map_err(|e| format!("Failed to create directory: {}", e))?;
+                .map_err(LoadError::file)?;
         }
 
+        // Before switching to WAL, which would already change the file
+        migrations::check_version(db_path).await?;
+
         let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", db_path.display()))
-            .map_err(|e| format!("Failed to parse SQLite connection string: {}", e))?
-            .create_if_missing(true);
+            .map_err(LoadError::file)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            // Overwrite deleted content, so decrypting leaves no ciphertext
+            // behind and encrypting no plain text
+            .pragma("secure_delete", "on");
             
         let pool = SqlitePool::connect_with(options)
             .await
-            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+            .map_err(LoadError::file)?;
 
-        let persistence = Self { pool };
-        persistence.init_tables().await?;
-        
-        Ok(persistence)
+        migrations::migrate(&pool).await?;
+
+        Ok(Self { pool })
     }
 
-    fn db_path() -> std::path::PathBuf {
-        let mut path = if let Some(project_dirs) = directories::ProjectDirs::from("rs", "Iced", "Todos") {
-            project_dirs.data_dir().into()
+    /// Opens an existing database without creating, migrating or writing to it.
+    pub async fn open_read_only(db_path: &Path) -> Result<Self, LoadError> {
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", db_path.display()))
+            .map_err(LoadError::file)?
+            .read_only(true);
+
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .map_err(LoadError::file)?;
+
+        Ok(Self { pool })
+    }
+
+    /// Closes the pool, waiting for in-flight queries. With WAL this also
+    /// checkpoints the log back into the database file.
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Whether the table exists. Databases opened read-only may predate it.
+    async fn has_table(&self, name: &str) -> Result<bool, LoadError> {
+        sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row| row.is_some())
+            .map_err(LoadError::file)
+    }
+
+    /// Checks for what would make a load fail outright: a file that is not a
+    /// task database, corruption, or a schema from a newer version.
+    pub async fn check(&self) -> Result<(), LoadError> {
+        let integrity: String = sqlx::query_scalar("PRAGMA quick_check")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(LoadError::file)?;
+
+        if integrity != "ok" {
+            return Err(LoadError::format(integrity));
+        }
+
+        if !self.has_table("tasks").await? {
+            return Err(LoadError::format("there are no tasks in this file"));
+        }
+
+        let version: i64 = if self.has_table("schema_version").await? {
+            sqlx::query_scalar("SELECT version FROM schema_version WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(LoadError::file)?
+                .unwrap_or(0)
         } else {
-            std::env::current_dir().unwrap_or_default()
+            0
         };
-        
-        path.push("todos.db");
-        path
+
+        if version > migrations::SCHEMA_VERSION {
+            return Err(LoadError::UnsupportedVersion {
+                found: version,
+                supported: migrations::SCHEMA_VERSION,
+            });
+        }
+
+        Ok(())
     }
 
-    async fn init_tables(&self) -> Result<(), String> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS app_state (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                input_value TEXT NOT NULL DEFAULT '',
-                filter INTEGER NOT NULL DEFAULT 0
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(|e| format!("Failed to create app_state table: {}", e))?;
+    /// Number of tasks outside the trash.
+    pub async fn task_count(&self) -> Result<usize, LoadError> {
+        let count: i64 = match sqlx::query_scalar("SELECT COUNT(*) FROM tasks WHERE deleted_at IS NULL")
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(count) => count,
+            // Written before the trash existed
+            Err(_) => sqlx::query_scalar("SELECT COUNT(*) FROM tasks")
+                .fetch_one(&self.pool)
+                .await
+                .map_err(LoadError::file)?,
+        };
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS tasks (
-                id TEXT PRIMARY KEY,
-                description TEXT NOT NULL,
-                completed BOOLEAN NOT NULL DEFAULT FALSE,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(|e| format!("Failed to create tasks table: {}", e))?;
+        Ok(count as usize)
+    }
 
-        // Initialize default app state if not exists
-        sqlx::query(
-            "INSERT OR IGNORE INTO app_state (id, input_value, filter) VALUES (1, '', 0)"
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(|e| format!("Failed to initialize app state: {}", e))?;
+    /// Writes a consistent copy of the database to `path`, which must not
+    /// exist yet.
+    pub async fn vacuum_into(&self, path: &Path) -> Result<(), SaveError> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.display().to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(SaveError::write)?;
 
         Ok(())
     }
 
-    pub async fn load(&self) -> Result<SavedState, LoadError> {
+    /// The stored key info, or `None` if the data is not encrypted.
+    pub async fn key_info(&self) -> Result<Option<KeyInfo>, LoadError> {
+        if !self.has_table("encryption").await? {
+            return Ok(None);
+        }
+
+        let row = sqlx::query("SELECT salt, check_value FROM encryption WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(LoadError::file)?;
+
+        row.map(|row| {
+            Ok(KeyInfo {
+                salt: row.try_get("salt").map_err(LoadError::format)?,
+                check: row.try_get("check_value").map_err(LoadError::format)?,
+            })
+        })
+        .transpose()
+    }
+
+    pub async fn load(&self, cipher: Option<&Cipher>) -> Result<SavedState, LoadError> {
+        self.read(false, cipher).await
+    }
+
+    /// Loads every row that can be read, skipping the ones that can't.
+    pub async fn load_lenient(&self, cipher: Option<&Cipher>) -> Result<SavedState, LoadError> {
+        self.read(true, cipher).await
+    }
+
+    async fn read(&self, lenient: bool, cipher: Option<&Cipher>) -> Result<SavedState, LoadError> {
         // Load app state
-        let app_state_row = sqlx::query("SELECT input_value, filter FROM app_state WHERE id = 1")
+        let app_state = sqlx::query("SELECT input_value, filter FROM app_state WHERE id = 1")
             .fetch_one(&self.pool)
             .await
-            .map_err(|_| LoadError::File)?;
+            .map_err(LoadError::file)
+            .and_then(|row| {
+                let input_value = get_text(&row, "input_value", cipher)?;
+                let filter_int: i64 = row.try_get("filter").map_err(LoadError::format)?;
+                Ok((input_value, filter_int))
+            });
+
+        let (input_value, filter_int) = match app_state {
+            Ok(app_state) => app_state,
+            Err(_) if lenient => (String::new(), 0),
+            Err(e) => return Err(e),
+        };
 
-        let input_value: String = app_state_row.get("input_value");
-        let filter_int: i64 = app_state_row.get("filter");
         let filter = match filter_int {
             1 => Filter::Active,
             2 => Filter::Completed,
+            3 => Filter::Trash,
             _ => Filter::All,
         };
 
-        // Load tasks
-        let task_rows = sqlx::query("SELECT id, description, completed FROM tasks ORDER BY created_at")
+        // Not worth failing the load over
+        let preferences = self.load_preferences().await.unwrap_or_default();
+        let caldav = self.load_caldav(cipher).await;
+
+        // Load tasks. `*` and sorting here rather than in SQL, so databases
+        // opened read-only before later columns were added can still be read
+        let task_rows = sqlx::query("SELECT * FROM tasks ORDER BY rowid")
             .fetch_all(&self.pool)
             .await
-            .map_err(|_| LoadError::File)?;
+            .map_err(LoadError::file)?;
 
         let mut tasks = Vec::new();
+        let mut skipped = 0;
         for row in task_rows {
-            let id_str: String = row.get("id");
-            let id = Uuid::parse_str(&id_str).map_err(|_| LoadError::Format)?;
-            let description: String = row.get("description");
-            let completed: bool = row.get("completed");
-
-            tasks.push(Task::from_parts(id, description, completed));
+            match Self::task_from_row(&row, cipher) {
+                Ok(task) => tasks.push(task),
+                Err(_) if lenient => skipped += 1,
+                Err(e) => return Err(e),
+            }
         }
+        tasks.sort_by_key(Task::position);
 
         Ok(SavedState {
             input_value,
             filter,
             tasks,
+            preferences,
+            history: Vec::new(),
+            caldav,
+            legacy_imported: None,
+            skipped,
         })
     }
 
-    pub async fn save(&self, state: SavedState) -> Result<(), SaveError> {
-        let mut tx = self.pool.begin().await.map_err(|_| SaveError::Write)?;
+    /// The CalDAV collection synced with. `None` if there is none, or it
+    /// cannot be read, e.g. from a database opened read-only before it was
+    /// added.
+    async fn load_caldav(&self, cipher: Option<&Cipher>) -> Option<Collection> {
+        let row = sqlx::query("SELECT * FROM app_state WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await
+            .ok()??;
+        let caldav = get_optional_text(&row, "caldav", cipher).ok()??;
+
+        serde_json::from_str(&caldav).ok()
+    }
+
+    /// The recorded changes to a task, or to every task without `task_id`,
+    /// oldest first. Rows that cannot be read are skipped.
+    pub async fn load_history(
+        &self,
+        task_id: Option<Uuid>,
+        cipher: Option<&Cipher>,
+    ) -> Result<Vec<HistoryEntry>, LoadError> {
+        if !self.has_table("task_history").await? {
+            return Ok(Vec::new());
+        }
+
+        let rows = match task_id {
+            Some(task_id) => sqlx::query("SELECT * FROM task_history WHERE task_id = ? ORDER BY id")
+                .bind(task_id.to_string())
+                .fetch_all(&self.pool)
+                .await,
+            None => sqlx::query("SELECT * FROM task_history ORDER BY id")
+                .fetch_all(&self.pool)
+                .await,
+        }
+        .map_err(LoadError::file)?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| Self::history_entry_from_row(row, cipher).ok().flatten())
+            .collect())
+    }
+
+    fn history_entry_from_row(row: &SqliteRow, cipher: Option<&Cipher>) -> Result<Option<HistoryEntry>, LoadError> {
+        let task_id: String = row.try_get("task_id").map_err(LoadError::format)?;
+        let task_id = Uuid::parse_str(&task_id).map_err(LoadError::format)?;
+        let changed_at: i64 = row.try_get("changed_at").map_err(LoadError::format)?;
+        let kind: String = row.try_get("kind").map_err(LoadError::format)?;
+        let old_value = get_optional_text(row, "old_value", cipher)?;
+        let new_value = get_optional_text(row, "new_value", cipher)?;
+
+        Ok(DateTime::from_timestamp(changed_at, 0)
+            .zip(change_from_columns(&kind, old_value, new_value))
+            .map(|(at, change)| HistoryEntry {
+                task_id,
+                at,
+                change,
+            }))
+    }
+
+    /// Reads the preferences, with defaults for anything missing, such as the
+    /// columns in a database opened read-only before they were added.
+    pub async fn load_preferences(&self) -> Result<Preferences, LoadError> {
+        let row = sqlx::query("SELECT * FROM app_state WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(LoadError::file)?;
+
+        Ok(row.map(|row| Self::preferences_from_row(&row)).unwrap_or_default())
+    }
+
+    fn preferences_from_row(row: &SqliteRow) -> Preferences {
+        let real = |column| row.try_get::<Option<f64>, _>(column).ok().flatten();
+        let pair = |a, b| Some((real(a)? as f32, real(b)? as f32));
+
+        Preferences {
+            language: row
+                .try_get::<Option<String>, _>("language")
+                .ok()
+                .flatten()
+                .and_then(|code| Language::from_code(&code)),
+            window_size: pair("window_width", "window_height"),
+            window_position: pair("window_x", "window_y"),
+            fullscreen: row.try_get("fullscreen").unwrap_or_default(),
+            trash_retention_days: match row.try_get::<Option<i64>, _>("trash_retention_days") {
+                Ok(days) => days.map(|days| days.clamp(0, u32::MAX.into()) as u32),
+                Err(_) => Some(Preferences::DEFAULT_TRASH_RETENTION_DAYS),
+            },
+            backup_interval_hours: row
+                .try_get::<u32, _>("backup_interval_hours")
+                .unwrap_or(Preferences::DEFAULT_BACKUP_INTERVAL_HOURS),
+            backup_generations: row
+                .try_get::<u32, _>("backup_generations")
+                .unwrap_or(Preferences::DEFAULT_BACKUP_GENERATIONS),
+        }
+    }
+
+    fn task_from_row(row: &SqliteRow, cipher: Option<&Cipher>) -> Result<Task, LoadError> {
+        let id_str: String = row.try_get("id").map_err(LoadError::format)?;
+        let id = Uuid::parse_str(&id_str).map_err(LoadError::format)?;
+        let description = get_text(row, "description", cipher)?;
+        let completed: bool = row.try_get("completed").map_err(LoadError::format)?;
+        let position: i64 = row.try_get("position").unwrap_or_default();
+        let deleted_at = row
+            .try_get::<Option<i64>, _>("deleted_at")
+            .ok()
+            .flatten()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0));
+        let created_at = row
+            .try_get::<Option<String>, _>("created_at")
+            .ok()
+            .flatten()
+            .and_then(|text| NaiveDateTime::parse_from_str(&text, CREATED_AT_FORMAT).ok())
+            .map(|created_at| created_at.and_utc());
+        let completed_at = row
+            .try_get::<Option<i64>, _>("completed_at")
+            .ok()
+            .flatten()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0));
+
+        let mut task = Task::from_parts(
+            id,
+            description,
+            completed,
+            position,
+            deleted_at,
+            created_at,
+            completed_at,
+        );
+
+        // Missing from databases opened read-only before it was added
+        if let Some(extra) = get_optional_text(row, "extra", cipher).ok().flatten() {
+            *task.extra_mut() = serde_json::from_str(&extra).map_err(LoadError::format)?;
+        }
+
+        Ok(task)
+    }
+
+    pub async fn save(&self, changes: SavedChanges, cipher: Option<&Cipher>) -> Result<(), SaveError> {
+        let mut tx = self.pool.begin().await.map_err(SaveError::write)?;
 
         // Save app state
-        let filter_int = match state.filter {
+        let filter_int = match changes.filter {
             Filter::All => 0,
             Filter::Active => 1,
             Filter::Completed => 2,
+            Filter::Trash => 3,
         };
 
-        sqlx::query("UPDATE app_state SET input_value = ?, filter = ? WHERE id = 1")
-            .bind(&state.input_value)
+        let preferences = &changes.preferences;
+        let query = sqlx::query(
+            r#"
+            UPDATE app_state SET
+                input_value = ?, filter = ?, language = ?,
+                window_width = ?, window_height = ?, window_x = ?, window_y = ?, fullscreen = ?,
+                trash_retention_days = ?, backup_interval_hours = ?, backup_generations = ?,
+                caldav = ?
+            WHERE id = 1
+            "#,
+        );
+
+        let caldav = changes
+            .caldav
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(SaveError::write)?;
+
+        let query = bind_text(query, &changes.input_value, cipher)
             .bind(filter_int)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| SaveError::Write)?;
+            .bind(preferences.language.map(Language::code))
+            .bind(preferences.window_size.map(|(width, _)| width))
+            .bind(preferences.window_size.map(|(_, height)| height))
+            .bind(preferences.window_position.map(|(x, _)| x))
+            .bind(preferences.window_position.map(|(_, y)| y))
+            .bind(preferences.fullscreen)
+            .bind(preferences.trash_retention_days)
+            .bind(preferences.backup_interval_hours)
+            .bind(preferences.backup_generations);
 
-        // Clear existing tasks
-        sqlx::query("DELETE FROM tasks")
+        bind_optional_text(query, caldav.as_deref(), cipher)
             .execute(&mut *tx)
             .await
-            .map_err(|_| SaveError::Write)?;
+            .map_err(SaveError::write)?;
+
+        // Append history first, so the history of tasks deleted in the same
+        // save goes with them
+        for entry in &changes.history {
+            let (kind, old_value, new_value) = change_columns(&entry.change);
+            let query = sqlx::query(
+                "INSERT INTO task_history (task_id, changed_at, kind, old_value, new_value) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(entry.task_id.to_string())
+            .bind(entry.at.timestamp())
+            .bind(kind);
+
+            bind_optional_text(bind_optional_text(query, old_value, cipher), new_value, cipher)
+                .execute(&mut *tx)
+                .await
+                .map_err(SaveError::write)?;
+        }
+
+        // Remove deleted tasks along with their history
+        for id in &changes.deleted {
+            sqlx::query("DELETE FROM tasks WHERE id = ?")
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(SaveError::write)?;
+            sqlx::query("DELETE FROM task_history WHERE task_id = ?")
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(SaveError::write)?;
+        }
+
+        // Upsert changed tasks, keeping the original created_at
+        for task in &changes.upserted {
+            let query = sqlx::query(
+                r#"
+                INSERT INTO tasks (id, description, completed, position, deleted_at, created_at, completed_at, extra)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(id) DO UPDATE SET
+                    description = excluded.description,
+                    completed = excluded.completed,
+                    position = excluded.position,
+                    deleted_at = excluded.deleted_at,
+                    completed_at = excluded.completed_at,
+                    extra = excluded.extra
+                "#,
+            )
+            .bind(task.id().to_string());
 
-        // Save tasks
-        for task in &state.tasks {
-            sqlx::query("INSERT INTO tasks (id, description, completed) VALUES (?, ?, ?)")
-                .bind(task.id().to_string())
-                .bind(task.description())
+            let extra = if task.extra().is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(task.extra()).map_err(SaveError::write)?)
+            };
+
+            let query = bind_text(query, task.description(), cipher)
                 .bind(task.completed())
+                .bind(task.position())
+                .bind(task.deleted_at().map(|deleted_at| deleted_at.timestamp()))
+                .bind(
+                    task.created_at()
+                        .map(|created_at| created_at.format(CREATED_AT_FORMAT).to_string()),
+                )
+                .bind(task.completed_at().map(|completed_at| completed_at.timestamp()));
+
+            bind_optional_text(query, extra.as_deref(), cipher)
                 .execute(&mut *tx)
                 .await
-                .map_err(|_| SaveError::Write)?;
+                .map_err(SaveError::write)?;
         }
 
-        tx.commit().await.map_err(|_| SaveError::Write)?;
+        tx.commit().await.map_err(SaveError::write)?;
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Rewrites every encrypted value from `from` to `to`, where `None` means
+    /// plain text, and replaces the stored key info.
+    pub async fn reencrypt(&self, from: Option<&Cipher>, to: Option<&Cipher>) -> Result<(), SaveError> {
+        let mut tx = self.pool.begin().await.map_err(SaveError::write)?;
+
+        let app_state = sqlx::query("SELECT input_value, caldav FROM app_state WHERE id = 1")
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(SaveError::write)?;
+        let input_value = get_text(&app_state, "input_value", from).map_err(SaveError::write)?;
+        let caldav = get_optional_text(&app_state, "caldav", from).map_err(SaveError::write)?;
+
+        let query = sqlx::query("UPDATE app_state SET input_value = ?, caldav = ? WHERE id = 1");
+        bind_optional_text(bind_text(query, &input_value, to), caldav.as_deref(), to)
+            .execute(&mut *tx)
+            .await
+            .map_err(SaveError::write)?;
+
+        let task_rows = sqlx::query("SELECT id, description, extra FROM tasks")
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(SaveError::write)?;
+
+        for row in task_rows {
+            let id: String = row.try_get("id").map_err(SaveError::write)?;
+            let description = get_text(&row, "description", from).map_err(SaveError::write)?;
+            let extra = get_optional_text(&row, "extra", from).map_err(SaveError::write)?;
+
+            let query = sqlx::query("UPDATE tasks SET description = ?, extra = ? WHERE id = ?");
+            bind_optional_text(bind_text(query, &description, to), extra.as_deref(), to)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(SaveError::write)?;
+        }
+
+        let history_rows = sqlx::query("SELECT id, old_value, new_value FROM task_history")
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(SaveError::write)?;
+
+        for row in history_rows {
+            let id: i64 = row.try_get("id").map_err(SaveError::write)?;
+            let old_value = get_optional_text(&row, "old_value", from).map_err(SaveError::write)?;
+            let new_value = get_optional_text(&row, "new_value", from).map_err(SaveError::write)?;
+
+            let query = sqlx::query("UPDATE task_history SET old_value = ?, new_value = ? WHERE id = ?");
+            bind_optional_text(
+                bind_optional_text(query, old_value.as_deref(), to),
+                new_value.as_deref(),
+                to,
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(SaveError::write)?;
+        }
+
+        sqlx::query("DELETE FROM encryption")
+            .execute(&mut *tx)
+            .await
+            .map_err(SaveError::write)?;
+
+        if let Some(to) = to {
+            let key_info = to.key_info();
+            sqlx::query("INSERT INTO encryption (id, salt, check_value) VALUES (1, ?, ?)")
+                .bind(key_info.salt)
+                .bind(key_info.check)
+                .execute(&mut *tx)
+                .await
+                .map_err(SaveError::write)?;
+        }
+
+        tx.commit().await.map_err(SaveError::write)?;
+
+        // Move the rewritten pages out of the log and rebuild the file, so no
+        // copy of the old values is left in either
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&self.pool)
+            .await
+            .map_err(SaveError::write)?;
+        sqlx::query("VACUUM")
+            .execute(&self.pool)
+            .await
+            .map_err(SaveError::write)?;
+
+        Ok(())
+    }
+}
+
+/// A connection of its own for `PRAGMA data_version`, which only changes for
+/// commits made through other connections: the pool's or another process's.
+/// The pool's own commits are taken in with `own_write`, so only other
+/// processes' are reported.
+#[derive(Debug)]
+struct Watcher {
+    connection: SqliteConnection,
+    data_version: i64,
+    /// Another process committed before one of ours was taken in, and that
+    /// has not been reported yet.
+    unreported: bool,
+}
+
+impl Watcher {
+    async fn open(db_path: &Path) -> Result<Self, LoadError> {
+        let mut connection = SqliteConnectOptions::from_str(&format!("sqlite://{}", db_path.display()))
+            .map_err(LoadError::file)?
+            .read_only(true)
+            .connect()
+            .await
+            .map_err(LoadError::file)?;
+
+        let data_version = Self::data_version(&mut connection).await?;
+
+        Ok(Self {
+            connection,
+            data_version,
+            unreported: false,
+        })
+    }
+
+    async fn data_version(connection: &mut SqliteConnection) -> Result<i64, LoadError> {
+        sqlx::query_scalar("PRAGMA data_version")
+            .fetch_one(connection)
+            .await
+            .map_err(LoadError::file)
+    }
+
+    /// Whether another process committed anything since the last call.
+    async fn changed(&mut self) -> Result<bool, LoadError> {
+        let data_version = Self::data_version(&mut self.connection).await?;
+        let changed = data_version != self.data_version || self.unreported;
+        self.data_version = data_version;
+        self.unreported = false;
+
+        Ok(changed)
+    }
+
+    /// Takes in the current version without reporting it.
+    async fn catch_up(&mut self) -> Result<(), LoadError> {
+        self.data_version = Self::data_version(&mut self.connection).await?;
+
+        Ok(())
+    }
+}
+
+/// The default backend, backed by `todos.db` in the data directory.
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    db_path: PathBuf,
+    /// The `todos.json` written by versions before the move to SQLite.
+    legacy_path: PathBuf,
+    /// Where `back_up` puts its snapshots.
+    backup_dir: PathBuf,
+    /// Opened by the first load and kept for the lifetime of the app.
+    persistence: Arc<Mutex<Option<SqlitePersistence>>>,
+    /// Opened by each load, so only writes made after it are reported.
+    watcher: Arc<Mutex<Option<Watcher>>>,
+    /// The unlocked key, if the data is encrypted. Saves hold it for reading,
+    /// so changing the passphrase waits for them and the other way around.
+    cipher: Arc<RwLock<Option<Cipher>>>,
+}
+
+impl SqliteStorage {
+    pub fn new(db_path: PathBuf, legacy_path: PathBuf, backup_dir: PathBuf) -> Self {
+        Self {
+            db_path,
+            legacy_path,
+            backup_dir,
+            persistence: Arc::default(),
+            watcher: Arc::default(),
+            cipher: Arc::default(),
+        }
+    }
+
+    /// Returns the open persistence handle, opening it on first use.
+    async fn persistence(&self) -> Result<SqlitePersistence, LoadError> {
+        let mut persistence = self.persistence.lock().await;
+
+        if let Some(persistence) = persistence.as_ref() {
+            return Ok(persistence.clone());
+        }
+
+        let opened = SqlitePersistence::new(&self.db_path).await?;
+        *persistence = Some(opened.clone());
+
+        Ok(opened)
+    }
+
+    async fn close_persistence(&self) {
+        if let Some(watcher) = self.watcher.lock().await.take() {
+            let _ = watcher.connection.close().await;
+        }
+
+        if let Some(persistence) = self.persistence.lock().await.take() {
+            persistence.close().await;
+        }
+    }
+
+    /// Starts watching from the current version of the database. Reloads
+    /// keep the connection opened by the first load.
+    async fn watch(&self) -> Result<(), LoadError> {
+        let mut watcher = self.watcher.lock().await;
+
+        match watcher.as_mut() {
+            Some(watcher) => {
+                watcher.catch_up().await?;
+                watcher.unreported = false;
+                Ok(())
+            }
+            None => {
+                *watcher = Some(Watcher::open(&self.db_path).await?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs a write of this process's own, so `check_changed` does not report
+    /// it back as another process's. Commits from elsewhere before it are
+    /// still reported.
+    async fn own_write<T>(
+        &self,
+        write: impl Future<Output = Result<T, SaveError>>,
+    ) -> Result<T, SaveError> {
+        let mut watcher = self.watcher.lock().await;
+        let Some(watcher) = watcher.as_mut() else {
+            return write.await;
+        };
+
+        // If the version cannot be read, assume something changed
+        watcher.unreported = watcher.changed().await.unwrap_or(true);
+        let result = write.await;
+        // Failing here only reports the write as well, costing a reload
+        let _ = watcher.catch_up().await;
+
+        result
+    }
+
+    async fn check_changed(self) -> Result<bool, LoadError> {
+        let mut watcher = self.watcher.lock().await;
+
+        match watcher.as_mut() {
+            Some(watcher) => watcher.changed().await,
+            // Nothing loaded since the last reset; start watching now
+            None => {
+                *watcher = Some(Watcher::open(&self.db_path).await?);
+                Ok(false)
+            }
+        }
+    }
+
+    async fn load_state(self) -> Result<SavedState, LoadError> {
+        let persistence = self.persistence().await?;
+        let cipher = self.cipher.read().await;
+
+        if cipher.is_none() && persistence.key_info().await?.is_some() {
+            return Err(LoadError::Locked);
+        }
+
+        // Before reading, so a write that lands in between is still reported
+        self.watch().await?;
+        let state = persistence.load(cipher.as_ref()).await?;
+
+        if state.tasks.is_empty()
+            && let Some(legacy) = self.load_legacy().await
+        {
+            return self.import_legacy(&persistence, legacy, cipher.as_ref()).await;
+        }
+
+        Ok(state)
+    }
+
+    async fn load_legacy(&self) -> Option<SavedState> {
+        let path = &self.legacy_path;
+        let contents = tokio::fs::read_to_string(&path).await.ok()?;
+
+        match serde_json::from_str(&contents) {
+            Ok(legacy) => Some(legacy),
+            Err(e) => {
+                eprintln!("Failed to parse legacy {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    async fn import_legacy(
+        &self,
+        persistence: &SqlitePersistence,
+        mut legacy: SavedState,
+        cipher: Option<&Cipher>,
+    ) -> Result<SavedState, LoadError> {
+        // The JSON kept its order implicitly
+        for (position, task) in legacy.tasks.iter_mut().enumerate() {
+            task.set_position(position as i64);
+        }
+
+        persistence
+            .save(SavedChanges {
+                input_value: legacy.input_value.clone(),
+                filter: legacy.filter,
+                upserted: legacy.tasks.clone(),
+                deleted: Vec::new(),
+                history: Vec::new(),
+                preferences: legacy.preferences.clone(),
+                caldav: legacy.caldav.clone(),
+            }, cipher)
+            .await
+            .map_err(LoadError::file)?;
+
+        // Keep the original around as a backup, out of the way of future imports
+        let path = &self.legacy_path;
+        if let Err(e) = tokio::fs::rename(path, path.with_extension("json.migrated")).await {
+            eprintln!("Failed to rename legacy {}: {}", path.display(), e);
+        }
+
+        Ok(SavedState {
+            legacy_imported: Some(legacy.tasks.len()),
+            ..legacy
+        })
+    }
+
+    async fn load_read_only_state(self) -> Result<SavedState, LoadError> {
+        let persistence = SqlitePersistence::open_read_only(&self.db_path).await?;
+        let state = self.read_only_state(&persistence).await;
+        persistence.close().await;
+        state
+    }
+
+    async fn read_only_state(&self, persistence: &SqlitePersistence) -> Result<SavedState, LoadError> {
+        let cipher = self.cipher.read().await;
+
+        if cipher.is_none() && persistence.key_info().await?.is_some() {
+            return Err(LoadError::Locked);
+        }
+
+        self.watch().await?;
+        persistence.load_lenient(cipher.as_ref()).await
+    }
+
+    /// The history of one task, or of all of them without `task_id`.
+    async fn history(self, task_id: Option<Uuid>) -> Result<Vec<HistoryEntry>, LoadError> {
+        let cipher = self.cipher.read().await;
+        let open = self.persistence.lock().await.clone();
+
+        match open {
+            Some(persistence) => persistence.load_history(task_id, cipher.as_ref()).await,
+            // Opened read-only, nothing to migrate or write to
+            None => {
+                let persistence = SqlitePersistence::open_read_only(&self.db_path).await?;
+                let history = persistence.load_history(task_id, cipher.as_ref()).await;
+                persistence.close().await;
+                history
+            }
+        }
+    }
+
+    async fn unlock_with(self, passphrase: String) -> Result<(), LoadError> {
+        // Read-only, so a second instance can unlock without writing
+        let persistence = SqlitePersistence::open_read_only(&self.db_path).await?;
+        let key_info = persistence.key_info().await;
+        persistence.close().await;
+
+        let Some(key_info) = key_info? else {
+            return Ok(());
+        };
+
+        let cipher = tokio::task::spawn_blocking(move || Cipher::unlock(&passphrase, &key_info))
+            .await
+            .map_err(LoadError::file)??;
+
+        *self.cipher.write().await = Some(cipher);
+
+        Ok(())
+    }
+
+    async fn change_passphrase(self, passphrase: Option<String>) -> Result<(), SaveError> {
+        let persistence = self.persistence().await.map_err(SaveError::write)?;
+        let mut cipher = self.cipher.write().await;
+
+        let new_cipher = match passphrase {
+            Some(passphrase) => Some(
+                tokio::task::spawn_blocking(move || Cipher::generate(&passphrase))
+                    .await
+                    .map_err(SaveError::write)?
+                    .map_err(SaveError::write)?,
+            ),
+            None => None,
+        };
+
+        self.own_write(persistence.reencrypt(cipher.as_ref(), new_cipher.as_ref()))
+            .await?;
+        *cipher = new_cipher;
+
+        Ok(())
+    }
+
+    async fn save_changes(self, changes: SavedChanges) -> Result<(), SaveError> {
+        let persistence = self.persistence().await.map_err(SaveError::write)?;
+        let cipher = self.cipher.read().await;
+        self.own_write(persistence.save(changes, cipher.as_ref())).await
+    }
+
+    async fn back_up_database(self, keep: u32) -> Result<Option<Snapshot>, SaveError> {
+        let persistence = self.persistence().await.map_err(SaveError::write)?;
+        let snapshot = backups::create(&persistence, &self.backup_dir).await?;
+        backups::rotate(&self.backup_dir, keep).await?;
+
+        Ok(Some(snapshot))
+    }
+
+    async fn restore_snapshot(self, snapshot: Snapshot, keep: u32) -> Result<(), SaveError> {
+        // Refuse what a load would reject anyway, before anything is replaced
+        backups::check(&snapshot.path).await.map_err(SaveError::write)?;
+
+        // Copied first, as backing up the current data may rotate the
+        // snapshot itself away
+        let restoring = backups::sibling(&self.db_path, ".restoring");
+        tokio::fs::copy(&snapshot.path, &restoring)
+            .await
+            .map_err(SaveError::write)?;
+
+        // So the restore can be undone
+        self.clone().back_up_database(keep).await?;
+
+        self.close_persistence().await;
+        *self.cipher.write().await = None;
+
+        // A log left next to the old file would be replayed into the new one
+        for suffix in ["-wal", "-shm"] {
+            match tokio::fs::remove_file(backups::sibling(&self.db_path, suffix)).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(SaveError::write(e)),
+                _ => {}
+            }
+        }
+
+        tokio::fs::rename(&restoring, &self.db_path)
+            .await
+            .map_err(SaveError::write)
+    }
+
+    async fn maintain_database(self) -> Result<MaintenanceReport, SaveError> {
+        // Maintenance needs the file to itself, the next use reopens it
+        self.close_persistence().await;
+
+        let report_dir = self.db_path.parent().unwrap_or(Path::new("."));
+        maintenance::run(&self.db_path, report_dir).await
+    }
+
+    async fn reset_database(self) -> Result<Option<String>, SaveError> {
+        // The open pool still points at the old file, let go of it first
+        self.close_persistence().await;
+        *self.cipher.write().await = None;
+
+        let backup = super::move_to_backup(&self.db_path).await?;
+        Ok(backup.map(|backup| backup.display().to_string()))
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        Box::pin(self.clone().load_state())
+    }
+
+    fn save(&self, changes: SavedChanges) -> StorageFuture<Result<(), SaveError>> {
+        Box::pin(self.clone().save_changes(changes))
+    }
+
+    fn load_read_only(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        Box::pin(self.clone().load_read_only_state())
+    }
+
+    fn load_preferences(&self) -> StorageFuture<Preferences> {
+        let db_path = self.db_path.clone();
+
+        Box::pin(async move {
+            // Read-only and without the key: no migration, no lock, no unlock
+            let Ok(persistence) = SqlitePersistence::open_read_only(&db_path).await else {
+                return Preferences::default();
+            };
+            let preferences = persistence.load_preferences().await;
+            persistence.close().await;
+            preferences.unwrap_or_default()
+        })
+    }
+
+    fn load_history(&self, task_id: Uuid) -> StorageFuture<Result<Vec<HistoryEntry>, LoadError>> {
+        Box::pin(self.clone().history(Some(task_id)))
+    }
+
+    fn load_all_history(&self) -> StorageFuture<Result<Vec<HistoryEntry>, LoadError>> {
+        Box::pin(self.clone().history(None))
+    }
+
+    fn unlock(&self, passphrase: String) -> StorageFuture<Result<(), LoadError>> {
+        Box::pin(self.clone().unlock_with(passphrase))
+    }
+
+    fn supports_encryption(&self) -> bool {
+        true
+    }
+
+    fn is_encrypted(&self) -> bool {
+        self.cipher
+            .try_read()
+            .is_ok_and(|cipher| cipher.is_some())
+    }
+
+    fn set_passphrase(&self, passphrase: Option<String>) -> StorageFuture<Result<(), SaveError>> {
+        Box::pin(self.clone().change_passphrase(passphrase))
+    }
+
+    fn supports_backups(&self) -> bool {
+        true
+    }
+
+    fn back_up(&self, keep: u32) -> StorageFuture<Result<Option<Snapshot>, SaveError>> {
+        Box::pin(self.clone().back_up_database(keep))
+    }
+
+    fn snapshots(&self) -> StorageFuture<Result<Vec<Snapshot>, LoadError>> {
+        let backup_dir = self.backup_dir.clone();
+
+        Box::pin(async move { backups::list(&backup_dir).await })
+    }
+
+    fn restore(&self, snapshot: Snapshot, keep: u32) -> StorageFuture<Result<(), SaveError>> {
+        Box::pin(self.clone().restore_snapshot(snapshot, keep))
+    }
+
+    fn supports_maintenance(&self) -> bool {
+        true
+    }
+
+    fn maintain(&self) -> StorageFuture<Result<MaintenanceReport, SaveError>> {
+        Box::pin(self.clone().maintain_database())
+    }
+
+    fn reset(&self) -> StorageFuture<Result<Option<String>, SaveError>> {
+        Box::pin(self.clone().reset_database())
+    }
+
+    fn changed(&self) -> StorageFuture<Result<bool, LoadError>> {
+        Box::pin(self.clone().check_changed())
+    }
+
+    fn close(&self) -> StorageFuture<()> {
+        let storage = self.clone();
+
+        Box::pin(async move { storage.close_persistence().await })
+    }
+
+    fn location(&self) -> String {
+        self.db_path.display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ChangeSet;
+    use crate::state::interchange::Format;
+    use crate::state::persistence::test_dir;
+    use crate::task::TaskMessage;
+
+    #[tokio::test]
+    async fn toggling_one_of_10k_tasks_writes_one_row() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        let persistence = SqlitePersistence::new(&db_path).await.unwrap();
+
+        let mut tasks: Vec<Task> = (0..10_000)
+            .map(|position| {
+                let mut task = Task::new(format!("Task {}", position));
+                task.set_position(position);
+                task
+            })
+            .collect();
+        persistence
+            .save(
+                SavedChanges {
+                    upserted: tasks.clone(),
+                    ..SavedChanges::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Counts every row written to tasks from here on
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE task_writes (count INTEGER NOT NULL);
+            INSERT INTO task_writes VALUES (0);
+            CREATE TRIGGER count_inserts AFTER INSERT ON tasks BEGIN UPDATE task_writes SET count = count + 1; END;
+            CREATE TRIGGER count_updates AFTER UPDATE ON tasks BEGIN UPDATE task_writes SET count = count + 1; END;
+            CREATE TRIGGER count_deletes AFTER DELETE ON tasks BEGIN UPDATE task_writes SET count = count + 1; END;
+            "#,
+        )
+        .execute(&persistence.pool)
+        .await
+        .unwrap();
+
+        let mut changes = ChangeSet::default();
+        let task = &mut tasks[5_000];
+        let toggled = task.update(TaskMessage::Completed(true));
+        changes.upsert(task.id());
+        for change in toggled {
+            changes.record(HistoryEntry::new(task, change));
+        }
+
+        let (upserted, deleted, history) = changes.take(&tasks);
+        assert_eq!(upserted.len(), 1);
+        assert!(deleted.is_empty());
+        assert_eq!(history.len(), 1);
+
+        persistence
+            .save(
+                SavedChanges {
+                    upserted,
+                    deleted,
+                    history,
+                    ..SavedChanges::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let writes: i64 = sqlx::query_scalar("SELECT count FROM task_writes")
+            .fetch_one(&persistence.pool)
+            .await
+            .unwrap();
+        assert_eq!(writes, 1);
+
+        let state = persistence.load(None).await.unwrap();
+        assert_eq!(state.tasks.len(), 10_000);
+        assert_eq!(state.tasks.iter().filter(|task| task.completed()).count(), 1);
+
+        persistence.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn only_other_processes_writes_are_reported() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        let storage = SqliteStorage::new(db_path.clone(), dir.join("todos.json"), dir.join("backups"));
+        storage.load().await.unwrap();
+
+        for description in ["Buy milk", "Call mom"] {
+            storage
+                .save(SavedChanges {
+                    upserted: vec![Task::new(description.to_string())],
+                    ..SavedChanges::default()
+                })
+                .await
+                .unwrap();
+            assert!(!storage.changed().await.unwrap());
+        }
+
+        // Another process, with a pool of its own
+        let other = SqlitePersistence::new(&db_path).await.unwrap();
+        other
+            .save(
+                SavedChanges {
+                    upserted: vec![Task::new("Pay rent".to_string())],
+                    ..SavedChanges::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        other.close().await;
+
+        // Still reported after a save of our own in between
+        storage.save(SavedChanges::default()).await.unwrap();
+        assert!(storage.changed().await.unwrap());
+        assert!(!storage.changed().await.unwrap());
+
+        assert_eq!(storage.load().await.unwrap().tasks.len(), 3);
+        assert!(!storage.changed().await.unwrap());
+
+        storage.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn a_json_export_keeps_the_history() {
+        let dir = test_dir();
+        let storage = SqliteStorage::new(dir.join("todos.db"), dir.join("todos.json"), dir.join("backups"));
+        storage.load().await.unwrap();
+
+        let milk = Task::new(String::from("Buy milk"));
+        let rent = Task::new(String::from("Pay rent"));
+        let history = vec![
+            HistoryEntry::new(&milk, Change::Created { description: milk.description().to_string() }),
+            HistoryEntry::new(&rent, Change::Created { description: rent.description().to_string() }),
+            HistoryEntry::new(&milk, Change::Completed(true)),
+        ];
+        storage
+            .save(SavedChanges {
+                upserted: vec![milk.clone(), rent],
+                history: history.clone(),
+                ..SavedChanges::default()
+            })
+            .await
+            .unwrap();
+
+        let state = SavedState {
+            history: storage.load_all_history().await.unwrap(),
+            ..storage.load().await.unwrap()
+        };
+        let imported = crate::state::interchange::json::import(&Format::Json.export(&state)).unwrap();
+
+        // In order, to the second the database keeps
+        let changes = |history: &[HistoryEntry]| {
+            history
+                .iter()
+                .map(|entry| (entry.task_id, entry.at.timestamp(), entry.change.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changes(&imported.history), changes(&history));
+        assert_eq!(storage.load_history(milk.id()).await.unwrap().len(), 2);
+
+        storage.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn rows_left_out_of_a_lenient_load_are_counted() {
+        let dir = test_dir();
+        let db_path = dir.join("todos.db");
+        let persistence = SqlitePersistence::new(&db_path).await.unwrap();
+        persistence
+            .save(
+                SavedChanges {
+                    upserted: vec![Task::new(String::from("Buy milk")), Task::new(String::from("Pay rent"))],
+                    ..SavedChanges::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        sqlx::query("UPDATE tasks SET id = 'damaged' WHERE description = 'Pay rent'")
+            .execute(&persistence.pool)
+            .await
+            .unwrap();
+
+        assert!(persistence.load(None).await.is_err());
+        let state = persistence.load_lenient(None).await.unwrap();
+        assert_eq!(state.tasks.len(), 1);
+        assert_eq!(state.skipped, 1);
+
+        persistence.close().await;
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,195 @@
+use super::{SavedChanges, SavedState, LoadError, SaveError, Storage, StorageFuture};
+
+const STATE_KEY: &str = "state";
+
+/// The parts of `web_sys::Storage` the web backend uses. Implemented by
+/// `LocalStorage` in the browser and by `MemoryStore` in tests, so the
+/// backend can be exercised natively.
+pub trait KeyValueStore: std::fmt::Debug + Clone + Send + Sync + 'static {
+    fn get(&self, key: &str) -> Result<Option<String>, String>;
+
+    fn set(&self, key: &str, value: &str) -> Result<(), String>;
+
+    fn remove(&self, key: &str) -> Result<(), String>;
+}
+
+/// The browser's `localStorage`, looked up on every call since `web_sys`
+/// handles cannot be kept in a `Send` type.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    fn storage() -> Result<web_sys::Storage, String> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| String::from("localStorage is not available"))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl KeyValueStore for LocalStorage {
+    fn get(&self, key: &str) -> Result<Option<String>, String> {
+        Self::storage()?.get_item(key).map_err(|e| format!("{:?}", e))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        Self::storage()?.set_item(key, value).map_err(|e| format!("{:?}", e))
+    }
+
+    fn remove(&self, key: &str) -> Result<(), String> {
+        Self::storage()?.remove_item(key).map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// Stores the whole state as JSON under a single key of a `KeyValueStore`,
+/// `localStorage` in the browser.
+#[derive(Debug, Clone, Default)]
+pub struct WebStorage<S> {
+    store: S,
+}
+
+impl<S: KeyValueStore> WebStorage<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    fn read(store: &S) -> Result<SavedState, LoadError> {
+        match store.get(STATE_KEY).map_err(LoadError::file)? {
+            Some(contents) => serde_json::from_str(&contents).map_err(LoadError::format),
+            // First visit, nothing saved yet
+            None => Ok(SavedState::default()),
+        }
+    }
+
+    fn write(store: &S, changes: SavedChanges) -> Result<(), SaveError> {
+        let mut state = Self::read(store).map_err(SaveError::write)?;
+        state.apply(changes);
+
+        let json = serde_json::to_string(&state).map_err(SaveError::write)?;
+        store.set(STATE_KEY, &json).map_err(SaveError::write)
+    }
+
+    /// Copies the state to a timestamped key and removes the original.
+    fn back_up(store: &S) -> Result<Option<String>, SaveError> {
+        let Some(contents) = store.get(STATE_KEY).map_err(SaveError::write)? else {
+            return Ok(None);
+        };
+
+        let key = format!("{}.{}.bak", STATE_KEY, chrono::Utc::now().timestamp_millis());
+        store.set(&key, &contents).map_err(SaveError::write)?;
+        store.remove(STATE_KEY).map_err(SaveError::write)?;
+
+        Ok(Some(key))
+    }
+}
+
+impl<S: KeyValueStore> Storage for WebStorage<S> {
+    fn load(&self) -> StorageFuture<Result<SavedState, LoadError>> {
+        let result = Self::read(&self.store);
+
+        Box::pin(async move { result })
+    }
+
+    fn save(&self, changes: SavedChanges) -> StorageFuture<Result<(), SaveError>> {
+        let result = Self::write(&self.store, changes);
+
+        Box::pin(async move { result })
+    }
+
+    fn reset(&self) -> StorageFuture<Result<Option<String>, SaveError>> {
+        let result = Self::back_up(&self.store);
+
+        Box::pin(async move { result })
+    }
+
+    fn location(&self) -> String {
+        String::from("localStorage")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Task;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// An in-memory stand-in for `localStorage`.
+    #[derive(Debug, Clone, Default)]
+    pub struct MemoryStore {
+        items: Arc<Mutex<HashMap<String, String>>>,
+    }
+
+    impl KeyValueStore for MemoryStore {
+        fn get(&self, key: &str) -> Result<Option<String>, String> {
+            let items = self.items.lock().map_err(|e| e.to_string())?;
+            Ok(items.get(key).cloned())
+        }
+
+        fn set(&self, key: &str, value: &str) -> Result<(), String> {
+            let mut items = self.items.lock().map_err(|e| e.to_string())?;
+            items.insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn remove(&self, key: &str) -> Result<(), String> {
+            let mut items = self.items.lock().map_err(|e| e.to_string())?;
+            items.remove(key);
+            Ok(())
+        }
+    }
+
+    fn descriptions(state: &SavedState) -> Vec<&str> {
+        state.tasks.iter().map(Task::description).collect()
+    }
+
+    #[tokio::test]
+    async fn saves_are_applied_to_the_stored_state() {
+        let store = MemoryStore::default();
+        let storage = WebStorage::new(store.clone());
+        assert!(storage.load().await.unwrap().tasks.is_empty());
+
+        let milk = Task::new(String::from("Buy milk"));
+        let mom = Task::new(String::from("Call mom"));
+        storage
+            .save(SavedChanges {
+                input_value: String::from("Pay"),
+                upserted: vec![milk.clone(), mom.clone()],
+                ..SavedChanges::default()
+            })
+            .await
+            .unwrap();
+        storage
+            .save(SavedChanges {
+                input_value: String::from("Pay rent"),
+                deleted: vec![milk.id()],
+                ..SavedChanges::default()
+            })
+            .await
+            .unwrap();
+
+        // A fresh backend on the same store, as after reloading the page
+        let state = WebStorage::new(store).load().await.unwrap();
+        assert_eq!(state.input_value, "Pay rent");
+        assert_eq!(descriptions(&state), ["Call mom"]);
+        assert_eq!(state.tasks[0].id(), mom.id());
+    }
+
+    #[tokio::test]
+    async fn reset_keeps_a_copy_of_unreadable_data() {
+        let store = MemoryStore::default();
+        store.set(STATE_KEY, "{ not json").unwrap();
+        let storage = WebStorage::new(store.clone());
+        assert!(matches!(storage.load().await, Err(LoadError::Format(_))));
+
+        let key = storage.reset().await.unwrap().unwrap();
+        assert_eq!(store.get(&key).unwrap().as_deref(), Some("{ not json"));
+        assert!(store.get(STATE_KEY).unwrap().is_none());
+        assert!(storage.load().await.unwrap().tasks.is_empty());
+
+        // Nothing left to back up
+        assert!(storage.reset().await.unwrap().is_none());
+    }
+}
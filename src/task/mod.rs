@@ -1,30 +1,49 @@
 pub mod view;
 
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use iced::Element;
 
+use crate::state::history::Change;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     #[serde(default = "Uuid::new_v4")]
     id: Uuid,
     description: String,
     completed: bool,
+    /// Sort key for manual ordering. Only relative order matters, so gaps left
+    /// by deleted tasks are fine.
+    #[serde(default)]
+    position: i64,
+    /// When the task was moved to the trash, if it is there.
+    #[serde(default)]
+    deleted_at: Option<DateTime<Utc>>,
+    /// `None` for tasks imported without a creation date.
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    /// When the task was last marked done. Tasks completed before this was
+    /// recorded have none.
+    #[serde(default)]
+    completed_at: Option<DateTime<Utc>>,
+    /// What other apps keep on the task and this one has no use for, so that
+    /// exporting back to them loses nothing. Keyed by format.
+    #[serde(default)]
+    extra: BTreeMap<String, String>,
 
     #[serde(skip)]
     state: TaskState,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum TaskState {
+    #[default]
     Idle,
-    Editing,
-}
-
-impl Default for TaskState {
-    fn default() -> Self {
-        Self::Idle
-    }
+    /// Keeps the description from before the edit, to record a rename.
+    Editing { original: String },
 }
 
 #[derive(Debug, Clone)]
@@ -33,7 +52,27 @@ pub enum TaskMessage {
     Edit,
     DescriptionEdited(String),
     FinishEdition,
+    /// Moves the task to the trash.
     Delete,
+    Restore,
+    /// Removes the task from the trash for good.
+    DeleteForever,
+    ShowHistory,
+}
+
+impl TaskMessage {
+    /// Whether the message changes data that is persisted for the task.
+    pub fn is_mutation(&self) -> bool {
+        match self {
+            TaskMessage::Completed(_)
+            | TaskMessage::DescriptionEdited(_)
+            | TaskMessage::FinishEdition
+            | TaskMessage::Delete
+            | TaskMessage::Restore
+            | TaskMessage::DeleteForever => true,
+            TaskMessage::Edit | TaskMessage::ShowHistory => false,
+        }
+    }
 }
 
 impl Task {
@@ -42,15 +81,33 @@ impl Task {
             id: Uuid::new_v4(),
             description,
             completed: false,
+            position: 0,
+            deleted_at: None,
+            created_at: Some(Utc::now()),
+            completed_at: None,
+            extra: BTreeMap::new(),
             state: TaskState::Idle,
         }
     }
 
-    pub fn from_parts(id: Uuid, description: String, completed: bool) -> Self {
+    pub fn from_parts(
+        id: Uuid,
+        description: String,
+        completed: bool,
+        position: i64,
+        deleted_at: Option<DateTime<Utc>>,
+        created_at: Option<DateTime<Utc>>,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> Self {
         Task {
             id,
             description,
             completed,
+            position,
+            deleted_at,
+            created_at,
+            completed_at,
+            extra: BTreeMap::new(),
             state: TaskState::Idle,
         }
     }
@@ -63,30 +120,126 @@ impl Task {
         self.completed
     }
 
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: i64) {
+        self.position = position;
+    }
+
+    /// Takes everything from `other`, the same task as changed elsewhere,
+    /// except its place in the list and an edit in progress.
+    pub fn replace_content(&mut self, other: Task) {
+        *self = Task {
+            position: self.position,
+            state: std::mem::take(&mut self.state),
+            ..other
+        };
+    }
+
+    pub fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deleted_at
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    pub fn completed_at(&self) -> Option<DateTime<Utc>> {
+        self.completed_at
+    }
+
+    pub fn extra(&self) -> &BTreeMap<String, String> {
+        &self.extra
+    }
+
+    pub fn extra_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.extra
+    }
+
     pub fn text_input_id(i: usize) -> iced::widget::text_input::Id {
         iced::widget::text_input::Id::new(format!("task-{i}"))
     }
 
-    pub fn update(&mut self, message: TaskMessage) {
+    /// Applies the message, returning the changes to record in the history.
+    pub fn update(&mut self, message: TaskMessage) -> Vec<Change> {
         match message {
             TaskMessage::Completed(completed) => {
-                self.completed = completed;
-                if completed {
-                    crate::audio::play_done_sound();
+                if completed == self.completed {
+                    return Vec::new();
                 }
+                self.completed = completed;
+                self.completed_at = completed.then(Utc::now);
+                vec![Change::Completed(completed)]
             }
             TaskMessage::Edit => {
-                self.state = TaskState::Editing;
+                if !matches!(self.state, TaskState::Editing { .. }) {
+                    self.state = TaskState::Editing {
+                        original: self.description.clone(),
+                    };
+                }
+                Vec::new()
             }
             TaskMessage::DescriptionEdited(new_description) => {
                 self.description = new_description;
+                Vec::new()
             }
             TaskMessage::FinishEdition => {
-                if !self.description.is_empty() {
-                    self.state = TaskState::Idle;
+                if self.description.is_empty() {
+                    return Vec::new();
+                }
+                self.finish_editing().into_iter().collect()
+            }
+            TaskMessage::Delete => {
+                let mut changes: Vec<Change> = self.finish_editing().into_iter().collect();
+                self.deleted_at = Some(Utc::now());
+                changes.push(Change::Deleted);
+                changes
+            }
+            TaskMessage::Restore => {
+                if self.deleted_at.take().is_none() {
+                    return Vec::new();
                 }
+                vec![Change::Restored]
+            }
+            TaskMessage::DeleteForever | TaskMessage::ShowHistory => Vec::new(),
+        }
+    }
+
+    /// Sets the description back to an earlier version, returning the rename
+    /// if it differs from the current one.
+    pub fn revert_description(&mut self, description: String) -> Option<Change> {
+        if description == self.description {
+            return None;
+        }
+
+        // An edit in progress continues from the reverted description
+        if let TaskState::Editing { original } = &mut self.state {
+            original.clone_from(&description);
+        }
+
+        let from = std::mem::replace(&mut self.description, description);
+        Some(Change::Renamed {
+            from,
+            to: self.description.clone(),
+        })
+    }
+
+    /// Leaves edit mode, returning the rename if the description changed.
+    fn finish_editing(&mut self) -> Option<Change> {
+        match std::mem::take(&mut self.state) {
+            TaskState::Editing { original } if original != self.description => {
+                Some(Change::Renamed {
+                    from: original,
+                    to: self.description.clone(),
+                })
             }
-            TaskMessage::Delete => {}
+            _ => None,
         }
     }
 
@@ -1,4 +1,4 @@
-use iced::widget::{button, checkbox, container, row, text_input};
+use iced::widget::{button, checkbox, container, row, text, text_input};
 use iced::{Center, Element, Fill, Theme};
 
 use crate::i18n::LANGUAGE_LOADER;
@@ -9,8 +9,9 @@ use super::{Task, TaskMessage, TaskState};
 
 pub fn task_view(task: &Task, index: usize) -> Element<'_, TaskMessage> {
     let content = match task.state() {
+        _ if task.is_deleted() => trashed_view(task),
         TaskState::Idle => idle_view(task),
-        TaskState::Editing => editing_view(task, index),
+        TaskState::Editing { .. } => editing_view(task, index),
     };
 
     container(content)
@@ -36,6 +37,7 @@ fn idle_view(task: &Task) -> Element<'_, TaskMessage> {
 
     row![
         checkbox,
+        history_button(),
         button(edit_icon())
             .on_press(TaskMessage::Edit)
             .padding(4)
@@ -46,6 +48,34 @@ fn idle_view(task: &Task) -> Element<'_, TaskMessage> {
     .into()
 }
 
+fn trashed_view(task: &Task) -> Element<'_, TaskMessage> {
+    row![
+        text(task.description())
+            .width(Fill)
+            .shaping(text::Shaping::Advanced),
+        history_button(),
+        button(text(fl!(LANGUAGE_LOADER, "task-restore")).size(14))
+            .on_press(TaskMessage::Restore)
+            .padding(4)
+            .style(button::text),
+        button(delete_icon())
+            .on_press(TaskMessage::DeleteForever)
+            .padding(4)
+            .style(button::danger),
+    ]
+    .spacing(20)
+    .align_y(Center)
+    .into()
+}
+
+fn history_button<'a>() -> Element<'a, TaskMessage> {
+    button(text(fl!(LANGUAGE_LOADER, "task-history")).size(14))
+        .on_press(TaskMessage::ShowHistory)
+        .padding(4)
+        .style(button::text)
+        .into()
+}
+
 fn editing_view(task: &Task, index: usize) -> Element<'_, TaskMessage> {
     let text_input = text_input(
         &fl!(LANGUAGE_LOADER, "describe-task-placeholder"),
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use iced::widget::{button, column, container, pick_list, row, scrollable, text};
+use iced::{Center, Element, Fill};
+
+use crate::app::Message;
+use crate::i18n::{
+    translate, translate_backup_generations, translate_backup_interval, translate_backup_task_count,
+    Language,
+};
+use crate::state::persistence::Snapshot;
+use crate::state::{BackupDialog, State};
+use crate::ui::maintenance::view_maintenance_status;
+use crate::ui::styles::subtle;
+
+const INTERVAL_HOURS: [u32; 5] = [1, 3, 6, 12, 24];
+const GENERATIONS: [u32; 5] = [3, 5, 10, 20, 50];
+
+/// An entry of the interval picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Interval {
+    hours: u32,
+    language: Language,
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&translate_backup_interval(self.hours, self.language))
+    }
+}
+
+/// An entry of the generations picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Generations {
+    count: u32,
+    language: Language,
+}
+
+impl fmt::Display for Generations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&translate_backup_generations(self.count, self.language))
+    }
+}
+
+/// Lists the backups to restore, and how often they are taken.
+pub fn view_backup_dialog<'a>(dialog: &BackupDialog, state: &State) -> Element<'a, Message> {
+    let language = state.language;
+
+    let intervals: Vec<Interval> = INTERVAL_HOURS
+        .into_iter()
+        .map(|hours| Interval { hours, language })
+        .collect();
+    let generations: Vec<Generations> = GENERATIONS
+        .into_iter()
+        .map(|count| Generations { count, language })
+        .collect();
+
+    let settings = row![
+        pick_list(
+            intervals,
+            Some(Interval {
+                hours: state.backup_interval_hours,
+                language,
+            }),
+            |interval| Message::BackupIntervalChanged(interval.hours),
+        )
+        .text_size(14),
+        pick_list(
+            generations,
+            Some(Generations {
+                count: state.backup_generations,
+                language,
+            }),
+            |generations| Message::BackupGenerationsChanged(generations.count),
+        )
+        .text_size(14),
+    ]
+    .spacing(10);
+
+    let action = |key, message: Message, style: fn(&iced::Theme, button::Status) -> button::Style| {
+        button(text(translate(key, language)).size(14))
+            .on_press_maybe((!dialog.working).then_some(message))
+            .padding(iced::Padding {
+                top: 5.0,
+                left: 12.0,
+                bottom: 5.0,
+                right: 12.0,
+            })
+            .style(style)
+    };
+
+    let mut actions = row![action("backup-now", Message::BackUpNow, button::primary)].spacing(10);
+
+    if state.storage.supports_maintenance() && state.read_only.is_none() {
+        actions = actions.push(action("maintenance-button", Message::Maintain, button::secondary));
+    }
+
+    let actions = actions.push(action("backup-close", Message::BackupDialogToggled, button::secondary));
+
+    let list: Element<'a, Message> = match &dialog.snapshots {
+        None => text(translate("backup-loading", language))
+            .size(14)
+            .style(subtle)
+            .into(),
+        Some(snapshots) if snapshots.is_empty() => text(translate("backup-none", language))
+            .size(14)
+            .style(subtle)
+            .into(),
+        Some(snapshots) => scrollable(
+            column(
+                snapshots
+                    .iter()
+                    .map(|snapshot| view_snapshot(snapshot, dialog.working, language)),
+            )
+            .spacing(6),
+        )
+        .into(),
+    };
+
+    let message: Element<'a, Message> = match (dialog.error, &dialog.maintenance) {
+        (Some(key), _) => text(translate(key, language)).size(12).style(subtle).into(),
+        (None, Some(maintenance)) => view_maintenance_status(maintenance, language),
+        (None, None) => text(translate("backup-hint", language)).size(12).style(subtle).into(),
+    };
+
+    let content = column![
+        text(translate("backup-title", language)).size(18),
+        settings,
+        container(list).max_height(200),
+        actions,
+        message,
+    ]
+    .spacing(10);
+
+    container(content)
+        .padding(12)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn view_snapshot<'a>(snapshot: &Snapshot, working: bool, language: Language) -> Element<'a, Message> {
+    let created_at = snapshot
+        .created_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+
+    let task_count = match snapshot.task_count {
+        Some(count) => translate_backup_task_count(count, language),
+        None => translate("backup-unreadable", language),
+    };
+
+    row![
+        text(created_at).size(14).width(Fill),
+        text(task_count).size(12).style(subtle),
+        button(text(translate("backup-restore", language)).size(12))
+            .on_press_maybe((!working).then(|| Message::RestoreSnapshot(snapshot.clone())))
+            .padding(4)
+            .style(button::text),
+    ]
+    .spacing(10)
+    .align_y(Center)
+    .into()
+}
@@ -1,16 +1,17 @@
 use iced::widget::{button, row, text};
 use iced::{Center, Element, Fill};
 
-use crate::i18n::{translate, translate_tasks_left, Language};
-use crate::state::Filter;
+use crate::i18n::{translate, translate_synced_at, translate_tasks_left, Language};
+use crate::state::{Filter, SyncStatus};
 use crate::task::Task;
 
 pub fn view_controls<'a>(
     tasks: &[Task],
     current_filter: Filter,
+    sync: Option<&SyncStatus>,
     language: Language,
 ) -> Element<'a, crate::app::Message> {
-    let tasks_left = tasks.iter().filter(|task| !task.completed()).count();
+    let tasks_left = tasks.iter().filter(|task| Filter::Active.matches(task)).count();
 
     let filter_button = |key, filter, current_filter| {
         let label = text(translate(key, language));
@@ -31,29 +32,69 @@ pub fn view_controls<'a>(
             })
     };
 
-    row![
-        text(translate_tasks_left(tasks_left, language)).width(Fill),
-        row![
-            filter_button("filter-all", Filter::All, current_filter),
-            filter_button("filter-active", Filter::Active, current_filter),
-            filter_button("filter-completed", Filter::Completed, current_filter),
-            button(text(translate("language-toggle", language)).size(12))
-                .on_press(crate::app::Message::LanguageChanged(match language {
-                    Language::Korean => Language::English,
-                    Language::English => Language::Korean,
-                }))
-                .padding(iced::Padding {
-                    top: 5.0,
-                    left: 8.0,
-                    bottom: 5.0,
-                    right: 8.0,
-                })
-                .style(button::text),
-        ]
-        .spacing(10)
-        .align_y(Center)
-    ]
-    .spacing(20)
-    .align_y(Center)
-    .into()
+    // Opens the sync dialog, where syncing is set up or started by hand
+    let sync_button = sync.map(|status| {
+        let label = match status {
+            SyncStatus::Idle => translate("sync-button", language),
+            SyncStatus::Syncing => translate("sync-syncing", language),
+            SyncStatus::Synced(at) => translate_synced_at(&at.format("%H:%M").to_string(), language),
+            SyncStatus::Failed(_) => translate("sync-failed", language),
+        };
+
+        button(text(label).size(12))
+            .on_press(crate::app::Message::SyncDialogToggled)
+            .padding(iced::Padding {
+                top: 5.0,
+                left: 8.0,
+                bottom: 5.0,
+                right: 8.0,
+            })
+            .style(if matches!(status, SyncStatus::Failed(_)) {
+                button::danger
+            } else {
+                button::text
+            })
+    });
+
+    let mut controls = row![text(translate_tasks_left(tasks_left, language)).width(Fill)]
+        .spacing(20)
+        .align_y(Center);
+
+    if let Some(sync_button) = sync_button {
+        controls = controls.push(sync_button);
+    }
+
+    controls
+        .push(
+            row![
+                filter_button("filter-all", Filter::All, current_filter),
+                filter_button("filter-active", Filter::Active, current_filter),
+                filter_button("filter-completed", Filter::Completed, current_filter),
+                filter_button("filter-trash", Filter::Trash, current_filter),
+                button(text(translate("copy-markdown", language)).size(12))
+                    .on_press(crate::app::Message::CopyAsMarkdown)
+                    .padding(iced::Padding {
+                        top: 5.0,
+                        left: 8.0,
+                        bottom: 5.0,
+                        right: 8.0,
+                    })
+                    .style(button::text),
+                button(text(translate("language-toggle", language)).size(12))
+                    .on_press(crate::app::Message::LanguageChanged(match language {
+                        Language::Korean => Language::English,
+                        Language::English => Language::Korean,
+                    }))
+                    .padding(iced::Padding {
+                        top: 5.0,
+                        left: 8.0,
+                        bottom: 5.0,
+                        right: 8.0,
+                    })
+                    .style(button::text),
+            ]
+            .spacing(10)
+            .align_y(Center),
+        )
+        .into()
 }
\ No newline at end of file
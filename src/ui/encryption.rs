@@ -0,0 +1,121 @@
+use iced::widget::{button, center, column, container, row, text, text_input};
+use iced::{Center, Element, Fill};
+
+use crate::app::Message;
+use crate::i18n::{translate, translate_data_location, Language};
+use crate::state::PassphraseDialog;
+use crate::ui::styles::subtle;
+
+/// Asks for the passphrase of encrypted data before anything is loaded.
+pub fn view_unlock<'a>(
+    passphrase: &str,
+    wrong_passphrase: bool,
+    unlocking: bool,
+    location: String,
+    language: Language,
+) -> Element<'a, Message> {
+    let mut input = text_input(&translate("unlock-placeholder", language), passphrase)
+        .id("passphrase")
+        .secure(true)
+        .padding(10);
+    let mut unlock = button(text(translate("unlock-button", language)).width(Fill).align_x(Center))
+        .width(Fill)
+        .padding(8)
+        .style(button::primary);
+
+    if !unlocking {
+        input = input
+            .on_input(Message::PassphraseChanged)
+            .on_submit(Message::Unlock);
+        unlock = unlock.on_press(Message::Unlock);
+    }
+
+    let hint = if unlocking {
+        translate("unlock-working", language)
+    } else if wrong_passphrase {
+        translate("unlock-wrong", language)
+    } else {
+        String::new()
+    };
+
+    let content = column![
+        text(translate("unlock-title", language)).size(30),
+        text(translate_data_location(&location, language))
+            .size(12)
+            .style(subtle),
+        input,
+        unlock,
+        text(hint).size(12).style(subtle),
+    ]
+    .spacing(20)
+    .max_width(400);
+
+    center(content).padding(16).into()
+}
+
+/// Encrypts the data, changes its passphrase or decrypts it again.
+pub fn view_passphrase_dialog<'a>(
+    dialog: &PassphraseDialog,
+    encrypted: bool,
+    language: Language,
+) -> Element<'a, Message> {
+    let (title, submit) = if encrypted {
+        ("encryption-title-change", "encryption-change")
+    } else {
+        ("encryption-title-encrypt", "encryption-encrypt")
+    };
+
+    let input = |placeholder, value: &str, on_input: fn(String) -> Message| {
+        let input = text_input(&translate(placeholder, language), value)
+            .secure(true)
+            .padding(8);
+
+        if dialog.working {
+            input
+        } else {
+            input.on_input(on_input).on_submit(Message::SetPassphrase)
+        }
+    };
+
+    let action = |key, message: Message, style: fn(&iced::Theme, button::Status) -> button::Style| {
+        button(text(translate(key, language)).size(14))
+            .on_press_maybe((!dialog.working).then_some(message))
+            .padding(iced::Padding {
+                top: 5.0,
+                left: 12.0,
+                bottom: 5.0,
+                right: 12.0,
+            })
+            .style(style)
+    };
+
+    let mut actions = row![action(submit, Message::SetPassphrase, button::primary)].spacing(10);
+    if encrypted {
+        actions = actions.push(action("encryption-remove", Message::RemovePassphrase, button::danger));
+    }
+    actions = actions.push(action("encryption-cancel", Message::PassphraseDialogToggled, button::secondary));
+
+    let message = match dialog.error {
+        Some(key) => translate(key, language),
+        None => translate("encryption-hint", language),
+    };
+
+    let content = column![
+        text(translate(title, language)).size(18),
+        input("passphrase-new", &dialog.passphrase, Message::NewPassphraseChanged),
+        input(
+            "passphrase-confirm",
+            &dialog.confirmation,
+            Message::PassphraseConfirmationChanged
+        ),
+        actions,
+        text(message).size(12).style(subtle),
+    ]
+    .spacing(10);
+
+    container(content)
+        .padding(12)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
@@ -0,0 +1,98 @@
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::{Center, Element, Fill};
+
+use crate::app::Message;
+use crate::i18n::{translate, translate_history_created, translate_history_renamed, Language};
+use crate::state::history::{Change, HistoryEntry, HistoryPanel};
+use crate::task::Task;
+use crate::ui::styles::subtle;
+
+/// The timeline of `task`, with a way back to each earlier description.
+pub fn view_history_panel<'a>(
+    panel: &HistoryPanel,
+    task: &Task,
+    language: Language,
+) -> Element<'a, Message> {
+    let header = row![
+        text(translate("history-title", language)).size(18).width(Fill),
+        button(text(translate("history-close", language)).size(14))
+            .on_press(Message::HistoryClosed)
+            .padding(4)
+            .style(button::text),
+    ]
+    .align_y(Center);
+
+    let body: Element<'a, Message> = match &panel.entries {
+        _ if panel.failed => text(translate("history-failed", language))
+            .size(14)
+            .style(subtle)
+            .into(),
+        None => text(translate("history-loading", language))
+            .size(14)
+            .style(subtle)
+            .into(),
+        Some(entries) if entries.is_empty() => text(translate("history-empty", language))
+            .size(14)
+            .style(subtle)
+            .into(),
+        Some(entries) => scrollable(
+            column(
+                entries
+                    .iter()
+                    // Newest first
+                    .rev()
+                    .map(|entry| view_entry(entry, task, language)),
+            )
+            .spacing(6),
+        )
+        .into(),
+    };
+
+    container(column![header, container(body).max_height(240)].spacing(10))
+        .padding(12)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn view_entry<'a>(entry: &HistoryEntry, task: &Task, language: Language) -> Element<'a, Message> {
+    let description = match &entry.change {
+        Change::Created { description } => translate_history_created(description, language),
+        Change::Renamed { from, to } => translate_history_renamed(from, to, language),
+        Change::Completed(true) => translate("history-completed", language),
+        Change::Completed(false) => translate("history-uncompleted", language),
+        Change::Deleted => translate("history-deleted", language),
+        Change::Restored => translate("history-restored", language),
+    };
+
+    let time = entry
+        .at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+
+    let mut line = row![
+        text(time).size(12).style(subtle),
+        text(description)
+            .size(14)
+            .width(Fill)
+            .shaping(text::Shaping::Advanced),
+    ]
+    .spacing(10)
+    .align_y(Center);
+
+    if let Some(version) = entry
+        .change
+        .description()
+        .filter(|version| *version != task.description())
+    {
+        line = line.push(
+            button(text(translate("history-revert", language)).size(12))
+                .on_press(Message::DescriptionReverted(task.id(), version.to_string()))
+                .padding(4)
+                .style(button::text),
+        );
+    }
+
+    line.into()
+}
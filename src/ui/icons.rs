@@ -21,4 +21,10 @@ pub fn plus_icon() -> Text<'static> {
     text("+").size(20).style(|theme: &iced::Theme| text::Style {
         color: Some(theme.extended_palette().background.strong.text),
     })
+}
+
+pub fn drag_handle_icon() -> Text<'static> {
+    text("≡").size(16).width(20).align_x(Center).style(|theme: &iced::Theme| text::Style {
+        color: Some(theme.extended_palette().background.strong.color),
+    })
 }
\ No newline at end of file
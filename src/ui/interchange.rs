@@ -0,0 +1,106 @@
+use iced::widget::{button, column, container, pick_list, row, text, text_input};
+use iced::{Center, Element, Fill};
+
+use crate::app::Message;
+use crate::i18n::{translate, translate_exported, translate_import_summary, Language};
+use crate::state::interchange::{Format, Mode};
+use crate::state::{ImportPreview, InterchangeDialog, State};
+use crate::ui::styles::subtle;
+
+const PADDING: iced::Padding = iced::Padding {
+    top: 5.0,
+    left: 12.0,
+    bottom: 5.0,
+    right: 12.0,
+};
+
+/// Exports the tasks to a file, or reads one and shows what importing it
+/// would do before anything changes.
+pub fn view_interchange_dialog<'a>(dialog: &InterchangeDialog, state: &State) -> Element<'a, Message> {
+    let language = state.language;
+
+    let action = |key, message: Message, style: fn(&iced::Theme, button::Status) -> button::Style| {
+        button(text(translate(key, language)).size(14))
+            .on_press_maybe((!dialog.working).then_some(message))
+            .padding(PADDING)
+            .style(style)
+    };
+
+    let path = text_input(&translate("interchange-path-placeholder", language), &dialog.path)
+        .on_input(Message::InterchangePathChanged)
+        .on_submit(Message::PreviewImport)
+        .size(14)
+        .padding(6);
+
+    let actions = row![
+        pick_list(Format::ALL, Some(dialog.format), Message::ExportFormatChanged).text_size(14),
+        action("interchange-export", Message::Export(dialog.format), button::secondary),
+        action("interchange-preview", Message::PreviewImport, button::secondary),
+        action("interchange-close", Message::InterchangeDialogToggled, button::text),
+    ]
+    .spacing(10)
+    .align_y(Center);
+
+    let mut content = column![
+        text(translate("interchange-title", language)).size(18),
+        path,
+        actions,
+    ]
+    .spacing(10);
+
+    if let Some(preview) = &dialog.preview {
+        content = content.push(view_preview(preview, dialog.mode, state.read_only.is_none(), language));
+    }
+
+    let message = match (dialog.error, &dialog.exported) {
+        (Some(key), _) => translate(key, language),
+        (None, Some(path)) => translate_exported(&path.display().to_string(), language),
+        (None, None) => translate("interchange-hint", language),
+    };
+
+    container(content.push(text(message).size(12).style(subtle)))
+        .padding(12)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn view_preview<'a>(preview: &ImportPreview, mode: Mode, writable: bool, language: Language) -> Element<'a, Message> {
+    let mode_button = |key, option: Mode| {
+        button(text(translate(key, language)).size(12))
+            .on_press(Message::ImportModeChanged(option))
+            .padding(PADDING)
+            .style(if option == mode { button::primary } else { button::text })
+    };
+
+    let merge = &preview.merge;
+    let summary = translate_import_summary(merge.added, merge.updated, merge.unchanged, merge.removed.len(), language);
+    let hint = match mode {
+        Mode::Merge => "interchange-merge-hint",
+        Mode::Replace => "interchange-replace-hint",
+    };
+
+    column![
+        row![
+            mode_button("interchange-mode-merge", Mode::Merge),
+            mode_button("interchange-mode-replace", Mode::Replace),
+            text(summary).size(14).width(Fill),
+        ]
+        .spacing(10)
+        .align_y(Center),
+        text(translate(hint, language)).size(12).style(subtle),
+        row![
+            button(text(translate("interchange-import", language)).size(14))
+                .on_press_maybe(writable.then_some(Message::ConfirmImport))
+                .padding(PADDING)
+                .style(button::primary),
+            button(text(translate("interchange-cancel", language)).size(14))
+                .on_press(Message::ImportCancelled)
+                .padding(PADDING)
+                .style(button::secondary),
+        ]
+        .spacing(10),
+    ]
+    .spacing(8)
+    .into()
+}
@@ -0,0 +1,33 @@
+use iced::widget::{column, text};
+use iced::Element;
+
+use crate::app::Message;
+use crate::i18n::{translate, translate_maintenance_repaired, translate_maintenance_report, Language};
+use crate::state::Maintenance;
+use crate::ui::styles::subtle;
+
+/// What a maintenance run is doing or found, with where its report went.
+pub fn view_maintenance_status<'a>(maintenance: &Maintenance, language: Language) -> Element<'a, Message> {
+    let summary = match maintenance {
+        Maintenance::Running => translate("maintenance-running", language),
+        Maintenance::Failed => translate("maintenance-failed", language),
+        Maintenance::Done(report) => match &report.salvage {
+            None => translate("maintenance-healthy", language),
+            Some(salvage) => translate_maintenance_repaired(salvage.tasks, salvage.unreadable, language),
+        },
+    };
+
+    let mut status = column![text(summary).size(12).style(subtle)].spacing(4);
+
+    if let Maintenance::Done(report) = maintenance
+        && let Some(path) = &report.report_path
+    {
+        status = status.push(
+            text(translate_maintenance_report(&path.display().to_string(), language))
+                .size(12)
+                .style(subtle),
+        );
+    }
+
+    status.into()
+}
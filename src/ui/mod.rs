@@ -1,3 +1,12 @@
+pub mod backups;
 pub mod controls;
+pub mod encryption;
+pub mod history;
 pub mod icons;
-pub mod styles;
\ No newline at end of file
+pub mod interchange;
+pub mod maintenance;
+pub mod recovery;
+pub mod status;
+pub mod styles;
+pub mod sync;
+pub mod trash;
\ No newline at end of file
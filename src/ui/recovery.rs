@@ -0,0 +1,85 @@
+use iced::widget::{button, center, column, text};
+use iced::{Center, Element, Fill, Theme};
+
+use crate::i18n::{translate, translate_data_location, translate_unsupported_version, Language};
+use crate::state::persistence::LoadError;
+use crate::state::Maintenance;
+use crate::ui::maintenance::view_maintenance_status;
+use crate::ui::styles::subtle;
+
+pub fn view_recovery<'a>(
+    error: &LoadError,
+    location: String,
+    reset_failed: bool,
+    already_running: bool,
+    can_repair: bool,
+    maintenance: Option<&Maintenance>,
+    language: Language,
+) -> Element<'a, crate::app::Message> {
+    let (reason, cause) = match error {
+        LoadError::File(cause) => (translate("recovery-file", language), Some(cause.clone())),
+        LoadError::Format(cause) => (translate("recovery-format", language), Some(cause.clone())),
+        LoadError::UnsupportedVersion { found, supported } => (
+            translate_unsupported_version(*found, *supported, language),
+            None,
+        ),
+        LoadError::Locked | LoadError::WrongPassphrase => (translate("recovery-locked", language), None),
+    };
+
+    // Nothing else may touch the data while it is being repaired
+    let repairing = matches!(maintenance, Some(Maintenance::Running));
+
+    let action = |key, message, style: fn(&Theme, button::Status) -> button::Style| {
+        button(text(translate(key, language)).width(Fill).align_x(Center))
+            .on_press_maybe((!repairing).then_some(message))
+            .width(Fill)
+            .padding(iced::Padding {
+                top: 8.0,
+                left: 16.0,
+                bottom: 8.0,
+                right: 16.0,
+            })
+            .style(style)
+    };
+
+    let hint: Element<'a, crate::app::Message> = if already_running {
+        text(translate("recovery-already-running", language)).size(12).style(subtle).into()
+    } else if let Some(maintenance) = maintenance {
+        view_maintenance_status(maintenance, language)
+    } else if reset_failed {
+        text(translate("recovery-reset-failed", language)).size(12).style(subtle).into()
+    } else {
+        text(translate("recovery-backup-hint", language)).size(12).style(subtle).into()
+    };
+
+    let mut actions =
+        column![action("recovery-retry", crate::app::Message::RetryLoad, button::primary)];
+
+    // Only the instance that owns the data may replace it
+    if !already_running {
+        if can_repair {
+            actions = actions.push(action(
+                "maintenance-button",
+                crate::app::Message::Maintain,
+                button::secondary,
+            ));
+        }
+        actions = actions
+            .push(action("recovery-read-only", crate::app::Message::OpenReadOnly, button::secondary))
+            .push(action("recovery-start-fresh", crate::app::Message::StartFresh, button::danger));
+    }
+
+    let content = column![
+        text(translate("recovery-title", language)).size(30),
+        text(reason),
+        text(cause.unwrap_or_default()).size(12).style(subtle),
+        text(translate_data_location(&location, language))
+            .size(12)
+            .style(subtle),
+        actions.push(hint).spacing(10),
+    ]
+    .spacing(20)
+    .max_width(400);
+
+    center(content).padding(16).into()
+}
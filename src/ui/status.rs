@@ -0,0 +1,128 @@
+use iced::widget::{button, container, row, text};
+use iced::{Center, Element, Fill};
+
+use crate::i18n::{
+    translate, translate_data_location, translate_save_failed, translate_saved_at, Language,
+};
+use crate::state::{SaveFailure, State};
+use crate::ui::styles::subtle;
+
+pub fn view_status_bar<'a>(state: &State) -> Element<'a, crate::app::Message> {
+    let status = if state.saving {
+        translate("status-saving", state.language)
+    } else if let Some(failure) = &state.save_failure {
+        translate_save_failed(failure.attempts, state.language)
+    } else if state.dirty {
+        translate("status-pending", state.language)
+    } else if let Some(last_saved) = state.last_saved {
+        translate_saved_at(&last_saved.format("%H:%M:%S").to_string(), state.language)
+    } else {
+        String::new()
+    };
+
+    let mut status_bar = row![text(status).size(12).style(subtle).width(Fill)]
+        .spacing(10)
+        .align_y(Center);
+
+    if state.storage.supports_encryption() && state.read_only.is_none() {
+        let key = if state.encrypted {
+            "encryption-button-encrypted"
+        } else {
+            "encryption-button"
+        };
+
+        status_bar = status_bar.push(
+            button(text(translate(key, state.language)).size(12))
+                .on_press(crate::app::Message::PassphraseDialogToggled)
+                .padding(0)
+                .style(button::text),
+        );
+    }
+
+    if state.storage.supports_backups() && state.read_only.is_none() {
+        status_bar = status_bar.push(
+            button(text(translate("backup-button", state.language)).size(12))
+                .on_press(crate::app::Message::BackupDialogToggled)
+                .padding(0)
+                .style(button::text),
+        );
+    }
+
+    // There are no files to read or write in the browser
+    if cfg!(not(target_arch = "wasm32")) {
+        status_bar = status_bar.push(
+            button(text(translate("interchange-button", state.language)).size(12))
+                .on_press(crate::app::Message::InterchangeDialogToggled)
+                .padding(0)
+                .style(button::text),
+        );
+    }
+
+    status_bar
+        .push(
+            text(translate_data_location(&state.storage.location(), state.language))
+                .size(12)
+                .style(subtle),
+        )
+        .into()
+}
+
+/// Shown once saving has failed `SaveFailure::PERSISTENT` times in a row, or
+/// the save made for closing the window failed.
+pub fn view_save_failure_banner<'a>(
+    failure: &SaveFailure,
+    language: Language,
+) -> Element<'a, crate::app::Message> {
+    let padding = iced::Padding {
+        top: 5.0,
+        left: 12.0,
+        bottom: 5.0,
+        right: 12.0,
+    };
+
+    let key = if failure.close_blocked {
+        "save-failure-closing"
+    } else {
+        "save-failure-banner"
+    };
+
+    let mut content = row![
+        text(format!("{} {}", translate(key, language), failure.error))
+            .size(14)
+            .width(Fill),
+        button(text(translate("save-retry-now", language)).size(14))
+            .on_press(crate::app::Message::RetrySave)
+            .padding(padding)
+            .style(button::secondary),
+    ]
+    .spacing(10)
+    .align_y(Center);
+
+    if failure.close_blocked {
+        content = content.push(
+            button(text(translate("save-close-anyway", language)).size(14))
+                .on_press(crate::app::Message::CloseWithoutSaving)
+                .padding(padding)
+                .style(button::danger),
+        );
+    }
+
+    container(content)
+        .padding(12)
+        .width(Fill)
+        .style(|theme| {
+            let danger = theme.extended_palette().danger.weak;
+
+            container::Style {
+                background: Some(danger.color.into()),
+                text_color: Some(danger.text),
+                border: iced::Border {
+                    color: danger.color,
+                    width: 1.0,
+                    radius: 8.0.into(),
+                },
+                ..Default::default()
+            }
+        })
+        .into()
+}
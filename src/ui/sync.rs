@@ -0,0 +1,71 @@
+use iced::widget::{button, column, container, row, text, text_input};
+use iced::{Element, Fill};
+
+use crate::app::Message;
+use crate::i18n::translate;
+use crate::state::{State, SyncDialog, SyncStatus};
+use crate::ui::styles::subtle;
+
+const PADDING: iced::Padding = iced::Padding {
+    top: 5.0,
+    left: 12.0,
+    bottom: 5.0,
+    right: 12.0,
+};
+
+/// Connects to a CalDAV collection, syncs with it by hand or disconnects.
+pub fn view_sync_dialog<'a>(dialog: &SyncDialog, state: &State) -> Element<'a, Message> {
+    let language = state.language;
+    let syncing = matches!(state.sync_status, SyncStatus::Syncing);
+
+    let input = |placeholder, value: &str, on_input: fn(String) -> Message| {
+        text_input(&translate(placeholder, language), value)
+            .on_input(on_input)
+            .on_submit(Message::ConnectSync)
+            .size(14)
+            .padding(6)
+    };
+
+    let action = |key, message: Option<Message>, style: fn(&iced::Theme, button::Status) -> button::Style| {
+        button(text(translate(key, language)).size(14))
+            .on_press_maybe(message)
+            .padding(PADDING)
+            .style(style)
+    };
+
+    let mut actions = row![action(
+        "sync-connect",
+        (!syncing).then_some(Message::ConnectSync),
+        button::primary
+    )]
+    .spacing(10);
+    if state.caldav.is_some() {
+        actions = actions
+            .push(action("sync-now", (!syncing).then_some(Message::Sync), button::secondary))
+            .push(action("sync-disconnect", Some(Message::DisconnectSync), button::danger));
+    }
+    actions = actions.push(action("sync-close", Some(Message::SyncDialogToggled), button::text));
+
+    // The error is the server's, so it is not translated
+    let message = match (dialog.error, &state.sync_status) {
+        (Some(key), _) => translate(key, language),
+        (None, SyncStatus::Failed(error)) => format!("{} {}", translate("sync-failed", language), error),
+        (None, _) => translate("sync-hint", language),
+    };
+
+    let content = column![
+        text(translate("sync-title", language)).size(18),
+        input("sync-url-placeholder", &dialog.url, Message::SyncUrlChanged),
+        input("sync-username-placeholder", &dialog.username, Message::SyncUsernameChanged),
+        input("sync-password-placeholder", &dialog.password, Message::SyncPasswordChanged).secure(true),
+        actions,
+        text(message).size(12).style(subtle),
+    ]
+    .spacing(10);
+
+    container(content)
+        .padding(12)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
\ No newline at end of file
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use iced::widget::{button, pick_list, row, text};
+use iced::{Center, Element, Fill};
+
+use crate::app::Message;
+use crate::i18n::{translate, translate_retention_days, Language};
+use crate::state::State;
+use crate::task::Task;
+use crate::ui::styles::subtle;
+
+const RETENTION_DAYS: [Option<u32>; 5] = [Some(7), Some(30), Some(90), Some(365), None];
+
+/// An entry of the retention picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Retention {
+    days: Option<u32>,
+    language: Language,
+}
+
+impl fmt::Display for Retention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.days {
+            Some(days) => f.write_str(&translate_retention_days(days, self.language)),
+            None => f.write_str(&translate("trash-keep-forever", self.language)),
+        }
+    }
+}
+
+/// Shown above the list while the trash is open.
+pub fn view_trash_bar<'a>(state: &State) -> Element<'a, Message> {
+    let language = state.language;
+    let options: Vec<Retention> = RETENTION_DAYS
+        .into_iter()
+        .map(|days| Retention { days, language })
+        .collect();
+    let selected = Retention {
+        days: state.trash_retention_days,
+        language,
+    };
+    let has_trash = state.tasks.iter().any(Task::is_deleted);
+
+    row![
+        text(translate("trash-retention", language))
+            .size(14)
+            .style(subtle)
+            .width(Fill),
+        pick_list(options, Some(selected), |retention| {
+            Message::TrashRetentionChanged(retention.days)
+        })
+        .text_size(14),
+        button(text(translate("trash-empty", language)).size(14))
+            .on_press_maybe(has_trash.then_some(Message::EmptyTrash))
+            .padding(iced::Padding {
+                top: 5.0,
+                left: 12.0,
+                bottom: 5.0,
+                right: 12.0,
+            })
+            .style(button::danger),
+    ]
+    .spacing(10)
+    .align_y(Center)
+    .into()
+}